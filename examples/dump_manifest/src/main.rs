@@ -1,7 +1,7 @@
 use clap::Parser;
 use std::path::PathBuf;
 
-use dress_up::{OperatingHooks, SuitManifest};
+use dress_up::{diagnostic, OperatingHooks, SuitManifest};
 
 #[derive(Parser, Debug)]
 struct Args {
@@ -69,15 +69,25 @@ fn main() -> Result<(), Box<dyn std::error::Error + 'static>> {
 
     let input = std::fs::read(args.file)?;
 
-    let manifest = SuitManifest::from_bytes(&input);
-    let envelope = manifest.envelope()?;
+    println!("Envelope {}", diagnostic(&input));
+    let envelope = SuitManifest::parse(&input)?;
     let auth = envelope.auth_object()?;
-    println!("Auth {:x?}", auth);
+    println!("Auth {}", diagnostic(auth));
+    println!("Manifest {}", diagnostic(envelope.manifest_bytes()?));
     let manifest_obj = envelope.manifest()?;
-    println!("Manifest {:x?}", manifest_obj);
-    let version = manifest_obj.version()?;
-    println!("Manifest version: {}", version);
-    let seq_no = manifest_obj.sequence_number()?;
-    println!("Manifest sequence number: {}", seq_no);
+    let summary = manifest_obj.summary()?;
+    println!("Manifest version: {}", summary.version);
+    println!("Manifest sequence number: {}", summary.sequence_number);
+    println!("Components: {}", summary.components);
+    let sections = [
+        ("common", summary.common),
+        ("install", summary.install),
+        ("fetch", summary.fetch),
+        ("text", summary.text),
+    ];
+    for (name, present) in sections {
+        let present = if present { "present" } else { "absent" };
+        println!("Section {name}: {present}");
+    }
     Ok(())
 }
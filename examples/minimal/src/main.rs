@@ -73,10 +73,19 @@ impl<'a> OperatingHooks for OsHooks<'a> {
         &self,
         _component: &dress_up::component::Component,
         _slot: Option<u64>,
-        _offset: usize,
-        _bytes: &[u8],
+        offset: usize,
+        bytes: &[u8],
     ) -> Result<(), dress_up::error::Error> {
-        todo!()
+        if bytes.len() + offset > self.capacity {
+            return Err(Error::CapacityError);
+        }
+        let mut storage = self.storage.take();
+        if storage.len() < offset + bytes.len() {
+            storage.resize(offset + bytes.len(), 0);
+        }
+        storage[offset..offset + bytes.len()].copy_from_slice(bytes);
+        self.storage.set(storage);
+        Ok(())
     }
 
     fn component_size(
@@ -96,16 +105,19 @@ impl<'a> OperatingHooks for OsHooks<'a> {
         Ok(self.capacity)
     }
 
-    fn fetch(
+    fn component_fetch(
         &self,
         _component: &dress_up::component::Component,
         _slot: Option<u64>,
         _uri: &str,
+        write: &mut impl FnMut(usize, &[u8]) -> Result<(), Error>,
     ) -> Result<(), Error> {
         let mut storage = self.storage.take();
         storage.clear();
-        storage.extend_from_slice(self.payload);
         self.storage.set(storage);
+        for (idx, chunk) in self.payload.chunks(256).enumerate() {
+            write(idx * 256, chunk)?;
+        }
         Ok(())
     }
 }
@@ -0,0 +1,370 @@
+//! Dress Up asynchronous operating hooks.
+//!
+//! Asynchronous counterpart of [`crate::OperatingHooks`] for operating systems with asynchronous
+//! storage and network drivers. Only the hooks performing I/O are asynchronous, the manifest
+//! decoding itself stays synchronous.
+//!
+//! The command sequences are executed against these hooks only, the synchronous hooks are adapted
+//! with [`Blocking`] and driven to completion with [`block_on`].
+use core::future::Future;
+use core::task::{Context, Poll, Waker};
+
+use generic_array::ArrayLength;
+use minicbor::bytes::ByteSlice;
+use uuid::Uuid;
+
+use crate::{
+    component::Component,
+    consts::SuitCommand,
+    error::Error,
+    report::{ReportingPolicy, SuitRecord},
+    OperatingHooks,
+};
+#[cfg(feature = "encryption")]
+use crate::{consts::SuitParameter, encryption::EncryptionInfo};
+
+/// A trait to expose asynchronous operating system functionality to the SUIT manifest parsing
+///
+/// Mirrors [`crate::OperatingHooks`], see there for the documentation of the individual hooks.
+/// The component access, fetch, invoke and dependency resolution hooks are asynchronous.
+#[allow(async_fn_in_trait)]
+pub trait AsyncOperatingHooks {
+    /// The size of the intermediate buffer used during reads and writes with components.
+    type ReadWriteBufferSize: ArrayLength;
+
+    /// Match the vendor ID from the manifest.
+    fn match_vendor_id(&self, uuid: Uuid, component: &Component<'_>) -> Result<bool, Error>;
+
+    /// Match the class ID from the manifest.
+    fn match_class_id(&self, uuid: Uuid, component: &Component<'_>) -> Result<bool, Error>;
+
+    /// Match the device ID from the manifest.
+    fn match_device_id(&self, _uuid: Uuid, _component: &Component<'_>) -> Result<bool, Error> {
+        Err(Error::UnsupportedCommand {
+            command: SuitCommand::DeviceIdentifier.into(),
+        })
+    }
+
+    /// Verify that the component slot index of the supplied component is valid
+    fn match_component_slot(
+        &self,
+        _component: &Component<'_>,
+        _component_slot: u64,
+    ) -> Result<bool, Error> {
+        Err(Error::UnsupportedCommand {
+            command: SuitCommand::ComponentSlot.into(),
+        })
+    }
+
+    /// Current time in seconds since the UNIX epoch.
+    fn current_time(&self) -> Result<u64, Error> {
+        Err(Error::UnsupportedCommand {
+            command: SuitCommand::UseBefore.into(),
+        })
+    }
+
+    /// Read the data from a component (with slot) into the supplied buffer.
+    async fn component_read(
+        &self,
+        component: &Component<'_>,
+        slot: Option<u64>,
+        offset: usize,
+        bytes: &mut [u8],
+    ) -> Result<(), Error>;
+
+    /// Write the supplied data into the component (with slot).
+    async fn component_write(
+        &self,
+        component: &Component<'_>,
+        slot: Option<u64>,
+        offset: usize,
+        bytes: &[u8],
+    ) -> Result<(), Error>;
+
+    /// Get the size of the component installed.
+    async fn component_size(&self, component: &Component<'_>) -> Result<usize, Error>;
+
+    /// Get the capacity of what can be installed in the component.
+    async fn component_capacity(&self, component: &Component<'_>) -> Result<usize, Error>;
+
+    /// Check if the component exists on the system.
+    async fn has_component(&self, component: &Component<'_>) -> Result<(), Error> {
+        self.component_capacity(component).await.map(|_| ())
+    }
+
+    /// Retrieve the payload from the uri and stream it into the component.
+    ///
+    /// The implementation passes each retrieved chunk together with its offset in the payload to
+    /// the `write` callback, which stores the chunk via
+    /// [`AsyncOperatingHooks::component_write`].
+    async fn component_fetch(
+        &self,
+        _component: &Component<'_>,
+        _slot: Option<u64>,
+        _uri: &str,
+        _write: &mut impl AsyncFnMut(usize, &[u8]) -> Result<(), Error>,
+    ) -> Result<(), Error> {
+        Err(Error::UnsupportedCommand {
+            command: SuitCommand::Fetch.into(),
+        })
+    }
+
+    /// Verify the image digest while fetching the payload.
+    fn verify_fetch(&self) -> bool {
+        false
+    }
+
+    /// Erase the content of the component (with slot).
+    async fn component_erase(
+        &self,
+        _component: &Component<'_>,
+        _slot: Option<u64>,
+    ) -> Result<(), Error> {
+        Ok(())
+    }
+
+    /// Invoke the component (with slot).
+    async fn invoke(
+        &self,
+        _component: &Component<'_>,
+        _slot: Option<u64>,
+        _args: Option<&ByteSlice>,
+    ) -> Result<(), Error> {
+        Err(Error::UnsupportedCommand {
+            command: SuitCommand::Invoke.into(),
+        })
+    }
+
+    /// Start the decryption of a payload before the first chunk.
+    #[cfg(feature = "encryption")]
+    fn decrypt_start(&self, _info: &EncryptionInfo) -> Result<(), Error> {
+        Ok(())
+    }
+
+    /// Decrypt a chunk of an encrypted payload.
+    #[cfg(feature = "encryption")]
+    fn decrypt(
+        &self,
+        _info: &EncryptionInfo,
+        _ciphertext: &[u8],
+        _plaintext: &mut [u8],
+    ) -> Result<usize, Error> {
+        Err(Error::UnsupportedParameter {
+            parameter: SuitParameter::EncryptionInfo.into(),
+        })
+    }
+
+    /// Finish the decryption of a payload after the last chunk, verifying the authentication tag.
+    #[cfg(feature = "encryption")]
+    fn decrypt_finish(&self, _info: &EncryptionInfo) -> Result<(), Error> {
+        Err(Error::UnsupportedParameter {
+            parameter: SuitParameter::EncryptionInfo.into(),
+        })
+    }
+
+    /// Resolve the dependency with the supplied index into the SUIT envelope of the dependency.
+    async fn resolve_dependency(
+        &self,
+        _index: u32,
+        _prefix: Option<&Component<'_>>,
+    ) -> Result<&[u8], Error> {
+        Err(Error::UnsupportedCommand {
+            command: SuitCommand::SetDependencyIndex.into(),
+        })
+    }
+
+    /// Authenticate a dependent manifest.
+    ///
+    /// See [`crate::OperatingHooks::authenticate_dependency`].
+    fn authenticate_dependency(&self, _cose: &[u8], _payload: &[u8]) -> Result<bool, Error> {
+        Ok(false)
+    }
+
+    /// Match a component against a component identifier from a set component index list.
+    fn match_component_identifier(
+        &self,
+        component: &Component<'_>,
+        candidate: &Component<'_>,
+    ) -> Result<bool, Error> {
+        component.identifier_eq(candidate)
+    }
+
+    /// Receive the reporting policy of a command before it is executed.
+    fn reporting_policy(
+        &self,
+        _command: SuitCommand,
+        _component: &Component<'_>,
+        _policy: ReportingPolicy,
+    ) {
+    }
+
+    /// Emit the record of a completed command.
+    fn emit_record(&self, _record: &SuitRecord) {}
+}
+
+/// Poll the future to completion without an executor.
+///
+/// Meant for futures of the [`Blocking`] hooks, which are ready on the first poll. Any other
+/// future is polled in a busy loop.
+pub(crate) fn block_on<F: Future>(future: F) -> F::Output {
+    let mut future = core::pin::pin!(future);
+    let mut context = Context::from_waker(Waker::noop());
+    loop {
+        if let Poll::Ready(output) = future.as_mut().poll(&mut context) {
+            return output;
+        }
+    }
+}
+
+/// Synchronous operating hooks as asynchronous operating hooks.
+///
+/// Every hook completes before its future is first polled.
+pub(crate) struct Blocking<'a, O: OperatingHooks>(pub(crate) &'a O);
+
+impl<O: OperatingHooks> AsyncOperatingHooks for Blocking<'_, O> {
+    type ReadWriteBufferSize = O::ReadWriteBufferSize;
+
+    fn match_vendor_id(&self, uuid: Uuid, component: &Component<'_>) -> Result<bool, Error> {
+        self.0.match_vendor_id(uuid, component)
+    }
+
+    fn match_class_id(&self, uuid: Uuid, component: &Component<'_>) -> Result<bool, Error> {
+        self.0.match_class_id(uuid, component)
+    }
+
+    fn match_device_id(&self, uuid: Uuid, component: &Component<'_>) -> Result<bool, Error> {
+        self.0.match_device_id(uuid, component)
+    }
+
+    fn match_component_slot(
+        &self,
+        component: &Component<'_>,
+        component_slot: u64,
+    ) -> Result<bool, Error> {
+        self.0.match_component_slot(component, component_slot)
+    }
+
+    fn current_time(&self) -> Result<u64, Error> {
+        self.0.current_time()
+    }
+
+    async fn component_read(
+        &self,
+        component: &Component<'_>,
+        slot: Option<u64>,
+        offset: usize,
+        bytes: &mut [u8],
+    ) -> Result<(), Error> {
+        self.0.component_read(component, slot, offset, bytes)
+    }
+
+    async fn component_write(
+        &self,
+        component: &Component<'_>,
+        slot: Option<u64>,
+        offset: usize,
+        bytes: &[u8],
+    ) -> Result<(), Error> {
+        self.0.component_write(component, slot, offset, bytes)
+    }
+
+    async fn component_size(&self, component: &Component<'_>) -> Result<usize, Error> {
+        self.0.component_size(component)
+    }
+
+    async fn component_capacity(&self, component: &Component<'_>) -> Result<usize, Error> {
+        self.0.component_capacity(component)
+    }
+
+    async fn has_component(&self, component: &Component<'_>) -> Result<(), Error> {
+        self.0.has_component(component)
+    }
+
+    async fn component_fetch(
+        &self,
+        component: &Component<'_>,
+        slot: Option<u64>,
+        uri: &str,
+        write: &mut impl AsyncFnMut(usize, &[u8]) -> Result<(), Error>,
+    ) -> Result<(), Error> {
+        // The executor writes through the blocking hooks, the chunk is written before returning
+        self.0
+            .component_fetch(component, slot, uri, &mut |offset, bytes| {
+                block_on(write(offset, bytes))
+            })
+    }
+
+    fn verify_fetch(&self) -> bool {
+        self.0.verify_fetch()
+    }
+
+    async fn component_erase(
+        &self,
+        component: &Component<'_>,
+        slot: Option<u64>,
+    ) -> Result<(), Error> {
+        self.0.component_erase(component, slot)
+    }
+
+    async fn invoke(
+        &self,
+        component: &Component<'_>,
+        slot: Option<u64>,
+        args: Option<&ByteSlice>,
+    ) -> Result<(), Error> {
+        self.0.invoke(component, slot, args)
+    }
+
+    #[cfg(feature = "encryption")]
+    fn decrypt_start(&self, info: &EncryptionInfo) -> Result<(), Error> {
+        self.0.decrypt_start(info)
+    }
+
+    #[cfg(feature = "encryption")]
+    fn decrypt(
+        &self,
+        info: &EncryptionInfo,
+        ciphertext: &[u8],
+        plaintext: &mut [u8],
+    ) -> Result<usize, Error> {
+        self.0.decrypt(info, ciphertext, plaintext)
+    }
+
+    #[cfg(feature = "encryption")]
+    fn decrypt_finish(&self, info: &EncryptionInfo) -> Result<(), Error> {
+        self.0.decrypt_finish(info)
+    }
+
+    async fn resolve_dependency(
+        &self,
+        index: u32,
+        prefix: Option<&Component<'_>>,
+    ) -> Result<&[u8], Error> {
+        self.0.resolve_dependency(index, prefix)
+    }
+
+    fn authenticate_dependency(&self, cose: &[u8], payload: &[u8]) -> Result<bool, Error> {
+        self.0.authenticate_dependency(cose, payload)
+    }
+
+    fn match_component_identifier(
+        &self,
+        component: &Component<'_>,
+        candidate: &Component<'_>,
+    ) -> Result<bool, Error> {
+        self.0.match_component_identifier(component, candidate)
+    }
+
+    fn reporting_policy(
+        &self,
+        command: SuitCommand,
+        component: &Component<'_>,
+        policy: ReportingPolicy,
+    ) {
+        self.0.reporting_policy(command, component, policy)
+    }
+
+    fn emit_record(&self, record: &SuitRecord) {
+        self.0.emit_record(record)
+    }
+}
@@ -3,9 +3,14 @@
 //! Packed in the manifest as bstr wrapper array.
 //! Contains the digest of the manifest, and a set of authentication blocks.
 
-use minicbor::{bytes::ByteSlice, Decoder};
+use minicbor::{bytes::ByteSlice, data::Type, Decoder};
 
-use crate::{digest::SuitDigest, error::Error};
+use crate::{
+    cose::{self, CoseHeaders, CoseSignature},
+    digest::SuitDigest,
+    error::Error,
+    OperatingHooks,
+};
 use digest::Update;
 
 /// Authentication structure in a SUIT manifest
@@ -17,25 +22,70 @@ pub(crate) struct Authentication<'a> {
 }
 
 impl<'a> Authentication<'a> {
-    pub(crate) fn new(authentication: &'a ByteSlice, manifest: &ByteSlice) -> Result<Self, Error> {
+    /// Check the shape of the authentication structure without verifying anything.
+    ///
+    /// The structure must be an array of a bstr wrapped SUIT digest followed by at least one bstr
+    /// wrapped COSE structure. Malformed elements are rejected with
+    /// [`Error::MalformedAuthBlock`].
+    pub(crate) fn check_structure(authentication: &ByteSlice) -> Result<(), Error> {
         let mut decoder = Decoder::new(authentication);
         let len = decoder.array()?;
         let len = len.ok_or(Error::UnexpectedIndefiniteLength {
             position: decoder.position(),
         })?;
-
-        // Structure must contain at least one suit_digest and one COSE auth
         if len < 2 {
             return Err(Error::InvalidAuthenticationStructure);
         }
-        let digest = decoder.bytes()?;
-        let mut digest_decoder = Decoder::new(digest);
-        let suit_digest = digest_decoder.decode::<SuitDigest>()?;
+        for index in 0..len as usize {
+            let malformed = Error::MalformedAuthBlock { index };
+            let block = decoder.bytes().map_err(|_| malformed)?;
+            let mut block_decoder = Decoder::new(block);
+            let valid = if index == 0 {
+                SuitDigest::from_cbor(&mut block_decoder).is_ok()
+            } else {
+                Self::is_cose_structure(&mut block_decoder)
+            };
+            // The wrapped element is a single CBOR item
+            if !valid || block_decoder.position() != block.len() {
+                return Err(malformed);
+            }
+        }
+        Ok(())
+    }
+
+    /// Whether the decoder holds an, optionally tagged, COSE structure.
+    fn is_cose_structure(decoder: &mut Decoder) -> bool {
+        if decoder.datatype().is_ok_and(|t| t == Type::Tag) && decoder.tag().is_err() {
+            return false;
+        }
+        decoder.datatype().is_ok_and(|t| t == Type::Array) && decoder.skip().is_ok()
+    }
+
+    pub(crate) fn new(authentication: &'a ByteSlice, manifest: &ByteSlice) -> Result<Self, Error> {
+        let auth = Self::decode(authentication)?;
+        let suit_digest = auth.manifest_digest()?;
         let mut hasher = suit_digest.hasher()?;
         hasher.update(manifest);
         if !suit_digest.match_hasher(hasher)? {
             return Err(Error::AuthenticationFailure);
         }
+        Ok(auth)
+    }
+
+    /// Decode the authentication structure without verifying the manifest digest.
+    pub(crate) fn decode(authentication: &'a ByteSlice) -> Result<Self, Error> {
+        Self::check_structure(authentication)?;
+        let mut decoder = Decoder::new(authentication);
+        let len = decoder.array()?;
+        let len = len.ok_or(Error::UnexpectedIndefiniteLength {
+            position: decoder.position(),
+        })?;
+
+        // Structure must contain at least one suit_digest and one COSE auth
+        if len < 2 {
+            return Err(Error::InvalidAuthenticationStructure);
+        }
+        let digest = decoder.bytes()?;
         Ok(Self {
             digest: digest.into(),
             decoder,
@@ -43,36 +93,186 @@ impl<'a> Authentication<'a> {
         })
     }
 
+    /// The digest of the manifest from the first element of the structure.
+    pub(crate) fn manifest_digest(&self) -> Result<SuitDigest<'a>, Error> {
+        SuitDigest::from_cbor(&mut Decoder::new(self.digest))
+    }
+
+    /// Iterate over the COSE structures of the authentication blocks.
+    pub(crate) fn blocks(&self) -> impl Iterator<Item = Result<&'a [u8], Error>> + 'a {
+        let mut decoder = self.decoder.clone();
+        (0..self.num_auth).map(move |_| Ok(decoder.bytes()?))
+    }
+
     pub(crate) fn authenticate<F>(&self, authenticate: F) -> Result<(), Error>
     where
         F: Fn(&[u8], &[u8]) -> Result<bool, Error>,
     {
-        let mut decoder = self.decoder.clone();
-        for _ in 0..self.num_auth {
-            let auth_block = decoder.bytes()?;
-            let res = authenticate(auth_block, self.digest)?;
+        for auth_block in self.blocks() {
+            let res = authenticate(auth_block?, self.digest)?;
             if res {
                 return Ok(());
             }
         }
         Err(Error::AuthenticationFailure)
     }
+
+    /// Authenticate with the public key selected by the key identifier of each block.
+    ///
+    /// Blocks without key identifier or without matching key are skipped.
+    pub(crate) fn authenticate_with_key<F>(
+        &self,
+        os_hooks: &impl OperatingHooks,
+        authenticate: F,
+    ) -> Result<(), Error>
+    where
+        F: Fn(&[u8], &[u8], &[u8], Option<usize>) -> Result<bool, Error>,
+    {
+        self.authenticate(|cose, payload| {
+            for signer in Self::block_signers(Ok(cose)) {
+                if Self::verify_with_key(os_hooks, &signer?, payload, &authenticate)? {
+                    return Ok(true);
+                }
+            }
+            Ok(false)
+        })
+    }
+
+    /// Authenticate with at least `required` distinct keys.
+    ///
+    /// Keys are selected as with [`Self::authenticate_with_key`], the signers of a COSE_Sign
+    /// block count like separate blocks. Every key identifier counts once, no matter how many of
+    /// its signatures verify. A `required` of zero never succeeds.
+    pub(crate) fn authenticate_threshold<F>(
+        &self,
+        os_hooks: &impl OperatingHooks,
+        required: usize,
+        authenticate: F,
+    ) -> Result<(), Error>
+    where
+        F: Fn(&[u8], &[u8], &[u8], Option<usize>) -> Result<bool, Error>,
+    {
+        let mut verified = 0;
+        for (index, signer) in self.signers().enumerate() {
+            let Some(kid) = signer?.kid else {
+                continue;
+            };
+            // The first signer with a key identifier accounts for all signers with it
+            let seen = self
+                .signers()
+                .take(index)
+                .any(|signer| signer.is_ok_and(|signer| signer.kid == Some(kid)));
+            if seen {
+                continue;
+            }
+            for signer in self.signers().skip(index) {
+                let signer = signer?;
+                if signer.kid == Some(kid)
+                    && Self::verify_with_key(os_hooks, &signer, self.digest, &authenticate)?
+                {
+                    verified += 1;
+                    break;
+                }
+            }
+            if required > 0 && verified >= required {
+                return Ok(());
+            }
+        }
+        Err(Error::AuthenticationFailure)
+    }
+
+    /// Iterate over the signers of all authentication blocks.
+    fn signers(&self) -> impl Iterator<Item = Result<Signer<'a>, Error>> + 'a {
+        self.blocks().flat_map(Self::block_signers)
+    }
+
+    /// Iterate over the signers of an authentication block.
+    ///
+    /// A COSE_Sign1 block is its own single signer, a COSE_Sign block has a signer for every
+    /// COSE_Signature.
+    fn block_signers<'b>(
+        block: Result<&'b [u8], Error>,
+    ) -> impl Iterator<Item = Result<Signer<'b>, Error>> + 'b {
+        let (single, multiple) =
+            match block.and_then(|cose| Ok((cose, CoseSignature::detect(cose)?))) {
+                Ok((cose, CoseSignature::Sign1)) => {
+                    let signer = CoseHeaders::decode_message(cose).map(|headers| Signer {
+                        cose,
+                        index: None,
+                        kid: headers.kid(),
+                    });
+                    (Some(signer), None)
+                }
+                Ok((cose, CoseSignature::Sign)) => match cose::signers(cose) {
+                    Ok(signers) => {
+                        let signers = signers.enumerate().map(move |(index, headers)| {
+                            headers.map(|headers| Signer {
+                                cose,
+                                index: Some(index),
+                                kid: headers.kid(),
+                            })
+                        });
+                        (None, Some(signers))
+                    }
+                    Err(e) => (Some(Err(e)), None),
+                },
+                Err(e) => (Some(Err(e)), None),
+            };
+        single.into_iter().chain(multiple.into_iter().flatten())
+    }
+
+    /// Verify the signature of a signer with the public key for its key identifier.
+    ///
+    /// The closure is called with the key, the full COSE structure, the payload and the index of
+    /// the signer in a COSE_Sign structure. Signers without key identifier or without matching
+    /// key do not verify.
+    fn verify_with_key<F>(
+        os_hooks: &impl OperatingHooks,
+        signer: &Signer,
+        payload: &[u8],
+        authenticate: &F,
+    ) -> Result<bool, Error>
+    where
+        F: Fn(&[u8], &[u8], &[u8], Option<usize>) -> Result<bool, Error>,
+    {
+        let Some(kid) = signer.kid else {
+            return Ok(false);
+        };
+        match os_hooks.public_key_for_kid(kid) {
+            Ok(key) => authenticate(key, signer.cose, payload, signer.index),
+            Err(Error::AuthenticationFailure) => Ok(false),
+            Err(e) => Err(e),
+        }
+    }
+}
+
+/// A signature of an authentication block.
+struct Signer<'a> {
+    /// The COSE structure holding the signature.
+    cose: &'a [u8],
+    /// Index of the COSE_Signature in a COSE_Sign structure, none for COSE_Sign1.
+    index: Option<usize>,
+    /// Key identifier of the signature.
+    kid: Option<&'a [u8]>,
 }
 
 #[cfg(test)]
 #[allow(clippy::unwrap_used)]
 mod tests {
     use super::*;
+    use crate::testhooks::NullHooks;
     extern crate std;
 
     #[test]
     fn auth_decode() {
         // Very dummy manifest
         let manifest: &[u8] = &std::vec![];
+        // [digest of the empty manifest, << [h'', {}, null, h''] >>]
         let input: &[u8] = &std::vec![
             0x82, 0x58, 0x24, 0x82, 0x2F, 0x58, 0x20, 0xE3, 0xB0, 0xC4, 0x42, 0x98, 0xFC, 0x1C,
             0x14, 0x9A, 0xFB, 0xF4, 0xC8, 0x99, 0x6F, 0xB9, 0x24, 0x27, 0xAE, 0x41, 0xE4, 0x64,
-            0x9B, 0x93, 0x4C, 0xA4, 0x95, 0x99, 0x1B, 0x78, 0x52, 0xB8, 0x55, 0x40,
+            0x9B, 0x93, 0x4C, 0xA4, 0x95, 0x99, 0x1B, 0x78, 0x52, 0xB8, 0x55, 0x45, 0x84, 0x40,
+            0xA0, 0xF6, 0x40,
         ];
 
         let auth = Authentication::new(input.into(), manifest.into()).unwrap();
@@ -84,4 +284,173 @@ mod tests {
         let res = auth.authenticate(|_cose, _payload| Ok(false));
         assert_eq!(res, Err(Error::AuthenticationFailure));
     }
+
+    /// Authentication structure with a single block wrapping `cose`.
+    fn auth_with_block(cose: &[u8]) -> std::vec::Vec<u8> {
+        let mut input = auth_with_kid(0x01);
+        input.truncate(39);
+        input.push(0x40 + cose.len() as u8);
+        input.extend_from_slice(cose);
+        input
+    }
+
+    #[test]
+    fn auth_cose_sign() {
+        let manifest: &[u8] = &std::vec![];
+        // 98([h'', {}, null, [[h'', {4: h'03'}, h''], [h'', {4: h'01'}, h'']]])
+        let input = auth_with_block(&[
+            0xD8, 0x62, 0x84, 0x40, 0xA0, 0xF6, 0x82, 0x83, 0x40, 0xA1, 0x04, 0x41, 0x03, 0x40,
+            0x83, 0x40, 0xA1, 0x04, 0x41, 0x01, 0x40,
+        ]);
+        let auth = Authentication::new(input.as_slice().into(), manifest.into()).unwrap();
+        let res =
+            auth.authenticate_with_key(&NullHooks::default(), |key, _cose, _payload, signer| {
+                // The key belongs to the second signer
+                Ok(key == b"key one" && signer == Some(1))
+            });
+        assert_eq!(res, Ok(()));
+        let res = auth
+            .authenticate_with_key(&NullHooks::default(), |key, _cose, _payload, _signer| {
+                Ok(key == b"key two")
+            });
+        assert_eq!(res, Err(Error::AuthenticationFailure));
+
+        // 17([h'', {4: h'01'}, null, h'']), a COSE_Mac0
+        let input = auth_with_block(&[0xD1, 0x84, 0x40, 0xA1, 0x04, 0x41, 0x01, 0xF6, 0x40]);
+        let auth = Authentication::new(input.as_slice().into(), manifest.into()).unwrap();
+        let res = auth
+            .authenticate_with_key(&NullHooks::default(), |_key, _cose, _payload, _signer| {
+                Ok(true)
+            });
+        assert_eq!(res, Err(Error::UnsupportedCoseStructure));
+    }
+
+    #[test]
+    fn auth_threshold() {
+        let manifest: &[u8] = &std::vec![];
+        let mut input = auth_with_kid(0x01);
+        for kid in [0x01, 0x02, 0x03] {
+            input.extend_from_slice(&auth_with_kid(kid)[39..]);
+        }
+        input[0] = 0x85;
+        let auth = Authentication::new(input.as_slice().into(), manifest.into()).unwrap();
+        assert_eq!(auth.blocks().count(), 4);
+
+        // Both provisioned keys verify, the repeated key identifier counts once
+        let verify = |_key: &[u8], _cose: &[u8], _payload: &[u8], _signer| Ok(true);
+        assert_eq!(
+            auth.authenticate_threshold(&NullHooks::default(), 2, verify),
+            Ok(())
+        );
+        assert_eq!(
+            auth.authenticate_threshold(&NullHooks::default(), 3, verify),
+            Err(Error::AuthenticationFailure)
+        );
+        assert_eq!(
+            auth.authenticate_threshold(&NullHooks::default(), 0, verify),
+            Err(Error::AuthenticationFailure)
+        );
+
+        // Only the second key verifies
+        let verify = |key: &[u8], _cose: &[u8], _payload: &[u8], _signer| Ok(key == b"key two");
+        assert_eq!(
+            auth.authenticate_threshold(&NullHooks::default(), 1, verify),
+            Ok(())
+        );
+        assert_eq!(
+            auth.authenticate_threshold(&NullHooks::default(), 2, verify),
+            Err(Error::AuthenticationFailure)
+        );
+
+        // Every signer of a single COSE_Sign block counts
+        // 98([h'', {}, null, [[h'', {4: h'01'}, h''], [h'', {4: h'02'}, h'']]])
+        let input = auth_with_block(&[
+            0xD8, 0x62, 0x84, 0x40, 0xA0, 0xF6, 0x82, 0x83, 0x40, 0xA1, 0x04, 0x41, 0x01, 0x40,
+            0x83, 0x40, 0xA1, 0x04, 0x41, 0x02, 0x40,
+        ]);
+        let auth = Authentication::new(input.as_slice().into(), manifest.into()).unwrap();
+        let verify = |key: &[u8], _cose: &[u8], _payload: &[u8], signer: Option<usize>| {
+            Ok(signer == Some(usize::from(key == b"key two")))
+        };
+        assert_eq!(
+            auth.authenticate_threshold(&NullHooks::default(), 2, verify),
+            Ok(())
+        );
+        assert_eq!(
+            auth.authenticate_threshold(&NullHooks::default(), 3, verify),
+            Err(Error::AuthenticationFailure)
+        );
+    }
+
+    #[test]
+    fn malformed_auth_block() {
+        let mut input = auth_with_kid(0x01);
+        assert_eq!(
+            Authentication::check_structure(input.as_slice().into()),
+            Ok(())
+        );
+
+        // Auth block wrapping a map instead of a COSE structure
+        input.truncate(39);
+        input.extend_from_slice(&[0x41, 0xA0]);
+        assert_eq!(
+            Authentication::check_structure(input.as_slice().into()),
+            Err(Error::MalformedAuthBlock { index: 1 })
+        );
+
+        // Trailing bytes after the digest
+        let input: &[u8] = &std::vec![0x82, 0x44, 0x82, 0x2F, 0x40, 0x00, 0x42, 0x80, 0x80];
+        assert_eq!(
+            Authentication::check_structure(input.into()),
+            Err(Error::MalformedAuthBlock { index: 0 })
+        );
+
+        // Auth block that is not a bstr
+        let input: &[u8] = &std::vec![0x82, 0x43, 0x82, 0x2F, 0x40, 0x80];
+        assert_eq!(
+            Authentication::check_structure(input.into()),
+            Err(Error::MalformedAuthBlock { index: 1 })
+        );
+
+        // Digest without an auth block
+        let input: &[u8] = &std::vec![0x81, 0x43, 0x82, 0x2F, 0x40];
+        assert_eq!(
+            Authentication::check_structure(input.into()),
+            Err(Error::InvalidAuthenticationStructure)
+        );
+    }
+
+    fn auth_with_kid(kid: u8) -> std::vec::Vec<u8> {
+        // [digest of the empty manifest, << 18([<< {1: -7} >>, {4: h'kid'}, null, h'']) >>]
+        let mut input = std::vec![
+            0x82, 0x58, 0x24, 0x82, 0x2F, 0x58, 0x20, 0xE3, 0xB0, 0xC4, 0x42, 0x98, 0xFC, 0x1C,
+            0x14, 0x9A, 0xFB, 0xF4, 0xC8, 0x99, 0x6F, 0xB9, 0x24, 0x27, 0xAE, 0x41, 0xE4, 0x64,
+            0x9B, 0x93, 0x4C, 0xA4, 0x95, 0x99, 0x1B, 0x78, 0x52, 0xB8, 0x55,
+        ];
+        input.extend_from_slice(&[
+            0x4C, 0xD2, 0x84, 0x43, 0xA1, 0x01, 0x26, 0xA1, 0x04, 0x41, kid, 0xF6, 0x40,
+        ]);
+        input
+    }
+
+    #[test]
+    fn auth_with_key() {
+        let manifest: &[u8] = &std::vec![];
+        let input = auth_with_kid(0x02);
+        let auth = Authentication::new(input.as_slice().into(), manifest.into()).unwrap();
+        let res = auth
+            .authenticate_with_key(&NullHooks::default(), |key, _cose, _payload, _signer| {
+                Ok(key == b"key two")
+            });
+        assert_eq!(res, Ok(()));
+
+        // No provisioned key for the key identifier
+        let input = auth_with_kid(0x03);
+        let auth = Authentication::new(input.as_slice().into(), manifest.into()).unwrap();
+        let res = auth
+            .authenticate_with_key(&NullHooks::default(), |_key, _cose, _payload, _signer| {
+                Ok(true)
+            });
+        assert_eq!(res, Err(Error::AuthenticationFailure));
+    }
 }
@@ -0,0 +1,481 @@
+//! SUIT manifest encoding.
+//!
+//! Produces canonical CBOR-encoded SUIT manifests. Meant for tooling and for writing tests, the
+//! builder only covers the manifest elements that Dress‑Up processes itself.
+use minicbor::bytes::ByteSlice;
+use minicbor::encode::write::{Cursor, Write};
+use minicbor::{CborLen, Encode, Encoder};
+use uuid::Uuid;
+
+use crate::component::Component;
+use crate::consts::{Manifest, SuitCommand, SuitCommon, SuitParameter, SUIT_SUPPORTED_VERSION};
+use crate::digest::{SuitDigest, SuitDigestAlgorithm};
+use crate::error::Error;
+use crate::report::ReportingPolicy;
+
+/// Encode `value` into `buf`, returning the number of bytes written.
+fn encode_into(value: &impl Encode<()>, buf: &mut [u8]) -> Result<usize, Error> {
+    let mut cursor = Cursor::new(buf);
+    minicbor::encode(value, &mut cursor).map_err(|_| Error::CapacityError)?;
+    Ok(cursor.position())
+}
+
+/// Encode `value` as byte string wrapped CBOR.
+fn encode_wrapped<C, W: Write>(
+    value: &(impl Encode<C> + CborLen<C>),
+    e: &mut Encoder<W>,
+    ctx: &mut C,
+) -> Result<(), minicbor::encode::Error<W::Error>> {
+    let len = value.cbor_len(ctx);
+    e.bytes_len(len as u64)?;
+    value.encode(e, ctx)
+}
+
+/// Length of `value` when encoded as byte string wrapped CBOR.
+fn wrapped_len<C>(value: &impl CborLen<C>, ctx: &mut C) -> usize {
+    let len = value.cbor_len(ctx);
+    (len as u64).cbor_len(ctx) + len
+}
+
+/// A SUIT parameter with its value.
+#[derive(Copy, Clone, Debug)]
+pub enum Parameter<'a> {
+    /// Vendor identifier, see [`SuitParameter::VendorId`].
+    VendorId(Uuid),
+    /// Class identifier, see [`SuitParameter::ClassId`].
+    ClassId(Uuid),
+    /// Image digest, see [`SuitParameter::ImageDigest`].
+    ImageDigest {
+        /// The digest algorithm.
+        algorithm: SuitDigestAlgorithm,
+        /// The digest bytes.
+        digest: &'a [u8],
+    },
+    /// Component slot, see [`SuitParameter::ComponentSlot`].
+    ComponentSlot(u64),
+    /// Image size, see [`SuitParameter::ImageSize`].
+    ImageSize(u64),
+    /// Direct content, see [`SuitParameter::Content`].
+    Content(&'a [u8]),
+    /// Fetch URI, see [`SuitParameter::Uri`].
+    Uri(&'a str),
+}
+
+impl Parameter<'_> {
+    fn key(&self) -> SuitParameter {
+        match self {
+            Parameter::VendorId(_) => SuitParameter::VendorId,
+            Parameter::ClassId(_) => SuitParameter::ClassId,
+            Parameter::ImageDigest { .. } => SuitParameter::ImageDigest,
+            Parameter::ComponentSlot(_) => SuitParameter::ComponentSlot,
+            Parameter::ImageSize(_) => SuitParameter::ImageSize,
+            Parameter::Content(_) => SuitParameter::Content,
+            Parameter::Uri(_) => SuitParameter::Uri,
+        }
+    }
+}
+
+impl<C> Encode<C> for Parameter<'_> {
+    fn encode<W: Write>(
+        &self,
+        e: &mut Encoder<W>,
+        ctx: &mut C,
+    ) -> Result<(), minicbor::encode::Error<W::Error>> {
+        let key: i32 = self.key().into();
+        e.i32(key)?;
+        match self {
+            Parameter::VendorId(uuid) | Parameter::ClassId(uuid) => e.bytes(uuid.as_bytes())?,
+            Parameter::ImageDigest { algorithm, digest } => {
+                let digest = SuitDigest::new(*algorithm, (*digest).into());
+                encode_wrapped(&digest, e, ctx)?;
+                e
+            }
+            Parameter::ComponentSlot(n) | Parameter::ImageSize(n) => e.u64(*n)?,
+            Parameter::Content(content) => e.bytes(content)?,
+            Parameter::Uri(uri) => e.str(uri)?,
+        };
+        Ok(())
+    }
+}
+
+impl<C> CborLen<C> for Parameter<'_> {
+    fn cbor_len(&self, ctx: &mut C) -> usize {
+        let key: i32 = self.key().into();
+        let value = match self {
+            Parameter::VendorId(uuid) | Parameter::ClassId(uuid) => {
+                <&ByteSlice>::from(&uuid.as_bytes()[..]).cbor_len(ctx)
+            }
+            Parameter::ImageDigest { algorithm, digest } => {
+                wrapped_len(&SuitDigest::new(*algorithm, (*digest).into()), ctx)
+            }
+            Parameter::ComponentSlot(n) | Parameter::ImageSize(n) => n.cbor_len(ctx),
+            Parameter::Content(content) => <&ByteSlice>::from(*content).cbor_len(ctx),
+            Parameter::Uri(uri) => uri.cbor_len(ctx),
+        };
+        key.cbor_len(ctx) + value
+    }
+}
+
+/// A single command in a command sequence.
+#[derive(Copy, Clone, Debug)]
+pub enum SequenceCommand<'a> {
+    /// Condition or directive taking a reporting policy as argument.
+    Report(SuitCommand, ReportingPolicy),
+    /// Override the parameters, see [`SuitCommand::OverrideParameters`].
+    OverrideParameters(&'a [Parameter<'a>]),
+    /// Select a component by index, see [`SuitCommand::SetComponentIndex`].
+    SetComponentIndex(u32),
+}
+
+impl SequenceCommand<'_> {
+    fn command(&self) -> SuitCommand {
+        match self {
+            SequenceCommand::Report(command, _) => *command,
+            SequenceCommand::OverrideParameters(_) => SuitCommand::OverrideParameters,
+            SequenceCommand::SetComponentIndex(_) => SuitCommand::SetComponentIndex,
+        }
+    }
+}
+
+impl<C> Encode<C> for SequenceCommand<'_> {
+    fn encode<W: Write>(
+        &self,
+        e: &mut Encoder<W>,
+        ctx: &mut C,
+    ) -> Result<(), minicbor::encode::Error<W::Error>> {
+        let command: i32 = self.command().into();
+        e.i32(command)?;
+        match self {
+            SequenceCommand::Report(_, policy) => policy.encode(e, ctx)?,
+            SequenceCommand::OverrideParameters(parameters) => {
+                e.map(parameters.len() as u64)?;
+                for parameter in parameters.iter() {
+                    parameter.encode(e, ctx)?;
+                }
+            }
+            SequenceCommand::SetComponentIndex(index) => {
+                e.u32(*index)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl<C> CborLen<C> for SequenceCommand<'_> {
+    fn cbor_len(&self, ctx: &mut C) -> usize {
+        let command: i32 = self.command().into();
+        let argument = match self {
+            SequenceCommand::Report(_, policy) => policy.cbor_len(ctx),
+            SequenceCommand::OverrideParameters(parameters) => {
+                (parameters.len() as u64).cbor_len(ctx)
+                    + parameters.iter().map(|p| p.cbor_len(ctx)).sum::<usize>()
+            }
+            SequenceCommand::SetComponentIndex(index) => index.cbor_len(ctx),
+        };
+        command.cbor_len(ctx) + argument
+    }
+}
+
+/// Builder for a SUIT command sequence.
+#[derive(Copy, Clone, Debug)]
+pub struct CommandSequenceBuilder<'a> {
+    commands: &'a [SequenceCommand<'a>],
+}
+
+impl<'a> CommandSequenceBuilder<'a> {
+    /// Create a command sequence from the commands.
+    pub fn new(commands: &'a [SequenceCommand<'a>]) -> Self {
+        Self { commands }
+    }
+
+    /// Encode the command sequence into the buffer, returning the number of bytes written.
+    pub fn encode_into(&self, buf: &mut [u8]) -> Result<usize, Error> {
+        encode_into(self, buf)
+    }
+}
+
+impl<C> Encode<C> for CommandSequenceBuilder<'_> {
+    fn encode<W: Write>(
+        &self,
+        e: &mut Encoder<W>,
+        ctx: &mut C,
+    ) -> Result<(), minicbor::encode::Error<W::Error>> {
+        // Each command consists of the command and its argument
+        e.array(2 * self.commands.len() as u64)?;
+        for command in self.commands {
+            command.encode(e, ctx)?;
+        }
+        Ok(())
+    }
+}
+
+impl<C> CborLen<C> for CommandSequenceBuilder<'_> {
+    fn cbor_len(&self, ctx: &mut C) -> usize {
+        (2 * self.commands.len() as u64).cbor_len(ctx)
+            + self.commands.iter().map(|c| c.cbor_len(ctx)).sum::<usize>()
+    }
+}
+
+/// Common section of the manifest.
+struct CommonBuilder<'a> {
+    components: &'a [Component<'a>],
+    sequence: Option<CommandSequenceBuilder<'a>>,
+}
+
+impl<C> Encode<C> for CommonBuilder<'_> {
+    fn encode<W: Write>(
+        &self,
+        e: &mut Encoder<W>,
+        ctx: &mut C,
+    ) -> Result<(), minicbor::encode::Error<W::Error>> {
+        e.map(if self.sequence.is_some() { 2 } else { 1 })?;
+        e.i16(SuitCommon::ComponentIdentifiers.into())?;
+        self.components.encode(e, ctx)?;
+        if let Some(sequence) = &self.sequence {
+            e.i16(SuitCommon::CommonCommandSequence.into())?;
+            encode_wrapped(sequence, e, ctx)?;
+        }
+        Ok(())
+    }
+}
+
+impl<C> CborLen<C> for CommonBuilder<'_> {
+    fn cbor_len(&self, ctx: &mut C) -> usize {
+        let components = 1 + self.components.cbor_len(ctx);
+        let sequence = self
+            .sequence
+            .as_ref()
+            .map(|s| 1 + wrapped_len(s, ctx))
+            .unwrap_or(0);
+        1 + components + sequence
+    }
+}
+
+/// Builder for a SUIT manifest.
+///
+/// The manifest is encoded with the common section containing the components and the optional
+/// common command sequence, followed by the optional encoded sections.
+#[derive(Copy, Clone, Debug)]
+pub struct ManifestBuilder<'a> {
+    version: u8,
+    sequence_number: u64,
+    components: &'a [Component<'a>],
+    common_sequence: Option<CommandSequenceBuilder<'a>>,
+    sections: &'a [(Manifest, &'a [u8])],
+}
+
+impl Default for ManifestBuilder<'_> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<'a> ManifestBuilder<'a> {
+    /// Create a manifest builder with the supported encoding version and sequence number 0.
+    pub fn new() -> Self {
+        Self {
+            version: SUIT_SUPPORTED_VERSION,
+            sequence_number: 0,
+            components: &[],
+            common_sequence: None,
+            sections: &[],
+        }
+    }
+
+    /// Set the manifest encoding version.
+    pub fn version(mut self, version: u8) -> Self {
+        self.version = version;
+        self
+    }
+
+    /// Set the manifest sequence number.
+    pub fn sequence_number(mut self, sequence_number: u64) -> Self {
+        self.sequence_number = sequence_number;
+        self
+    }
+
+    /// Set the components affected by the manifest.
+    pub fn components(mut self, components: &'a [Component<'a>]) -> Self {
+        self.components = components;
+        self
+    }
+
+    /// Set the command sequence in the common section.
+    pub fn common_sequence(mut self, commands: &'a [SequenceCommand<'a>]) -> Self {
+        self.common_sequence = Some(CommandSequenceBuilder::new(commands));
+        self
+    }
+
+    /// Set the sections following the common section, such as the command sequences.
+    ///
+    /// Every section is given as its encoded value and stored byte string wrapped in the
+    /// manifest. The sections must be in ascending order of their keys for a canonical manifest.
+    pub fn sections(mut self, sections: &'a [(Manifest, &'a [u8])]) -> Self {
+        self.sections = sections;
+        self
+    }
+
+    /// Encode the manifest into the buffer, returning the number of bytes written.
+    pub fn encode_into(&self, buf: &mut [u8]) -> Result<usize, Error> {
+        encode_into(self, buf)
+    }
+
+    fn common(&self) -> CommonBuilder<'a> {
+        CommonBuilder {
+            components: self.components,
+            sequence: self.common_sequence,
+        }
+    }
+}
+
+impl<C> Encode<C> for ManifestBuilder<'_> {
+    fn encode<W: Write>(
+        &self,
+        e: &mut Encoder<W>,
+        ctx: &mut C,
+    ) -> Result<(), minicbor::encode::Error<W::Error>> {
+        e.map(3 + self.sections.len() as u64)?;
+        e.i16(Manifest::EncodingVersion.into())?.u8(self.version)?;
+        e.i16(Manifest::SequenceNumber.into())?
+            .u64(self.sequence_number)?;
+        e.i16(Manifest::CommonData.into())?;
+        encode_wrapped(&self.common(), e, ctx)?;
+        for (section, value) in self.sections {
+            e.i16((*section).into())?.bytes(value)?;
+        }
+        Ok(())
+    }
+}
+
+impl<C> CborLen<C> for ManifestBuilder<'_> {
+    fn cbor_len(&self, ctx: &mut C) -> usize {
+        let sections = self
+            .sections
+            .iter()
+            .map(|(section, value)| {
+                i16::from(*section).cbor_len(ctx) + <&ByteSlice>::from(*value).cbor_len(ctx)
+            })
+            .sum::<usize>();
+        // Map header and the three single byte keys
+        (3 + self.sections.len() as u64).cbor_len(ctx)
+            + 3
+            + self.version.cbor_len(ctx)
+            + self.sequence_number.cbor_len(ctx)
+            + wrapped_len(&self.common(), ctx)
+            + sections
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod tests {
+    extern crate std;
+    use super::*;
+    use crate::Authenticated;
+
+    const DIGEST: [u8; 32] = [
+        0x00, 0x11, 0x22, 0x33, 0x44, 0x55, 0x66, 0x77, 0x88, 0x99, 0xaa, 0xbb, 0xcc, 0xdd, 0xee,
+        0xff, 0x01, 0x23, 0x45, 0x67, 0x89, 0xab, 0xcd, 0xef, 0xfe, 0xdc, 0xba, 0x98, 0x76, 0x54,
+        0x32, 0x10,
+    ];
+
+    fn simple_parameters() -> [Parameter<'static>; 4] {
+        [
+            Parameter::VendorId(Uuid::from_bytes([
+                0xFA, 0x6B, 0x4A, 0x53, 0xD5, 0xAD, 0x5F, 0xDF, 0xBE, 0x9D, 0xE6, 0x63, 0xE4, 0xD4,
+                0x1F, 0xFE,
+            ])),
+            Parameter::ClassId(Uuid::from_bytes([
+                0x14, 0x92, 0xAF, 0x14, 0x25, 0x69, 0x5E, 0x48, 0xBF, 0x42, 0x9B, 0x2D, 0x51, 0xF2,
+                0xAB, 0x45,
+            ])),
+            Parameter::ImageDigest {
+                algorithm: SuitDigestAlgorithm::Sha256,
+                digest: &DIGEST,
+            },
+            Parameter::ImageSize(34768),
+        ]
+    }
+
+    #[test]
+    fn simple_sequence() {
+        // Same sequence as the simple_sequence command test
+        let expected = std::vec![
+            0x86, 0x14, 0xA4, 0x01, 0x50, 0xFA, 0x6B, 0x4A, 0x53, 0xD5, 0xAD, 0x5F, 0xDF, 0xBE,
+            0x9D, 0xE6, 0x63, 0xE4, 0xD4, 0x1F, 0xFE, 0x02, 0x50, 0x14, 0x92, 0xAF, 0x14, 0x25,
+            0x69, 0x5E, 0x48, 0xBF, 0x42, 0x9B, 0x2D, 0x51, 0xF2, 0xAB, 0x45, 0x03, 0x58, 0x24,
+            0x82, 0x2F, 0x58, 0x20, 0x00, 0x11, 0x22, 0x33, 0x44, 0x55, 0x66, 0x77, 0x88, 0x99,
+            0xAA, 0xBB, 0xCC, 0xDD, 0xEE, 0xFF, 0x01, 0x23, 0x45, 0x67, 0x89, 0xAB, 0xCD, 0xEF,
+            0xFE, 0xDC, 0xBA, 0x98, 0x76, 0x54, 0x32, 0x10, 0x0E, 0x19, 0x87, 0xD0, 0x01, 0x0F,
+            0x02, 0x0F
+        ];
+        let parameters = simple_parameters();
+        let commands = [
+            SequenceCommand::OverrideParameters(&parameters),
+            SequenceCommand::Report(SuitCommand::VendorIdentifier, ReportingPolicy::new(15)),
+            SequenceCommand::Report(SuitCommand::ClassIdentifier, ReportingPolicy::new(15)),
+        ];
+        let sequence = CommandSequenceBuilder::new(&commands);
+        let mut buf = [0u8; 128];
+        let len = sequence.encode_into(&mut buf).unwrap();
+        assert_eq!(&buf[..len], expected.as_slice());
+        assert_eq!(minicbor::len(sequence), len);
+    }
+
+    #[test]
+    fn manifest() {
+        let component_id = [0x81, 0x41, 0x00];
+        let components = [Component::from_bytes(&component_id)];
+        let parameters = simple_parameters();
+        let commands = [SequenceCommand::OverrideParameters(&parameters)];
+        let builder = ManifestBuilder::new()
+            .sequence_number(5)
+            .components(&components)
+            .common_sequence(&commands);
+        let mut buf = [0u8; 128];
+        let len = builder.encode_into(&mut buf).unwrap();
+        assert_eq!(minicbor::len(builder), len);
+
+        let manifest = crate::manifest::Manifest::<Authenticated>::from_bytes::<Authenticated>(
+            buf[..len].into(),
+        );
+        assert_eq!(manifest.version().unwrap(), 1);
+        assert_eq!(manifest.sequence_number().unwrap(), 5);
+        assert!(!manifest.has_payload_fetch().unwrap());
+    }
+
+    #[test]
+    fn manifest_sections() {
+        let component_id = [0x81, 0x41, 0x00];
+        let components = [Component::from_bytes(&component_id)];
+        // Fetch and install sections, h'820C00'
+        let sequence = [0x82, 0x0C, 0x00];
+        let sections = [
+            (Manifest::PayloadFetch, sequence.as_slice()),
+            (Manifest::PayloadInstallation, sequence.as_slice()),
+        ];
+        let builder = ManifestBuilder::new()
+            .components(&components)
+            .sections(&sections);
+        let mut buf = [0u8; 64];
+        let len = builder.encode_into(&mut buf).unwrap();
+        assert_eq!(minicbor::len(builder), len);
+        assert_eq!(buf[0], 0xA5);
+        assert_eq!(
+            &buf[len - 10..len],
+            &[0x10, 0x43, 0x82, 0x0C, 0x00, 0x14, 0x43, 0x82, 0x0C, 0x00]
+        );
+
+        let manifest = crate::manifest::Manifest::<Authenticated>::from_bytes::<Authenticated>(
+            buf[..len].into(),
+        );
+        assert!(manifest.has_payload_fetch().unwrap());
+    }
+
+    #[test]
+    fn capacity() {
+        let builder = ManifestBuilder::new();
+        let mut buf = [0u8; 4];
+        assert_eq!(builder.encode_into(&mut buf), Err(Error::CapacityError));
+    }
+}
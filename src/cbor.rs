@@ -1,3 +1,6 @@
+use core::cell::OnceCell;
+
+use minicbor::data::Type;
 use minicbor::decode::Decoder;
 use minicbor::decode::Error;
 
@@ -13,3 +16,238 @@ impl<'b> SubCbor<'b> for Decoder<'b> {
         self.input().get(start..end).ok_or(Error::end_of_input())
     }
 }
+
+/// Check that the keys of the definite length map at the decoder position are unique.
+///
+/// Integer keys are compared by their value, so a non-minimal encoding does not hide a
+/// duplicate, other keys are compared by their encoding. The decoder is left after the map.
+pub(crate) fn check_unique_keys(decoder: &mut Decoder) -> Result<(), crate::error::Error> {
+    let position = decoder.position();
+    let len = decoder
+        .map()?
+        .ok_or(crate::error::Error::UnexpectedIndefiniteLength { position })?;
+    let entries = decoder.position();
+    for index in 0..len {
+        let position = decoder.position();
+        let key = unique_key(decoder)?;
+        let mut previous = decoder.clone();
+        previous.set_position(entries);
+        for _ in 0..index {
+            if unique_key(&mut previous)? == key {
+                return Err(crate::error::Error::DuplicateKey { position });
+            }
+            previous.skip()?;
+        }
+        decoder.skip()?;
+    }
+    Ok(())
+}
+
+/// Map key as compared by [`check_unique_keys`].
+#[derive(PartialEq)]
+enum UniqueKey<'b> {
+    Int(i128),
+    Encoded(&'b [u8]),
+}
+
+fn unique_key<'b>(decoder: &mut Decoder<'b>) -> Result<UniqueKey<'b>, Error> {
+    match decoder.datatype()? {
+        Type::U8
+        | Type::U16
+        | Type::U32
+        | Type::U64
+        | Type::I8
+        | Type::I16
+        | Type::I32
+        | Type::I64
+        | Type::Int => Ok(UniqueKey::Int(decoder.int()?.into())),
+        _ => Ok(UniqueKey::Encoded(decoder.sub_cbor()?)),
+    }
+}
+
+/// Decode a map key.
+///
+/// Non-integer keys are skipped and return `None`, integer keys outside of the `i16` range are
+/// rejected with [`crate::error::Error::UnexpectedCbor`] at the position of the key.
+pub(crate) fn decode_key(decoder: &mut Decoder) -> Result<Option<i16>, crate::error::Error> {
+    let position = decoder.position();
+    match decoder.datatype()? {
+        Type::U8
+        | Type::U16
+        | Type::U32
+        | Type::U64
+        | Type::I8
+        | Type::I16
+        | Type::I32
+        | Type::I64
+        | Type::Int => {
+            let key = decoder.int()?;
+            let key =
+                i16::try_from(key).map_err(|_| crate::error::Error::UnexpectedCbor { position })?;
+            Ok(Some(key))
+        }
+        _ => {
+            decoder.skip()?;
+            Ok(None)
+        }
+    }
+}
+
+/// Positions of the values of the known keys of a CBOR map.
+///
+/// Decoding the map once avoids walking it again for every element looked up. Only the first
+/// occurrence of a key is recorded. A malformed map fails building the index, and with it every
+/// lookup.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct MapIndex<const N: usize> {
+    elements: heapless::Vec<(i16, usize), N>,
+}
+
+impl<const N: usize> MapIndex<N> {
+    /// Index the definite length map at the decoder position, recording the `keys`.
+    fn new<K: Copy + Into<i16>>(
+        mut decoder: Decoder,
+        keys: &[K; N],
+    ) -> Result<Self, crate::error::Error> {
+        let mut index = Self::default();
+        // Deterministic CBOR forbids indefinite lengths
+        let position = decoder.position();
+        let len = decoder
+            .map()?
+            .ok_or(crate::error::Error::UnexpectedIndefiniteLength { position })?;
+        for _ in 0..len {
+            if let Some(key) = decode_key(&mut decoder)? {
+                let known = keys.iter().any(|k| (*k).into() == key);
+                if known && index.get(key).is_none() {
+                    // At most one entry per known key, the capacity is never exceeded
+                    let _ = index.elements.push((key, decoder.position()));
+                }
+            }
+            decoder.skip()?;
+        }
+        Ok(index)
+    }
+
+    /// The index stored in `cell`, indexing the map at the decoder position on the first lookup.
+    pub(crate) fn get_or_build<'c, K: Copy + Into<i16>>(
+        cell: &'c OnceCell<Self>,
+        decoder: &Decoder,
+        keys: &[K; N],
+    ) -> Result<&'c Self, crate::error::Error> {
+        match cell.get() {
+            Some(index) => Ok(index),
+            None => {
+                let index = Self::new(decoder.clone(), keys)?;
+                Ok(cell.get_or_init(|| index))
+            }
+        }
+    }
+
+    /// Position of the value of the key, `None` if the map does not contain the key.
+    pub(crate) fn get(&self, key: i16) -> Option<usize> {
+        self.elements
+            .iter()
+            .find(|(k, _)| *k == key)
+            .map(|(_, position)| *position)
+    }
+}
+
+/// Maximum nesting of arrays, maps and tags accepted by [`check_canonical`].
+const MAX_CANONICAL_DEPTH: usize = 16;
+
+/// Decode the head of the CBOR item at the decoder position.
+///
+/// Returns the major type and the argument. Arguments not encoded in the shortest form and
+/// indefinite length items are rejected.
+fn canonical_head(decoder: &mut Decoder) -> Result<(u8, u64), crate::error::Error> {
+    let position = decoder.position();
+    let input = decoder.input();
+    let initial = *input.get(position).ok_or(Error::end_of_input())?;
+    let (major, info) = (initial >> 5, initial & 0x1F);
+    let (len, minimum) = match info {
+        0..=23 => {
+            decoder.set_position(position + 1);
+            return Ok((major, info.into()));
+        }
+        // Simple values below 32 are encoded in the initial byte
+        24 if major == 7 => (1, 32),
+        24 => (1, 24),
+        // Floating point values are not subject to the length check
+        25..=27 if major == 7 => (1 << (info - 24), 0),
+        25 => (2, 0x100),
+        26 => (4, 0x1_0000),
+        27 => (8, 0x1_0000_0000),
+        // Indefinite length items are not deterministic
+        31 if (2..=5).contains(&major) => {
+            return Err(crate::error::Error::NonCanonicalCbor { position })
+        }
+        _ => return Err(crate::error::Error::UnexpectedCbor { position }),
+    };
+    let bytes = input
+        .get(position + 1..position + 1 + len)
+        .ok_or(Error::end_of_input())?;
+    let argument = bytes.iter().fold(0u64, |acc, b| (acc << 8) | u64::from(*b));
+    if argument < minimum {
+        return Err(crate::error::Error::NonCanonicalCbor { position });
+    }
+    decoder.set_position(position + 1 + len);
+    Ok((major, argument))
+}
+
+/// Check that the CBOR item at the decoder position uses deterministic encoding.
+///
+/// Verifies that integers, lengths and tags use the shortest encoding, that no indefinite length
+/// items are used and that map keys are sorted in the bytewise lexicographic order of their
+/// encoding. Byte strings are not descended into. The decoder is left after the item.
+pub(crate) fn check_canonical(decoder: &mut Decoder) -> Result<(), crate::error::Error> {
+    check_canonical_nested(decoder, 0)
+}
+
+fn check_canonical_nested<'b>(
+    decoder: &mut Decoder<'b>,
+    depth: usize,
+) -> Result<(), crate::error::Error> {
+    let position = decoder.position();
+    if depth > MAX_CANONICAL_DEPTH {
+        return Err(crate::error::Error::UnexpectedCbor { position });
+    }
+    let (major, argument) = canonical_head(decoder)?;
+    match major {
+        2 | 3 => {
+            let len: usize = argument
+                .try_into()
+                .map_err(|_| crate::error::Error::UnexpectedCbor { position })?;
+            let end = decoder.position().saturating_add(len);
+            if end > decoder.input().len() {
+                return Err(Error::end_of_input().into());
+            }
+            decoder.set_position(end);
+        }
+        4 => {
+            for _ in 0..argument {
+                check_canonical_nested(decoder, depth + 1)?;
+            }
+        }
+        5 => {
+            let mut previous: Option<&'b [u8]> = None;
+            for _ in 0..argument {
+                let key_position = decoder.position();
+                check_canonical_nested(decoder, depth + 1)?;
+                let key = decoder
+                    .input()
+                    .get(key_position..decoder.position())
+                    .ok_or(Error::end_of_input())?;
+                if previous.is_some_and(|previous| previous >= key) {
+                    return Err(crate::error::Error::NonCanonicalCbor {
+                        position: key_position,
+                    });
+                }
+                previous = Some(key);
+                check_canonical_nested(decoder, depth + 1)?;
+            }
+        }
+        6 => check_canonical_nested(decoder, depth + 1)?,
+        _ => {}
+    }
+    Ok(())
+}
@@ -1,19 +1,27 @@
 //! Provides SUIT command decoding
 //!
 //! The command decoding covers parsing and execution for command sequences in a SUIT manifest.
+use core::cell::{Cell, RefCell};
+use core::marker::PhantomData;
+
 use bitflags::bitflags;
 use ctutils::{Choice, CtEq};
 use digest::Update;
 use minicbor::bytes::ByteSlice;
+use minicbor::data::Type;
 use minicbor::Decoder;
 
+use crate::asynchooks::AsyncOperatingHooks;
 use crate::cbor::SubCbor;
 use crate::component::{Component, ComponentInfo};
-use crate::consts::SuitCommand;
+use crate::consts::{SuitCommand, SUIT_MAX_COMMANDS, SUIT_MAX_NESTING};
+use crate::digest::{Hasher, SuitDigest, SuitDigestAlgorithm};
+#[cfg(feature = "encryption")]
+use crate::encryption::EncryptionInfo;
 use crate::error::Error;
-use crate::manifeststate::ManifestState;
-use crate::report::ReportingPolicy;
-use crate::OperatingHooks;
+use crate::manifeststate::{iter_parameters, ManifestState};
+use crate::report::{RecordSink, ReportingPolicy};
+use crate::IntegratedPayloads;
 
 bitflags! {
     #[derive(Copy, Clone, Debug, PartialEq, Default)]
@@ -61,11 +69,13 @@ impl<'a> CommandArgument<'a> {
 pub(crate) struct Command<'a> {
     pub(crate) command: SuitCommand,
     pub(crate) argument: CommandArgument<'a>,
+    /// The encoded argument
+    pub(crate) argument_bytes: &'a [u8],
     pub(crate) position: usize,
 }
 
 impl<'a> Command<'a> {
-    fn get_argument_cbor(&mut self) -> Result<&mut Decoder<'a>, Error> {
+    pub(crate) fn get_argument_cbor(&mut self) -> Result<&mut Decoder<'a>, Error> {
         if let CommandArgument::Cbor {
             ref mut decoder, ..
         } = self.argument
@@ -77,7 +87,7 @@ impl<'a> Command<'a> {
         })
     }
 
-    fn get_argument_offset(&self) -> usize {
+    pub(crate) fn get_argument_offset(&self) -> usize {
         if let CommandArgument::Cbor { offset, .. } = self.argument {
             offset
         } else {
@@ -85,7 +95,7 @@ impl<'a> Command<'a> {
         }
     }
 
-    fn get_report_policy(&self) -> Result<ReportingPolicy, Error> {
+    pub(crate) fn get_report_policy(&self) -> Result<ReportingPolicy, Error> {
         if let CommandArgument::Report(policy) = self.argument {
             return Ok(policy);
         }
@@ -97,12 +107,13 @@ impl<'a> Command<'a> {
 
 pub(crate) struct CommandSequenceIterator<'a> {
     d: Decoder<'a>,
-    remaining: u64,
+    /// Number of commands left, `None` for an indefinite length sequence until the break
+    remaining: Option<u64>,
     offset: usize,
 }
 
 impl<'a> CommandSequenceIterator<'a> {
-    fn new(sequence: &'a ByteSlice, offset: usize) -> Result<Self, Error> {
+    pub(crate) fn new(sequence: &'a ByteSlice, offset: usize) -> Result<Self, Error> {
         let mut d = Decoder::new(sequence);
         let length = Self::enter_sequence(&mut d).map_err(|e| e.add_offset(offset))?;
         Ok(CommandSequenceIterator {
@@ -112,13 +123,14 @@ impl<'a> CommandSequenceIterator<'a> {
         })
     }
 
-    fn enter_sequence(decoder: &mut Decoder) -> Result<u64, Error> {
+    fn enter_sequence(decoder: &mut Decoder) -> Result<Option<u64>, Error> {
         let position = decoder.position();
         let length = decoder.array()?;
         let length = match length {
-            Some(n) if n % 2 == 1 => return Err(Error::InvalidCommandSequence { position }),
-            None => return Err(Error::InvalidCommandSequence { position }),
-            Some(n) => n / 2,
+            Some(n) if n % 2 == 1 || n / 2 > SUIT_MAX_COMMANDS as u64 => {
+                return Err(Error::InvalidCommandSequence { position })
+            }
+            length => length.map(|n| n / 2),
         };
         Ok(length)
     }
@@ -126,10 +138,23 @@ impl<'a> CommandSequenceIterator<'a> {
     fn decode_command(&mut self) -> Result<Command<'a>, Error> {
         let position = self.d.position();
         let command = self.d.i32()?.into();
+        // Every command comes with an argument, also in an indefinite length sequence
+        if self.d.datatype()? == Type::Break {
+            return Err(Error::InvalidCommandSequence {
+                position: self.d.position(),
+            });
+        }
+        let argument_position = self.d.position();
         let argument = CommandArgument::new(command, &mut self.d)?;
+        let argument_bytes = self
+            .d
+            .input()
+            .get(argument_position..self.d.position())
+            .ok_or(Error::EndOfInput)?;
         Ok(Command {
             command,
             argument,
+            argument_bytes,
             position,
         })
     }
@@ -139,46 +164,195 @@ impl<'a> Iterator for CommandSequenceIterator<'a> {
     type Item = Result<Command<'a>, Error>;
 
     fn next(&mut self) -> Option<Self::Item> {
-        if self.remaining > 0 {
-            self.remaining -= 1;
-            return Some(self.decode_command());
+        let res = match self.remaining {
+            Some(0) => return None,
+            Some(remaining) => {
+                self.remaining = Some(remaining - 1);
+                self.decode_command()
+            }
+            None => match self.d.datatype() {
+                Ok(Type::Break) => return None,
+                Ok(_) => self.decode_command(),
+                Err(e) => Err(e.into()),
+            },
+        };
+        // Nothing sensible follows a malformed command
+        if res.is_err() {
+            self.remaining = Some(0);
         }
-        None
+        Some(res)
     }
 }
 
-struct RwBuf<N: generic_array::ArrayLength> {
+pub(crate) struct RwBuf<N: generic_array::ArrayLength> {
     pub buf: generic_array::GenericArray<u8, N>,
 }
 
 impl<N: generic_array::ArrayLength> RwBuf<N> {
-    fn new() -> Self {
+    pub(crate) fn new() -> Self {
         RwBuf {
             buf: generic_array::GenericArray::default(),
         }
     }
 }
 
+/// Read from the component after checking the read against the size of the component.
+///
+/// The `size` is queried once by the caller for all chunks of a pass over the component. Catches
+/// reads past the end of the component before they reach the operating system with
+/// [`Error::ReadOutOfBounds`].
+pub(crate) async fn read_checked<O: AsyncOperatingHooks>(
+    os_hooks: &O,
+    component: &Component<'_>,
+    slot: Option<u64>,
+    size: usize,
+    offset: usize,
+    bytes: &mut [u8],
+) -> Result<(), Error> {
+    if offset.checked_add(bytes.len()).is_none_or(|end| end > size) {
+        return Err(Error::ReadOutOfBounds {
+            offset,
+            len: bytes.len(),
+            size,
+        });
+    }
+    os_hooks
+        .component_read(component, slot, offset, bytes)
+        .await
+}
+
+/// End of an access of `len` bytes at `offset`.
+///
+/// Sizes from untrusted manifests can exceed the address range of 32 bit targets, the overflow is
+/// reported as [`Error::SizeOverflow`] instead of wrapping.
+pub(crate) fn access_end(offset: usize, len: usize) -> Result<usize, Error> {
+    offset
+        .checked_add(len)
+        .ok_or(Error::SizeOverflow { offset, len })
+}
+
+/// Advance the offset of a payload fetched in consecutive chunks past the chunk at `offset`.
+///
+/// Fails with [`Error::ChunkOutOfOrder`] if the chunk does not start at the current offset.
+#[cfg(feature = "encryption")]
+fn next_chunk(received: &mut usize, offset: usize, len: usize) -> Result<(), Error> {
+    if offset != *received {
+        return Err(Error::ChunkOutOfOrder {
+            offset,
+            expected: *received,
+        });
+    }
+    *received = access_end(offset, len)?;
+    Ok(())
+}
+
+/// Hash the first `size` bytes of the component, reading it in chunks of the read/write buffer
+/// size of the operating system.
+///
+/// The reads are checked against the `component_size` the caller already queried. A zero sized
+/// component results in the digest of the empty input.
+pub(crate) async fn hash_component<O: AsyncOperatingHooks>(
+    os_hooks: &O,
+    component: &Component<'_>,
+    slot: Option<u64>,
+    component_size: usize,
+    size: usize,
+    algo: SuitDigestAlgorithm,
+) -> Result<Hasher, Error> {
+    let mut hasher = Hasher::new(algo)?;
+    let mut buf = RwBuf::<O::ReadWriteBufferSize>::new().buf;
+    for offset in (0..size).step_by(buf.len()) {
+        let diff = size.saturating_sub(offset);
+        let read_size = if diff < buf.len() { diff } else { buf.len() };
+        let buf = &mut buf[0..read_size];
+        read_checked(os_hooks, component, slot, component_size, offset, buf).await?;
+        hasher.update(buf)
+    }
+    Ok(hasher)
+}
+
+/// Dependencies validated by earlier executions of a command sequence.
+///
+/// A sequence runs once for every component, each dependency is resolved and validated only the
+/// first time the sequence selects it. Dependency indices above 63 are not tracked and validated
+/// on every selection.
+#[derive(Debug, Default)]
+pub(crate) struct ValidatedDependencies(Cell<u64>);
+
+impl ValidatedDependencies {
+    fn contains(&self, index: u32) -> bool {
+        1u64.checked_shl(index)
+            .is_some_and(|bit| self.0.get() & bit != 0)
+    }
+
+    fn insert(&self, index: u32) {
+        if let Some(bit) = 1u64.checked_shl(index) {
+            self.0.set(self.0.get() | bit);
+        }
+    }
+}
+
 #[derive(Debug)]
 pub(crate) struct CommandSequence<'a> {
     sequence: &'a ByteSlice,
     offset: usize,
+    /// Payloads integrated in the envelope, for the fetch directives
+    payloads: Option<IntegratedPayloads<'a>>,
+    /// Dependencies validated by the executions for the previous components
+    validated: ValidatedDependencies,
 }
 
 impl<'a> CommandSequence<'a> {
     pub(crate) fn new(sequence: &'a ByteSlice, offset: usize) -> Self {
-        Self { sequence, offset }
+        Self {
+            sequence,
+            offset,
+            payloads: None,
+            validated: ValidatedDependencies::default(),
+        }
+    }
+
+    pub(crate) fn with_payloads(mut self, payloads: Option<IntegratedPayloads<'a>>) -> Self {
+        self.payloads = payloads;
+        self
+    }
+
+    /// Execute the command sequence.
+    ///
+    /// The nesting depth left for nested sequences and dependencies is encoded in `N`.
+    pub(crate) async fn execute<N: Nesting>(
+        &self,
+        state: ManifestState<'a>,
+        component_info: &'a ComponentInfo<'a>,
+        os_hooks: &impl AsyncOperatingHooks,
+        report: Option<&'a RefCell<dyn RecordSink>>,
+    ) -> Result<ManifestState<'a>, Error> {
+        CommandSequenceExecutor::new(self.sequence, self.offset, os_hooks)
+            .with_report(report)
+            .with_payloads(self.payloads)
+            .with_validated(&self.validated)
+            .process::<N>(state, component_info)
+            .await
+            .map_err(|e| e.add_offset(self.offset))
     }
 
-    pub(crate) fn execute(
+    /// Execute only the parts of the command sequence needed to verify the image digests.
+    ///
+    /// Parameter overrides, component selection, nested sequences and the image match condition
+    /// are processed, all other conditions and directives are skipped. Selected dependencies only
+    /// verify their image digests as well.
+    pub(crate) async fn verify_images<N: Nesting>(
         &self,
         state: ManifestState<'a>,
         component_info: &'a ComponentInfo<'a>,
-        os_hooks: &'a impl OperatingHooks,
+        os_hooks: &impl AsyncOperatingHooks,
     ) -> Result<ManifestState<'a>, Error> {
-        let executor = CommandSequenceExecutor::new(self.sequence, self.offset, os_hooks);
-        executor
-            .process(state, component_info)
+        CommandSequenceExecutor::new(self.sequence, self.offset, os_hooks)
+            .with_payloads(self.payloads)
+            .with_images_only()
+            .with_validated(&self.validated)
+            .process::<N>(state, component_info)
+            .await
             .map_err(|e| e.add_offset(self.offset))
     }
 
@@ -193,7 +367,11 @@ impl<'a> CommandSequence<'a> {
     fn add_properties(
         &self,
         mut content: CommandSequenceProperties,
+        depth: usize,
     ) -> Result<CommandSequenceProperties, Error> {
+        if depth > SUIT_MAX_NESTING {
+            return Err(Error::RecursionLimitExceeded);
+        }
         for command in CommandSequenceIterator::new(self.sequence, self.offset)? {
             let mut command = command?;
             if command.command.has_side_effect() {
@@ -212,36 +390,335 @@ impl<'a> CommandSequence<'a> {
                     if sequence.is_empty() {
                         continue;
                     }
-                    content = CommandSequence::new(sequence, 0).add_properties(content)?
+                    content =
+                        CommandSequence::new(sequence, 0).add_properties(content, depth + 1)?
+                }
+            } else if command.command == crate::consts::SuitCommand::RunSequence {
+                let mut decoder = command.get_argument_cbor()?.clone();
+                let sequence = decoder.bytes()?;
+                if !sequence.is_empty() {
+                    content = CommandSequence::new(sequence.into(), 0)
+                        .add_properties(content, depth + 1)?
                 }
             }
         }
         Ok(content)
     }
 
+    /// Visit every command of the sequence without executing it.
+    ///
+    /// The nested command sequences of the try each and run sequence commands are visited too.
+    fn walk<F>(&self, depth: usize, visit: &mut F) -> Result<(), Error>
+    where
+        F: FnMut(&mut Command<'a>) -> Result<(), Error>,
+    {
+        if depth > SUIT_MAX_NESTING {
+            return Err(Error::RecursionLimitExceeded);
+        }
+        for command in CommandSequenceIterator::new(self.sequence, self.offset)? {
+            let mut command = command?;
+            visit(&mut command)?;
+            match command.command {
+                SuitCommand::TryEach => {
+                    let mut decoder = command.get_argument_cbor()?.clone();
+                    for sequence in decoder.array_iter::<&ByteSlice>()? {
+                        let sequence = sequence?;
+                        if sequence.is_empty() {
+                            continue;
+                        }
+                        CommandSequence::new(sequence, 0).walk(depth + 1, visit)?;
+                    }
+                }
+                SuitCommand::RunSequence => {
+                    let mut decoder = command.get_argument_cbor()?.clone();
+                    let sequence = decoder.bytes()?;
+                    if !sequence.is_empty() {
+                        CommandSequence::new(sequence.into(), 0).walk(depth + 1, visit)?;
+                    }
+                }
+                _ => {}
+            }
+        }
+        Ok(())
+    }
+
+    /// Check the algorithm of every image digest parameter against the allowed algorithms.
+    ///
+    /// The nested command sequences of the try each and run sequence commands are checked too.
+    pub(crate) fn check_digest_algorithms(
+        &self,
+        allowed: &[SuitDigestAlgorithm],
+    ) -> Result<(), Error> {
+        self.walk(0, &mut |command| {
+            if !matches!(command.command, SuitCommand::OverrideParameters) {
+                return Ok(());
+            }
+            for parameter in iter_parameters(command.get_argument_cbor()?) {
+                let (parameter, value) = parameter?;
+                if !matches!(parameter, crate::consts::SuitParameter::ImageDigest) {
+                    continue;
+                }
+                let digest = Decoder::new(value).bytes()?;
+                let algorithm = SuitDigest::from_cbor(&mut Decoder::new(digest))?.algorithm();
+                if !allowed.contains(&algorithm) {
+                    return Err(Error::DigestAlgorithmNotPermitted {
+                        algorithm: algorithm.into(),
+                    });
+                }
+            }
+            Ok(())
+        })
+    }
+
+    /// Collect the commands of the sequence that the executors do not implement.
+    ///
+    /// These are the copy and swap directives, custom commands and the unset command, every
+    /// command is collected once. Returns [`Error::CapacityError`] if more than `N` different
+    /// commands are found.
+    pub(crate) fn collect_unsupported<const N: usize>(
+        &self,
+        found: &mut heapless::Vec<SuitCommand, N>,
+    ) -> Result<(), Error> {
+        self.walk(0, &mut |command| {
+            let unsupported = matches!(
+                command.command,
+                SuitCommand::Unset | SuitCommand::Copy | SuitCommand::Swap | SuitCommand::Custom(_)
+            );
+            if unsupported && !found.contains(&command.command) {
+                found
+                    .push(command.command)
+                    .map_err(|_| Error::CapacityError)?;
+            }
+            Ok(())
+        })
+    }
+
     pub(crate) fn properties(&self) -> Result<CommandSequenceProperties, Error> {
         let content = CommandSequenceProperties::default();
-        self.add_properties(content)
+        self.add_properties(content, 0)
+    }
+}
+
+/// Nesting of command sequences through the try each and run sequence commands.
+///
+/// Recursive futures have no static size, the remaining nesting depth is encoded in the type
+/// instead to avoid boxing the nested futures. Dependencies count as one nesting level as well.
+pub(crate) trait Nesting {
+    /// Number of nested levels supported.
+    const DEPTH: usize;
+
+    /// Execute a nested command sequence with the hooks, report and mode of the parent.
+    async fn execute<'a, O: AsyncOperatingHooks>(
+        sequence: &'a ByteSlice,
+        offset: usize,
+        state: ManifestState<'a>,
+        component_info: &'a ComponentInfo<'a>,
+        parent: &CommandSequenceExecutor<'a, '_, O>,
+    ) -> Result<ManifestState<'a>, Error>;
+
+    /// Authenticate and validate a dependent manifest one nesting level deeper.
+    ///
+    /// With `images_only` only the image digests of the dependent manifest are verified.
+    async fn validate_dependency<O: AsyncOperatingHooks>(
+        os_hooks: &O,
+        envelope: &[u8],
+        images_only: bool,
+    ) -> Result<(), Error>;
+}
+
+/// No further nesting of command sequences is supported.
+pub(crate) struct Flat;
+
+/// One more level of nesting of command sequences.
+pub(crate) struct Nested<N: Nesting>(PhantomData<N>);
+
+/// Maximum nesting supported, [`SUIT_MAX_NESTING`] levels deep.
+pub(crate) type MaxNesting = Nested<Nested<Nested<Nested<Flat>>>>;
+
+const _: () = assert!(MaxNesting::DEPTH == SUIT_MAX_NESTING);
+
+impl Nesting for Flat {
+    const DEPTH: usize = 0;
+
+    async fn execute<'a, O: AsyncOperatingHooks>(
+        _sequence: &'a ByteSlice,
+        _offset: usize,
+        _state: ManifestState<'a>,
+        _component_info: &'a ComponentInfo<'a>,
+        _parent: &CommandSequenceExecutor<'a, '_, O>,
+    ) -> Result<ManifestState<'a>, Error> {
+        Err(Error::RecursionLimitExceeded)
+    }
+
+    async fn validate_dependency<O: AsyncOperatingHooks>(
+        _os_hooks: &O,
+        _envelope: &[u8],
+        _images_only: bool,
+    ) -> Result<(), Error> {
+        Err(Error::RecursionLimitExceeded)
+    }
+}
+
+impl<N: Nesting> Nesting for Nested<N> {
+    const DEPTH: usize = N::DEPTH + 1;
+
+    async fn execute<'a, O: AsyncOperatingHooks>(
+        sequence: &'a ByteSlice,
+        offset: usize,
+        state: ManifestState<'a>,
+        component_info: &'a ComponentInfo<'a>,
+        parent: &CommandSequenceExecutor<'a, '_, O>,
+    ) -> Result<ManifestState<'a>, Error> {
+        let executor = CommandSequenceExecutor {
+            command_sequence: sequence,
+            offset,
+            nested: true,
+            commands: parent.commands.clone(),
+            side_effects: Cell::new(false),
+            io_failure: Cell::new(false),
+            ..*parent
+        };
+        let res = executor
+            .process::<N>(state, component_info)
+            .await
+            .map_err(|e| e.add_offset(offset));
+        parent.commands.set(executor.commands.get());
+        if executor.side_effects.get() {
+            parent.side_effects.set(true);
+        }
+        res
+    }
+
+    async fn validate_dependency<O: AsyncOperatingHooks>(
+        os_hooks: &O,
+        envelope: &[u8],
+        images_only: bool,
+    ) -> Result<(), Error> {
+        let suit = crate::SuitManifest::from_bytes(&envelope)
+            .authenticate(|cose, payload| os_hooks.authenticate_dependency(cose, payload))?;
+        let manifest = suit.envelope()?.manifest()?;
+        // The dependent manifest is processed one nesting level deeper, a manifest without image
+        // validation section only needs to authenticate
+        if images_only {
+            return manifest.run_verify_images::<N>(os_hooks).await;
+        }
+        match manifest
+            .run_section::<N>(
+                os_hooks,
+                crate::consts::Manifest::ImageValidation,
+                None,
+                None,
+            )
+            .await
+        {
+            Err(Error::NoCommandSection { .. }) => Ok(()),
+            res => res,
+        }
     }
 }
 
 #[derive(Debug, Clone)]
-pub(crate) struct CommandSequenceExecutor<'a, O: OperatingHooks> {
+pub(crate) struct CommandSequenceExecutor<'a, 'h, O: AsyncOperatingHooks> {
     command_sequence: &'a ByteSlice,
     offset: usize,
-    os_hooks: &'a O,
+    os_hooks: &'h O,
+    report: Option<&'a RefCell<dyn RecordSink>>,
+    /// Payloads integrated in the envelope, fetched instead of calling the hook
+    payloads: Option<IntegratedPayloads<'a>>,
+    /// Only process the commands needed to verify the image digests
+    images_only: bool,
+    /// Dependencies validated by earlier executions of the sequence
+    validated: Option<&'h ValidatedDependencies>,
+    /// Whether the sequence runs inside a try each or run sequence command
+    nested: bool,
+    /// Number of commands left to process, shared with the nested sequences
+    commands: Cell<usize>,
+    /// Whether a directive with side effects was executed, including the nested sequences
+    side_effects: Cell<bool>,
+    /// Whether the current directive failed in a component I/O hook
+    io_failure: Cell<bool>,
 }
 
-impl<'a, O: OperatingHooks> CommandSequenceExecutor<'a, O> {
-    fn new(command_sequence: &'a ByteSlice, offset: usize, os_hooks: &'a O) -> Self {
+impl<'a, 'h, O: AsyncOperatingHooks> CommandSequenceExecutor<'a, 'h, O> {
+    fn new(command_sequence: &'a ByteSlice, offset: usize, os_hooks: &'h O) -> Self {
         Self {
             command_sequence,
             offset,
             os_hooks,
+            report: None,
+            payloads: None,
+            images_only: false,
+            validated: None,
+            nested: false,
+            commands: Cell::new(SUIT_MAX_COMMANDS),
+            side_effects: Cell::new(false),
+            io_failure: Cell::new(false),
+        }
+    }
+
+    fn with_report(mut self, report: Option<&'a RefCell<dyn RecordSink>>) -> Self {
+        self.report = report;
+        self
+    }
+
+    fn with_payloads(mut self, payloads: Option<IntegratedPayloads<'a>>) -> Self {
+        self.payloads = payloads;
+        self
+    }
+
+    fn with_images_only(mut self) -> Self {
+        self.images_only = true;
+        self
+    }
+
+    fn with_validated(mut self, validated: &'h ValidatedDependencies) -> Self {
+        self.validated = Some(validated);
+        self
+    }
+
+    /// Flag the failure of a component I/O hook called by a directive.
+    ///
+    /// These are the only directive failures soft failure covers, see [`Self::process`].
+    fn io_hook<T>(&self, res: Result<T, Error>) -> Result<T, Error> {
+        if res.is_err() {
+            self.io_failure.set(true);
+        }
+        res
+    }
+
+    fn record(
+        &self,
+        policy: ReportingPolicy,
+        command: SuitCommand,
+        component_info: &ComponentInfo,
+        result: Result<(), Error>,
+    ) {
+        if let Some(record) = policy.record(command, component_info.index, result) {
+            self.os_hooks.emit_record(&record);
+            if let Some(mut report) = self.report.and_then(|r| r.try_borrow_mut().ok()) {
+                report.push(record);
+            }
         }
     }
 
-    fn try_each(
+    fn in_applylist(
+        &self,
+        component_info: &ComponentInfo,
+        decoder: &mut Decoder,
+    ) -> Result<bool, Error> {
+        component_info.in_applylist(decoder, |candidate| {
+            self.os_hooks
+                .match_component_identifier(component_info.component(), candidate)
+        })
+    }
+
+    /// Execute the sequences of a try each until one succeeds.
+    ///
+    /// Every sequence starts from the parameters as they were before the try each, parameters set
+    /// by a failed sequence are discarded. Side effects on the components are not rolled back, a
+    /// sequence failing after executing a directive with side effects fails with
+    /// [`Error::TryEachSideEffect`] instead of trying the next sequence.
+    async fn try_each<N: Nesting>(
         &self,
         state: &mut ManifestState<'a>,
         component_info: &'a ComponentInfo<'a>,
@@ -254,17 +731,25 @@ impl<'a, O: OperatingHooks> CommandSequenceExecutor<'a, O> {
             if sequence.is_empty() {
                 return Ok(());
             }
-            let res = CommandSequence::new(sequence, 0).execute(
-                state.clone(),
-                component_info,
-                self.os_hooks,
-            );
+            let side_effects = self.side_effects.replace(false);
+            let res = N::execute(sequence, 0, state.nested(), component_info, self).await;
+            let dirty = self.side_effects.get();
+            self.side_effects.set(side_effects || dirty);
             match res {
                 Ok(res) => {
+                    let soft_failure = state.soft_failure;
                     *state = res;
+                    state.soft_failure = soft_failure;
                     return Ok(());
                 }
-                Err(Error::ConditionMatchFail { position }) => {
+                // An abort or a nested try each without a matching sequence fail the sequence as well
+                Err(
+                    Error::ConditionMatchFail { position }
+                    | Error::ManifestAbort { position }
+                    | Error::ComponentConditionFailed { position, .. }
+                    | Error::ComponentSlotMismatch { position, .. }
+                    | Error::TryEachFail { position },
+                ) => {
                     // This requires pointer arithmetic (via element_offset) because offsets get
                     // lost during bytestring wrapping
                     if let Some(element) = sequence.get(position) {
@@ -275,6 +760,11 @@ impl<'a, O: OperatingHooks> CommandSequenceExecutor<'a, O> {
                 }
                 Err(err) => return Err(err),
             }
+            if dirty {
+                return Err(Error::TryEachSideEffect {
+                    position: err_position,
+                });
+            }
         }
         // use the last failed condition as position
         Err(Error::TryEachFail {
@@ -282,7 +772,30 @@ impl<'a, O: OperatingHooks> CommandSequenceExecutor<'a, O> {
         })
     }
 
-    fn process_command(
+    async fn directive_run_sequence<N: Nesting>(
+        &self,
+        state: &ManifestState<'a>,
+        component_info: &'a ComponentInfo<'a>,
+        decoder: &mut Decoder<'a>,
+    ) -> Result<(), Error> {
+        let sequence = decoder.bytes()?;
+        if sequence.is_empty() {
+            return Ok(());
+        }
+        let offset = decoder.position() - sequence.len();
+        // Parameters set inside the nested sequence don't leak into the current sequence
+        N::execute(
+            sequence.into(),
+            offset,
+            state.nested(),
+            component_info,
+            self,
+        )
+        .await?;
+        Ok(())
+    }
+
+    async fn process_command<N: Nesting>(
         &self,
         state: &mut ManifestState<'a>,
         component_info: &'a ComponentInfo<'a>,
@@ -292,16 +805,12 @@ impl<'a, O: OperatingHooks> CommandSequenceExecutor<'a, O> {
         let component = component_info.component();
         let argument_offset = command.get_argument_offset();
         match command.command {
-            SuitCommand::Unset => {
+            SuitCommand::Unset | SuitCommand::Custom(_) => {
                 return Err(Error::UnsupportedCommand {
                     command: command.command.into(),
                 })
             }
-            SuitCommand::Abort => {
-                return Err(Error::ConditionMatchFail {
-                    position: command.position,
-                })
-            }
+            SuitCommand::Abort => return Err(Error::ManifestAbort { position: 0 }),
             SuitCommand::OverrideParameters => {
                 let mut argument = command.get_argument_cbor()?.clone();
                 state
@@ -309,54 +818,70 @@ impl<'a, O: OperatingHooks> CommandSequenceExecutor<'a, O> {
                     .map_err(|e| e.add_offset(argument_offset))?;
             }
             SuitCommand::SetComponentIndex => {
-                *match_component = component_info.in_applylist(command.get_argument_cbor()?)?;
+                *match_component =
+                    self.in_applylist(component_info, command.get_argument_cbor()?)?;
+            }
+            SuitCommand::SetDependencyIndex => {
+                self.directive_set_dependency_index::<N>(
+                    component_info,
+                    command.get_argument_cbor()?,
+                )
+                .await?;
             }
             SuitCommand::CheckContent => {
                 // byte by byte check
-                self.cond_check_content(state, component)?;
+                self.cond_check_content(state, component).await?;
             }
             SuitCommand::ClassIdentifier => self.cond_class_identifier(state, component)?,
             SuitCommand::ComponentSlot => {
                 self.cond_component_slot(state, component)?;
             }
-            SuitCommand::Copy => Err(Error::UnsupportedCommand {
-                command: SuitCommand::Copy.into(),
-            })?,
             SuitCommand::DeviceIdentifier => {
                 self.cond_device_identifier(state, component)?;
             }
+            SuitCommand::Fetch if self.os_hooks.verify_fetch() => {
+                self.directive_fetch_and_verify(state, component).await?;
+            }
             SuitCommand::Fetch => {
-                self.directive_fetch(state, component)?;
+                self.directive_fetch(state, component).await?;
             }
             SuitCommand::ImageMatch => {
                 // Digest check
-                self.cond_image_match(state, component)?;
-            }
-            SuitCommand::Invoke => Err(Error::UnsupportedCommand {
-                command: SuitCommand::Invoke.into(),
-            })?,
-            SuitCommand::RunSequence => Err(Error::UnsupportedCommand {
-                command: SuitCommand::RunSequence.into(),
-            })?,
-            SuitCommand::Swap => Err(Error::UnsupportedCommand {
-                command: SuitCommand::RunSequence.into(),
-            })?,
+                self.cond_image_match(state, component).await?;
+            }
+            SuitCommand::UseBefore => self.cond_use_before(state)?,
+            SuitCommand::Invoke => {
+                self.directive_invoke(state, component).await?;
+            }
+            SuitCommand::RunSequence => {
+                let mut argument = command.get_argument_cbor()?.clone();
+                self.directive_run_sequence::<N>(state, component_info, &mut argument)
+                    .await
+                    .map_err(|e| e.add_offset(argument_offset - command.position))?
+            }
+            SuitCommand::Copy | SuitCommand::Swap => {
+                return Err(Error::UnsupportedCommand {
+                    command: command.command.into(),
+                })
+            }
             SuitCommand::TryEach => {
                 let mut argument = command.get_argument_cbor()?.clone();
-                self.try_each(state, component_info, &mut argument)?
+                self.try_each::<N>(state, component_info, &mut argument)
+                    .await
+                    .map_err(|e| e.add_offset(argument_offset - command.position))?
             }
             SuitCommand::VendorIdentifier => {
                 self.cond_vendor_identifier(state, component)?;
             }
             SuitCommand::WriteContent => {
-                self.directive_write(state, component)?;
+                self.directive_write(state, component).await?;
             }
-            SuitCommand::Custom(_n) => todo!(),
         }
         Ok(())
     }
 
-    pub(crate) fn process(
+    /// Process the command sequence, nested sequences and dependencies up to the depth of `N`.
+    pub(crate) async fn process<N: Nesting>(
         &self,
         mut state: ManifestState<'a>,
         component_info: &'a ComponentInfo<'a>,
@@ -365,6 +890,12 @@ impl<'a, O: OperatingHooks> CommandSequenceExecutor<'a, O> {
         for command in CommandSequenceIterator::new(self.command_sequence, self.offset)? {
             let mut command = command?;
             let position = command.position;
+            let commands = self
+                .commands
+                .get()
+                .checked_sub(1)
+                .ok_or(Error::InvalidCommandSequence { position })?;
+            self.commands.set(commands);
             if !match_component {
                 if matches!(command.command, SuitCommand::SetComponentIndex) {
                     if let CommandArgument::Cbor {
@@ -372,14 +903,58 @@ impl<'a, O: OperatingHooks> CommandSequenceExecutor<'a, O> {
                         offset,
                     } = command.argument
                     {
-                        match_component = component_info
-                            .in_applylist(decoder)
+                        match_component = self
+                            .in_applylist(component_info, decoder)
                             .map_err(|e| e.add_offset(offset))?;
                     }
                 }
+            } else if self.images_only && !command.command.verifies_images() {
+                continue;
             } else {
-                self.process_command(&mut state, component_info, &mut match_component, command)
-                    .map_err(|e| e.add_offset(position))?;
+                if let CommandArgument::Report(policy) = command.argument {
+                    self.os_hooks.reporting_policy(
+                        command.command,
+                        component_info.component(),
+                        policy,
+                    );
+                }
+                let policy = command.get_report_policy().ok();
+                let suit_command = command.command;
+                if suit_command.has_side_effect() {
+                    self.side_effects.set(true);
+                }
+                self.io_failure.set(false);
+                let res = self
+                    .process_command::<N>(&mut state, component_info, &mut match_component, command)
+                    .await
+                    .map_err(|e| {
+                        e.add_offset(position)
+                            .for_component(component_info.index, suit_command)
+                    });
+                if let Some(policy) = policy {
+                    self.record(policy, suit_command, component_info, res);
+                }
+                match res {
+                    // Soft failure ends the sequence without failing the enclosing sequence
+                    Err(
+                        Error::ConditionMatchFail { .. }
+                        | Error::ManifestAbort { .. }
+                        | Error::ComponentConditionFailed { .. }
+                        | Error::ComponentSlotMismatch { .. },
+                    ) if state.soft_failure() => break,
+                    // Without strict order a directive failing in a component I/O hook of a nested
+                    // sequence is a soft failure as well. Integrity, capacity and decoding
+                    // failures always fail the sequence.
+                    Err(_)
+                        if self.nested
+                            && self.io_failure.get()
+                            && !state.strict_order()
+                            && state.soft_failure() =>
+                    {
+                        break
+                    }
+                    res => res?,
+                }
             }
         }
         Ok(state)
@@ -445,6 +1020,18 @@ impl<'a, O: OperatingHooks> CommandSequenceExecutor<'a, O> {
         }
     }
 
+    fn cond_use_before(&self, state: &ManifestState) -> Result<(), Error> {
+        if let Some(use_before) = state.use_before {
+            if self.os_hooks.current_time()? < use_before {
+                Ok(())
+            } else {
+                Err(Error::ConditionMatchFail { position: 0 })
+            }
+        } else {
+            Err(Error::ParameterNotSet { position: 0 })
+        }
+    }
+
     fn cond_component_slot(
         &self,
         state: &ManifestState,
@@ -457,7 +1044,10 @@ impl<'a, O: OperatingHooks> CommandSequenceExecutor<'a, O> {
                     if b {
                         Ok(())
                     } else {
-                        Err(Error::ConditionMatchFail { position: 0 })
+                        Err(Error::ComponentSlotMismatch {
+                            slot: component_slot,
+                            position: 0,
+                        })
                     }
                 })
         } else {
@@ -465,13 +1055,17 @@ impl<'a, O: OperatingHooks> CommandSequenceExecutor<'a, O> {
         }
     }
 
-    fn cond_check_content(
+    /// Compare the component against the content parameter.
+    ///
+    /// Without the content parameter the component is compared against the image digest
+    /// parameter instead, the content takes precedence if both are set.
+    async fn cond_check_content(
         &self,
-        state: &ManifestState,
-        component: &Component,
+        state: &ManifestState<'_>,
+        component: &Component<'_>,
     ) -> Result<(), Error> {
         if let Some(content) = &state.content {
-            let size = self.os_hooks.component_size(component)?;
+            let size = self.os_hooks.component_size(component).await?;
             if size != content.len() {
                 return Err(Error::ConditionMatchFail { position: 0 });
             }
@@ -481,10 +1075,17 @@ impl<'a, O: OperatingHooks> CommandSequenceExecutor<'a, O> {
                 let diff = size.saturating_sub(offset);
                 let read_size = if diff < buf.len() { diff } else { buf.len() };
                 let buf = &mut buf[0..read_size];
-                self.os_hooks
-                    .component_read(component, state.component_slot, offset, buf)?;
+                read_checked(
+                    self.os_hooks,
+                    component,
+                    state.component_slot,
+                    size,
+                    offset,
+                    buf,
+                )
+                .await?;
                 let manifest_content = content
-                    .get(offset..(offset + read_size))
+                    .get(offset..access_end(offset, read_size)?)
                     .ok_or(Error::ConditionMatchFail { position: 0 })?;
                 choice = choice.and(manifest_content.ct_eq(buf));
             }
@@ -494,23 +1095,43 @@ impl<'a, O: OperatingHooks> CommandSequenceExecutor<'a, O> {
                 Err(Error::ConditionMatchFail { position: 0 })
             }
         } else {
-            Err(Error::ParameterNotSet { position: 0 })
+            self.cond_image_match(state, component).await
         }
     }
 
-    fn cond_image_match(&self, state: &ManifestState, component: &Component) -> Result<(), Error> {
-        if let Some(digest) = &state.image_digest {
-            let size = self.os_hooks.component_size(component)?;
-            let mut hasher = digest.hasher()?;
-            let mut buf = RwBuf::<O::ReadWriteBufferSize>::new().buf;
-            for offset in (0..size).step_by(buf.len()) {
-                let diff = size.saturating_sub(offset);
-                let read_size = if diff < buf.len() { diff } else { buf.len() };
-                let buf = &mut buf[0..read_size];
-                self.os_hooks
-                    .component_read(component, state.component_slot, offset, buf)?;
-                hasher.update(buf)
+    /// Determine the number of bytes covered by the image digest.
+    ///
+    /// This is the image size parameter if set, the component may be larger than the image it
+    /// contains. Falls back to the size of the component otherwise.
+    /// Size of the image to match within a component of `component_size` bytes.
+    fn cond_image_size(state: &ManifestState<'_>, component_size: usize) -> Result<usize, Error> {
+        match state.image_size {
+            Some(image_size) if image_size > component_size => {
+                Err(Error::ConditionMatchFail { position: 0 })
             }
+            Some(image_size) => Ok(image_size),
+            None => Ok(component_size),
+        }
+    }
+
+    async fn cond_image_match(
+        &self,
+        state: &ManifestState<'_>,
+        component: &Component<'_>,
+    ) -> Result<(), Error> {
+        if let Some(digest) = &state.image_digest {
+            // A size mismatch fails before the expensive hashing
+            let component_size = self.os_hooks.component_size(component).await?;
+            let size = Self::cond_image_size(state, component_size)?;
+            let hasher = hash_component(
+                self.os_hooks,
+                component,
+                state.component_slot,
+                component_size,
+                size,
+                digest.algorithm(),
+            )
+            .await?;
             digest.match_hasher(hasher).and_then(|b| {
                 if b {
                     Ok(())
@@ -523,23 +1144,305 @@ impl<'a, O: OperatingHooks> CommandSequenceExecutor<'a, O> {
         }
     }
 
-    fn directive_fetch(&self, state: &ManifestState, component: &Component) -> Result<(), Error> {
+    /// Retrieve the payload from the uri and pass it to `write`.
+    ///
+    /// A payload integrated in the envelope under the uri is passed in a single chunk, any other
+    /// uri is left to [`AsyncOperatingHooks::component_fetch`].
+    async fn fetch(
+        &self,
+        component: &Component<'_>,
+        slot: Option<u64>,
+        uri: &str,
+        write: &mut impl AsyncFnMut(usize, &[u8]) -> Result<(), Error>,
+    ) -> Result<(), Error> {
+        let payload = self
+            .payloads
+            .map(|payloads| payloads.get(uri))
+            .transpose()?;
+        match payload.flatten() {
+            Some(payload) => write(0, payload).await,
+            None => {
+                self.os_hooks
+                    .component_fetch(component, slot, uri, write)
+                    .await
+            }
+        }
+    }
+
+    async fn directive_fetch(
+        &self,
+        state: &ManifestState<'_>,
+        component: &Component<'_>,
+    ) -> Result<(), Error> {
         if let Some(uri) = state.uri {
-            self.os_hooks.fetch(component, state.component_slot, uri)
+            if let Some(size) = state.image_size {
+                self.check_capacity(component, size).await?;
+            }
+            let slot = state.component_slot;
+            self.os_hooks.component_erase(component, slot).await?;
+            #[cfg(feature = "encryption")]
+            if let Some(info) = &state.encryption_info {
+                self.os_hooks.decrypt_start(info)?;
+                let mut received = 0;
+                let mut written = 0;
+                let res = self
+                    .fetch(component, slot, uri, &mut async |offset, bytes: &[u8]| {
+                        next_chunk(&mut received, offset, bytes.len())?;
+                        self.write_decrypted(info, component, slot, &mut written, None, bytes)
+                            .await
+                    })
+                    .await
+                    .and_then(|()| self.os_hooks.decrypt_finish(info));
+                return self.erase_on_failure(res, component, slot).await;
+            }
+            self.fetch(component, slot, uri, &mut async |offset, bytes: &[u8]| {
+                self.os_hooks
+                    .component_write(component, slot, offset, bytes)
+                    .await
+            })
+            .await
         } else {
             Err(Error::ParameterNotSet { position: 0 })
         }
     }
 
-    fn directive_write(&self, state: &ManifestState, component: &Component) -> Result<(), Error> {
+    /// Fetch the payload and verify it against the image digest in a single pass.
+    ///
+    /// Every chunk is copied into the read/write buffer, hashed and written into the component
+    /// from the same buffer, avoiding a second pass over the component for the image match. The
+    /// chunks must arrive in order. The component is erased if the digest does not match.
+    async fn directive_fetch_and_verify(
+        &self,
+        state: &ManifestState<'_>,
+        component: &Component<'_>,
+    ) -> Result<(), Error> {
+        let (Some(uri), Some(digest)) = (state.uri, &state.image_digest) else {
+            return Err(Error::ParameterNotSet { position: 0 });
+        };
+        if let Some(size) = state.image_size {
+            self.check_capacity(component, size).await?;
+        }
+        let slot = state.component_slot;
+        self.os_hooks.component_erase(component, slot).await?;
+        let mut hasher = Hasher::new(digest.algorithm())?;
+        let mut written = 0;
+        #[cfg(feature = "encryption")]
+        if let Some(info) = &state.encryption_info {
+            self.os_hooks.decrypt_start(info)?;
+            let mut received = 0;
+            let res = self
+                .fetch(component, slot, uri, &mut async |offset, bytes: &[u8]| {
+                    next_chunk(&mut received, offset, bytes.len())?;
+                    self.write_decrypted(
+                        info,
+                        component,
+                        slot,
+                        &mut written,
+                        Some(&mut hasher),
+                        bytes,
+                    )
+                    .await
+                })
+                .await
+                .and_then(|()| self.os_hooks.decrypt_finish(info));
+            self.erase_on_failure(res, component, slot).await?;
+            return self
+                .verify_fetched(digest.match_hasher(hasher)?, component, slot)
+                .await;
+        }
+        let mut in_order = true;
+        let res = self
+            .fetch(component, slot, uri, &mut async |offset, bytes: &[u8]| {
+                // Out of order chunks can not be hashed, the payload fails verification
+                in_order &= offset == written;
+                if !in_order {
+                    return Ok(());
+                }
+                let mut buf = RwBuf::<O::ReadWriteBufferSize>::new().buf;
+                for chunk in bytes.chunks(buf.len()) {
+                    let buf = &mut buf[..chunk.len()];
+                    buf.copy_from_slice(chunk);
+                    hasher.update(buf);
+                    self.os_hooks
+                        .component_write(component, slot, written, buf)
+                        .await?;
+                    written = access_end(written, buf.len())?;
+                }
+                Ok(())
+            })
+            .await;
+        self.erase_on_failure(res, component, slot).await?;
+        let matched = in_order && digest.match_hasher(hasher)?;
+        self.verify_fetched(matched, component, slot).await
+    }
+
+    /// Erase the component again after a fetched payload failed the digest verification.
+    ///
+    /// The mismatch fails with [`Error::DigestMismatch`], which no soft failure or try each
+    /// recovers from.
+    async fn verify_fetched(
+        &self,
+        matched: bool,
+        component: &Component<'_>,
+        slot: Option<u64>,
+    ) -> Result<(), Error> {
+        if matched {
+            return Ok(());
+        }
+        self.os_hooks.component_erase(component, slot).await?;
+        Err(Error::DigestMismatch { position: 0 })
+    }
+
+    /// Erase the component again after installing a verified or encrypted payload failed.
+    ///
+    /// A partially fetched payload can not be verified anymore, the plaintext of an encrypted
+    /// payload is not authenticated before the decryption finished successfully. Neither must
+    /// remain in the component.
+    async fn erase_on_failure(
+        &self,
+        res: Result<(), Error>,
+        component: &Component<'_>,
+        slot: Option<u64>,
+    ) -> Result<(), Error> {
+        if res.is_err() {
+            self.os_hooks.component_erase(component, slot).await?;
+        }
+        res
+    }
+
+    async fn directive_write(
+        &self,
+        state: &ManifestState<'_>,
+        component: &Component<'_>,
+    ) -> Result<(), Error> {
         if let Some(content) = state.content {
-            self.os_hooks
-                .component_write(component, state.component_slot, 0, content)
+            #[cfg(feature = "encryption")]
+            if let Some(info) = &state.encryption_info {
+                // The ciphertext is larger than the plaintext, only the image size is reliable
+                if let Some(size) = state.image_size {
+                    self.check_capacity(component, size).await?;
+                }
+                self.os_hooks
+                    .component_erase(component, state.component_slot)
+                    .await?;
+                self.os_hooks.decrypt_start(info)?;
+                let mut offset = 0;
+                let res = self
+                    .write_decrypted(
+                        info,
+                        component,
+                        state.component_slot,
+                        &mut offset,
+                        None,
+                        content,
+                    )
+                    .await
+                    .and_then(|()| self.os_hooks.decrypt_finish(info));
+                return self
+                    .erase_on_failure(res, component, state.component_slot)
+                    .await;
+            }
+            self.check_capacity(component, content.len()).await?;
+            self.io_hook(
+                self.os_hooks
+                    .component_erase(component, state.component_slot)
+                    .await,
+            )?;
+            self.io_hook(
+                self.os_hooks
+                    .component_write(component, state.component_slot, 0, content)
+                    .await,
+            )
         } else {
             Err(Error::ParameterNotSet { position: 0 })
         }
     }
 
+    /// Check that a payload of `needed` bytes fits in the component.
+    async fn check_capacity(&self, component: &Component<'_>, needed: usize) -> Result<(), Error> {
+        let available = self.os_hooks.component_capacity(component).await?;
+        if needed > available {
+            return Err(Error::InsufficientCapacity { needed, available });
+        }
+        Ok(())
+    }
+
+    /// Decrypt the ciphertext in chunks and write the plaintext into the component at `offset`,
+    /// advancing the offset with the written plaintext. The plaintext is hashed with the optional
+    /// `hasher` before it is written.
+    #[cfg(feature = "encryption")]
+    async fn write_decrypted(
+        &self,
+        info: &EncryptionInfo<'_>,
+        component: &Component<'_>,
+        slot: Option<u64>,
+        offset: &mut usize,
+        mut hasher: Option<&mut Hasher>,
+        ciphertext: &[u8],
+    ) -> Result<(), Error> {
+        let mut buf = RwBuf::<O::ReadWriteBufferSize>::new().buf;
+        for chunk in ciphertext.chunks(buf.len()) {
+            let plaintext = &mut buf[..chunk.len()];
+            let len = self.os_hooks.decrypt(info, chunk, plaintext)?;
+            let plaintext = plaintext.get(..len).ok_or(Error::CapacityError)?;
+            if let Some(hasher) = hasher.as_deref_mut() {
+                hasher.update(plaintext);
+            }
+            self.os_hooks
+                .component_write(component, slot, *offset, plaintext)
+                .await?;
+            *offset = access_end(*offset, len)?;
+        }
+        Ok(())
+    }
+
+    /// Resolve the selected dependency, then authenticate and validate the dependent manifest.
+    ///
+    /// A dependency counts as one nesting level towards [`SUIT_MAX_NESTING`]. Any failure of the
+    /// dependent manifest fails with [`Error::DependencyFailed`]. A dependency already validated
+    /// by an earlier execution of the sequence is not resolved again.
+    async fn directive_set_dependency_index<N: Nesting>(
+        &self,
+        component_info: &ComponentInfo<'_>,
+        decoder: &mut Decoder<'_>,
+    ) -> Result<(), Error> {
+        let index = decoder.u32()?;
+        if N::DEPTH == 0 {
+            return Err(Error::RecursionLimitExceeded);
+        }
+        if self.validated.is_some_and(|v| v.contains(index)) {
+            return Ok(());
+        }
+        let dependency = component_info.dependency(index)?;
+        let prefix = dependency.map(|d| d.prefix()).transpose()?.flatten();
+        let envelope = self
+            .os_hooks
+            .resolve_dependency(index, prefix.as_ref())
+            .await?;
+        N::validate_dependency(self.os_hooks, envelope, self.images_only)
+            .await
+            .map_err(|e| match e {
+                Error::RecursionLimitExceeded => e,
+                _ => Error::DependencyFailed { index },
+            })?;
+        if let Some(validated) = self.validated {
+            validated.insert(index);
+        }
+        Ok(())
+    }
+
+    async fn directive_invoke(
+        &self,
+        state: &ManifestState<'_>,
+        component: &Component<'_>,
+    ) -> Result<(), Error> {
+        self.io_hook(
+            self.os_hooks
+                .invoke(component, state.component_slot, state.invoke_args)
+                .await,
+        )
+    }
+
     fn decode_reporting_policy(decoder: &mut Decoder) -> Result<ReportingPolicy, Error> {
         Ok(decoder.decode::<ReportingPolicy>()?)
     }
@@ -550,100 +1453,25 @@ impl<'a, O: OperatingHooks> CommandSequenceExecutor<'a, O> {
 mod tests {
     extern crate std;
     use super::*;
-    use crate::component::Component;
+    use crate::asynchooks::{block_on, Blocking};
+    use crate::component::{Component, DependencyIter};
     use crate::digest::{SuitDigest, SuitDigestAlgorithm};
-    use std::cell::Cell;
-    use uuid::{uuid, Uuid};
-
-    struct TestHooks {
-        class: Uuid,
-        vendor: Uuid,
-        buf: Cell<[u8; 4]>,
-    }
-
-    impl TestHooks {
-        fn new(class: Uuid, vendor: Uuid) -> Self {
-            TestHooks {
-                class,
-                vendor,
-                buf: [0u8; _].into(),
-            }
-        }
-    }
-
-    impl OperatingHooks for TestHooks {
-        type ReadWriteBufferSize = generic_array::typenum::U64;
-
-        fn match_vendor_id(
-            &self,
-            uuid: uuid::Uuid,
-            _component: &crate::component::Component,
-        ) -> Result<bool, Error> {
-            Ok(uuid == self.vendor)
-        }
-
-        fn match_class_id(
-            &self,
-            uuid: uuid::Uuid,
-            _component: &crate::component::Component,
-        ) -> Result<bool, Error> {
-            Ok(uuid == self.class)
-        }
-
-        fn component_read(
-            &self,
-            _component: &crate::component::Component,
-            _slot: Option<u64>,
-            offset: usize,
-            bytes: &mut [u8],
-        ) -> Result<(), Error> {
-            if bytes.len() + offset > self.buf.get().len() {
-                return Err(Error::InvalidCommandSequence { position: 0 });
-            }
-            bytes.copy_from_slice(&self.buf.get()[offset..offset + bytes.len()]);
-            Ok(())
-        }
-
-        fn component_write(
-            &self,
-            _component: &crate::component::Component,
-            _slot: Option<u64>,
-            offset: usize,
-            bytes: &[u8],
-        ) -> Result<(), Error> {
-            if bytes.len() + offset > self.buf.get().len() {
-                return Err(Error::InvalidCommandSequence { position: 0 });
-            }
-            let mut buf = self.buf.get();
-            buf[offset..offset + bytes.len()].copy_from_slice(bytes);
-            self.buf.set(buf);
-            Ok(())
-        }
-
-        fn component_capacity(
-            &self,
-            _component: &crate::component::Component,
-        ) -> Result<usize, Error> {
-            Ok(self.buf.get().len())
-        }
-
-        fn component_size(&self, _component: &crate::component::Component) -> Result<usize, Error> {
-            Ok(self.buf.get().len())
-        }
-    }
-
-    fn test_vendor_uuid() -> Uuid {
-        uuid!("fa6b4a53-d5ad-5fdf-be9d-e663e4d41ffe")
-    }
-
-    fn test_class_uuid() -> Uuid {
-        uuid!("1492af14-2569-5e48-bf42-9b2d51f2ab45")
-    }
-
-    fn create_test_hooks() -> TestHooks {
-        let vendor = test_vendor_uuid();
-        let class = test_class_uuid();
-        TestHooks::new(class, vendor)
+    #[cfg(feature = "encryption")]
+    use crate::testhooks::DecryptHooks;
+    use crate::testhooks::{
+        DependencyHooks, InvokeHooks, ReportHooks, StorageHooks, CLASS_ID, LARGE_SLOT, VENDOR_ID,
+    };
+    use std::cell::RefCell;
+
+    impl<'a, O: AsyncOperatingHooks> CommandSequenceExecutor<'a, '_, O> {
+        /// Process the sequence with the full nesting depth, driving the future to completion.
+        fn process_blocking(
+            &self,
+            state: ManifestState<'a>,
+            component_info: &'a ComponentInfo<'a>,
+        ) -> Result<ManifestState<'a>, Error> {
+            block_on(self.process::<MaxNesting>(state, component_info))
+        }
     }
 
     const COMPONENT_NAME: [u8; 3] = [0x81, 0x41, 0x00];
@@ -657,11 +1485,12 @@ mod tests {
     fn invalid_sequence() {
         let input: &[u8] = &std::vec![0x83, 0x14, 0x05, 0x15,];
 
-        let hooks = create_test_hooks();
+        let hooks = StorageHooks::default();
         let info = create_test_component();
-        let sequence = CommandSequenceExecutor::new(input.into(), 0, &hooks);
+        let blocking = Blocking(&hooks);
+        let sequence = CommandSequenceExecutor::new(input.into(), 0, &blocking);
         let state = ManifestState::default();
-        let res = sequence.process(state, &info).unwrap_err();
+        let res = sequence.process_blocking(state, &info).unwrap_err();
         assert_eq!(res, Error::InvalidCommandSequence { position: 0 });
 
         let sequence = CommandSequence::new(input.into(), 0);
@@ -672,30 +1501,40 @@ mod tests {
 
     #[test]
     fn indefinite_length_sequence() {
-        let input: &[u8] = &std::vec![0x9F, 0x14, 0x05, 0xFF];
+        // [_ 20, {5: 2}]
+        let input: &[u8] = &std::vec![0x9F, 0x14, 0xA1, 0x05, 0x02, 0xFF];
 
-        let hooks = create_test_hooks();
+        let hooks = StorageHooks::default();
         let info = create_test_component();
-        let sequence = CommandSequenceExecutor::new(input.into(), 0, &hooks);
+        let blocking = Blocking(&hooks);
+        let sequence = CommandSequenceExecutor::new(input.into(), 0, &blocking);
         let state = ManifestState::default();
-        let res = sequence.process(state, &info).unwrap_err();
-        assert_eq!(res, Error::InvalidCommandSequence { position: 0 });
+        let res = sequence.process_blocking(state, &info).unwrap();
+        assert_eq!(res.component_slot, Some(2));
 
         let sequence = CommandSequence::new(input.into(), 0);
         let properties = sequence.properties();
-        let res = properties.unwrap_err();
-        assert_eq!(res, Error::InvalidCommandSequence { position: 0 });
+        assert_eq!(properties.unwrap(), CommandSequenceProperties::default());
+
+        // Command without argument before the break
+        let input: &[u8] = &std::vec![0x9F, 0x14, 0xA1, 0x05, 0x02, 0x0C, 0xFF];
+        let blocking = Blocking(&hooks);
+        let sequence = CommandSequenceExecutor::new(input.into(), 0, &blocking);
+        let state = ManifestState::default();
+        let res = sequence.process_blocking(state, &info).unwrap_err();
+        assert_eq!(res, Error::InvalidCommandSequence { position: 6 });
     }
 
     #[test]
     fn unset_detection() {
         let input: &[u8] = &std::vec![0x82, 0x00, 0x05];
 
-        let hooks = create_test_hooks();
+        let hooks = StorageHooks::default();
         let info = create_test_component();
-        let sequence = CommandSequenceExecutor::new(input.into(), 0, &hooks);
+        let blocking = Blocking(&hooks);
+        let sequence = CommandSequenceExecutor::new(input.into(), 0, &blocking);
         let state = ManifestState::default();
-        let res = sequence.process(state, &info).unwrap_err();
+        let res = sequence.process_blocking(state, &info).unwrap_err();
         assert_eq!(res, Error::UnsupportedCommand { command: 0 });
 
         let sequence = CommandSequence::new(input.into(), 0);
@@ -708,11 +1547,12 @@ mod tests {
     fn component_switch() {
         let input: &[u8] = &std::vec![0x86, 0x0C, 0x01, 0x14, 0xA1, 0x05, 0x01, 0x0C, 0x00];
         let state = ManifestState::default();
-        let hooks = create_test_hooks();
+        let hooks = StorageHooks::default();
         let info = create_test_component();
-        let sequence = CommandSequenceExecutor::new(input.into(), 0, &hooks);
+        let blocking = Blocking(&hooks);
+        let sequence = CommandSequenceExecutor::new(input.into(), 0, &blocking);
 
-        let res = sequence.process(state, &info).unwrap();
+        let res = sequence.process_blocking(state, &info).unwrap();
         assert_eq!(res.component_slot, None);
 
         let sequence = CommandSequence::new(input.into(), 0);
@@ -725,11 +1565,12 @@ mod tests {
     fn missing_parameter() {
         let input: &[u8] = &std::vec![0x82, 0x01, 0x05];
         let state = ManifestState::default();
-        let hooks = create_test_hooks();
+        let hooks = StorageHooks::default();
         let info = create_test_component();
-        let sequence = CommandSequenceExecutor::new(input.into(), 0, &hooks);
+        let blocking = Blocking(&hooks);
+        let sequence = CommandSequenceExecutor::new(input.into(), 0, &blocking);
 
-        let res = sequence.process(state, &info).unwrap_err();
+        let res = sequence.process_blocking(state, &info).unwrap_err();
         assert_eq!(res, Error::ParameterNotSet { position: 1 });
 
         let sequence = CommandSequence::new(input.into(), 0);
@@ -750,12 +1591,13 @@ mod tests {
             0x02, 0x0F
         ];
 
-        let hooks = create_test_hooks();
-        let sequence = CommandSequenceExecutor::new(input.into(), 0, &hooks);
+        let hooks = StorageHooks::default();
+        let blocking = Blocking(&hooks);
+        let sequence = CommandSequenceExecutor::new(input.into(), 0, &blocking);
         let mut state = ManifestState::default();
         let info = create_test_component();
 
-        let res = sequence.process(state.clone(), &info);
+        let res = sequence.process_blocking(state.clone(), &info);
 
         let digest_bytes: &[u8] = &std::vec![
             0x00, 0x11, 0x22, 0x33, 0x44, 0x55, 0x66, 0x77, 0x88, 0x99, 0xaa, 0xbb, 0xcc, 0xdd,
@@ -763,8 +1605,8 @@ mod tests {
             0x76, 0x54, 0x32, 0x10
         ];
         let digest = SuitDigest::new(SuitDigestAlgorithm::Sha256, digest_bytes.into());
-        state.set_vendor_id(test_vendor_uuid());
-        state.set_class_id(test_class_uuid());
+        state.set_vendor_id(VENDOR_ID);
+        state.set_class_id(CLASS_ID);
         state.set_image_digest(digest);
         state.set_image_size(34768);
 
@@ -787,15 +1629,17 @@ mod tests {
             0x69, 0x5E, 0x48, 0xBF, 0x42, 0x9B, 0x2D, 0x51, 0xF2, 0xAB, 0x45, 0x03, 0x58, 0x24,
             0x82, 0x2F, 0x58, 0x20, 0xB1, 0x6A, 0xA5, 0x6B, 0xE3, 0x88, 0x0D, 0x18, 0xCD, 0x41,
             0xE6, 0x83, 0x84, 0xCF, 0x1E, 0xC8, 0xC1, 0x76, 0x80, 0xC4, 0x5A, 0x02, 0xB1, 0x57,
-            0x5D, 0xC1, 0x51, 0x89, 0x23, 0xAE, 0x8B, 0x0E, 0x0E, 0x19, 0x87, 0xD0, 0x12, 0x44,
-            0x74, 0xBA, 0x25, 0x21, 0x01, 0x0F, 0x02, 0x0F, 0x12, 0x0F, 0x03, 0x0F, 0x06, 0x0F,
+            0x5D, 0xC1, 0x51, 0x89, 0x23, 0xAE, 0x8B, 0x0E, 0x0E, 0x04, 0x12, 0x44, 0x74, 0xBA,
+            0x25, 0x21, 0x01, 0x0F, 0x02, 0x0F, 0x12, 0x0F, 0x03, 0x0F, 0x06, 0x0F,
         ];
         let state = ManifestState::default();
-        let hooks = create_test_hooks();
+        let hooks = StorageHooks::default();
         let info = create_test_component();
 
-        let sequence = CommandSequenceExecutor::new(input.into(), 0, &hooks);
-        let res = sequence.process(state, &info);
+        let blocking = Blocking(&hooks);
+
+        let sequence = CommandSequenceExecutor::new(input.into(), 0, &blocking);
+        let res = sequence.process_blocking(state, &info);
         assert!(res.is_ok());
 
         let sequence = CommandSequence::new(input.into(), 0);
@@ -809,6 +1653,97 @@ mod tests {
         );
     }
 
+    #[test]
+    fn image_size_mismatch() {
+        // Writes four bytes, the image size and the digest cover five bytes
+        let input: &[u8] = &std::vec![
+            0x86, 0x14, 0xA3, 0x03, 0x58, 0x24, 0x82, 0x2F, 0x58, 0x20, 0xB1, 0x6A, 0xA5, 0x6B,
+            0xE3, 0x88, 0x0D, 0x18, 0xCD, 0x41, 0xE6, 0x83, 0x84, 0xCF, 0x1E, 0xC8, 0xC1, 0x76,
+            0x80, 0xC4, 0x5A, 0x02, 0xB1, 0x57, 0x5D, 0xC1, 0x51, 0x89, 0x23, 0xAE, 0x8B, 0x0E,
+            0x0E, 0x05, 0x12, 0x44, 0x74, 0xBA, 0x25, 0x21, 0x12, 0x0F, 0x03, 0x0F,
+        ];
+        let state = ManifestState::default();
+        let hooks = StorageHooks::default();
+        let info = create_test_component();
+
+        let blocking = Blocking(&hooks);
+
+        let sequence = CommandSequenceExecutor::new(input.into(), 0, &blocking);
+        let res = sequence.process_blocking(state, &info);
+        assert_eq!(
+            res,
+            Err(Error::ComponentConditionFailed {
+                index: 0,
+                command: SuitCommand::ImageMatch.into(),
+                position: 52,
+            })
+        );
+    }
+
+    #[test]
+    fn image_size_smaller_than_component() {
+        // Writes four bytes, the image size and the digest cover the first two bytes
+        let input: &[u8] = &std::vec![
+            0x86, 0x14, 0xA3, 0x03, 0x58, 0x24, 0x82, 0x2F, 0x58, 0x20, 0xD0, 0x80, 0xE1, 0xB0,
+            0xDB, 0x75, 0xCF, 0xE3, 0x2C, 0x92, 0x52, 0xF9, 0x11, 0x52, 0x97, 0xC6, 0xDB, 0xC1,
+            0xFF, 0x67, 0xD7, 0x43, 0x2B, 0x48, 0x2C, 0xCE, 0x0E, 0x47, 0x68, 0xA4, 0x78, 0x5C,
+            0x0E, 0x02, 0x12, 0x44, 0x74, 0xBA, 0x25, 0x21, 0x12, 0x0F, 0x03, 0x0F,
+        ];
+        let state = ManifestState::default();
+        let hooks = StorageHooks::default();
+        let info = create_test_component();
+
+        let blocking = Blocking(&hooks);
+
+        let sequence = CommandSequenceExecutor::new(input.into(), 0, &blocking);
+        let res = sequence.process_blocking(state, &info);
+        assert!(res.is_ok());
+    }
+
+    #[test]
+    fn write_insufficient_capacity() {
+        // Five bytes of content for the four byte component
+        let input: &[u8] =
+            &std::vec![0x84, 0x14, 0xA1, 0x12, 0x45, 0x01, 0x02, 0x03, 0x04, 0x05, 0x12, 0x0F];
+        let hooks = StorageHooks::default();
+        let info = create_test_component();
+
+        let state = ManifestState::default();
+        let blocking = Blocking(&hooks);
+        let sequence = CommandSequenceExecutor::new(input.into(), 0, &blocking);
+        let res = sequence.process_blocking(state, &info).unwrap_err();
+        assert_eq!(
+            res,
+            Error::InsufficientCapacity {
+                needed: 5,
+                available: 4
+            }
+        );
+        // Nothing is erased for a payload that does not fit
+        assert_eq!(hooks.erased.get(), 0);
+        assert_eq!(hooks.buf.get(), [0x00; 4]);
+    }
+
+    #[test]
+    fn fetch_insufficient_capacity() {
+        // Image size of 16 bytes for the four byte component
+        let input: &[u8] = &std::vec![0x84, 0x14, 0xA2, 0x0E, 0x10, 0x15, 0x61, 0x78, 0x15, 0x0F];
+        let hooks = StorageHooks::default();
+        let info = create_test_component();
+
+        let state = ManifestState::default();
+        let blocking = Blocking(&hooks);
+        let sequence = CommandSequenceExecutor::new(input.into(), 0, &blocking);
+        let res = sequence.process_blocking(state, &info).unwrap_err();
+        assert_eq!(
+            res,
+            Error::InsufficientCapacity {
+                needed: 16,
+                available: 4
+            }
+        );
+    }
+
     #[test]
     fn try_each() {
         // Try each statement with abort in first arm and set component slot in second
@@ -816,12 +1751,13 @@ mod tests {
             0x82, 0x0F, 0x82, 0x47, 0x84, 0x0E, 0x05, 0x14, 0xA1, 0x05, 0x01, 0x45, 0x82, 0x14,
             0xA1, 0x05, 0x02
         ];
-        let hooks = create_test_hooks();
+        let hooks = StorageHooks::default();
         let info = create_test_component();
 
         let state = ManifestState::default();
-        let sequence = CommandSequenceExecutor::new(input.into(), 0, &hooks);
-        let res = sequence.process(state, &info).unwrap();
+        let blocking = Blocking(&hooks);
+        let sequence = CommandSequenceExecutor::new(input.into(), 0, &blocking);
+        let res = sequence.process_blocking(state, &info).unwrap();
         assert_eq!(res.component_slot, Some(2));
 
         let sequence = CommandSequence::new(input.into(), 0);
@@ -830,37 +1766,128 @@ mod tests {
         assert_eq!(res, CommandSequenceProperties::default());
     }
 
+    #[test]
+    fn try_each_parameters() {
+        // The first sequence sets the component slot and aborts, the second sets the image size
+        let input: &[u8] = &std::vec![
+            0x82, 0x0F, 0x82, 0x47, 0x84, 0x14, 0xA1, 0x05, 0x01, 0x0E, 0x05, 0x45, 0x82, 0x14,
+            0xA1, 0x0E, 0x04
+        ];
+        let hooks = StorageHooks::default();
+        let info = create_test_component();
+
+        let state = ManifestState::default();
+        let blocking = Blocking(&hooks);
+        let sequence = CommandSequenceExecutor::new(input.into(), 0, &blocking);
+        let res = sequence.process_blocking(state, &info).unwrap();
+        assert_eq!(res.component_slot, None);
+        assert_eq!(res.image_size, Some(4));
+    }
+
+    #[test]
+    fn try_each_side_effect() {
+        // The first sequence writes h'AA' and aborts
+        let input: &[u8] = &std::vec![
+            0x82, 0x0F, 0x82, 0x4A, 0x86, 0x14, 0xA1, 0x12, 0x41, 0xAA, 0x12, 0x0F, 0x0E, 0x05,
+            0x40
+        ];
+        let hooks = StorageHooks::default();
+        let info = create_test_component();
+
+        let state = ManifestState::default();
+        let blocking = Blocking(&hooks);
+        let sequence = CommandSequenceExecutor::new(input.into(), 0, &blocking);
+        let res = sequence.process_blocking(state, &info).unwrap_err();
+        assert_eq!(res, Error::TryEachSideEffect { position: 12 });
+
+        // Aborting before the write falls through to the empty sequence
+        let input: &[u8] = &std::vec![
+            0x82, 0x0F, 0x82, 0x4A, 0x86, 0x14, 0xA1, 0x12, 0x41, 0xAA, 0x0E, 0x05, 0x12, 0x0F,
+            0x40
+        ];
+        let hooks = StorageHooks::default();
+        let state = ManifestState::default();
+        let blocking = Blocking(&hooks);
+        let sequence = CommandSequenceExecutor::new(input.into(), 0, &blocking);
+        assert!(sequence.process_blocking(state, &info).is_ok());
+        assert_eq!(hooks.buf.get(), [0u8; 4]);
+    }
+
     #[test]
     fn try_each_fail() {
         let input: &[u8] = &std::vec![0x82, 0x0F, 0x81, 0x43, 0x82, 0x0E, 0x05];
-        let hooks = create_test_hooks();
+        let hooks = StorageHooks::default();
         let info = create_test_component();
 
         let state = ManifestState::default();
-        let sequence = CommandSequenceExecutor::new(input.into(), 0, &hooks);
-        let res = sequence.process(state, &info).unwrap_err();
+        let blocking = Blocking(&hooks);
+        let sequence = CommandSequenceExecutor::new(input.into(), 0, &blocking);
+        let res = sequence.process_blocking(state, &info).unwrap_err();
         assert_eq!(res, Error::TryEachFail { position: 5 });
 
         let input: &[u8] =
             &std::vec![0x82, 0x0F, 0x82, 0x43, 0x82, 0x0E, 0x05, 0x43, 0x82, 0x0E, 0x05];
         let state = ManifestState::default();
-        let sequence = CommandSequenceExecutor::new(input.into(), 0, &hooks);
-        let res = sequence.process(state, &info).unwrap_err();
+        let blocking = Blocking(&hooks);
+        let sequence = CommandSequenceExecutor::new(input.into(), 0, &blocking);
+        let res = sequence.process_blocking(state, &info).unwrap_err();
+        assert_eq!(res, Error::TryEachFail { position: 9 });
+    }
+
+    #[test]
+    fn try_each_class_id() {
+        // Both sequences check the class identifier
+        let input: &[u8] =
+            &std::vec![0x82, 0x0F, 0x82, 0x43, 0x82, 0x02, 0x00, 0x43, 0x82, 0x02, 0x00];
+        let hooks = StorageHooks::default();
+        let info = create_test_component();
+
+        let mut state = ManifestState::default();
+        state.set_class_id(uuid::Uuid::nil());
+        let blocking = Blocking(&hooks);
+        let sequence = CommandSequenceExecutor::new(input.into(), 0, &blocking);
+        let res = sequence.process_blocking(state, &info).unwrap_err();
         assert_eq!(res, Error::TryEachFail { position: 9 });
     }
 
+    #[test]
+    fn try_each_nested() {
+        // The first sequence is a try each aborting in its only sequence
+        let input: &[u8] = &std::vec![
+            0x82, 0x0F, 0x82, 0x47, 0x82, 0x0F, 0x81, 0x43, 0x82, 0x0E, 0x05, 0x43, 0x82, 0x0E,
+            0x05
+        ];
+        let hooks = StorageHooks::default();
+        let info = create_test_component();
+
+        let state = ManifestState::default();
+        let blocking = Blocking(&hooks);
+        let sequence = CommandSequenceExecutor::new(input.into(), 0, &blocking);
+        let res = sequence.process_blocking(state, &info).unwrap_err();
+        assert_eq!(res, Error::TryEachFail { position: 13 });
+
+        // The failing nested try each falls through to the empty sequence
+        let input: &[u8] =
+            &std::vec![0x82, 0x0F, 0x82, 0x47, 0x82, 0x0F, 0x81, 0x43, 0x82, 0x0E, 0x05, 0x40];
+        let state = ManifestState::default();
+        let blocking = Blocking(&hooks);
+        let sequence = CommandSequenceExecutor::new(input.into(), 0, &blocking);
+        assert!(sequence.process_blocking(state, &info).is_ok());
+    }
+
     #[test]
     fn try_each_invalid() {
         let input: &[u8] = &std::vec![
             0x82, 0x0F, 0x82, 0x45, 0x82, 0x14, 0xA1, 0x00, 0x02, 0x45, 0x82, 0x14, 0xA1, 0x05,
             0x02,
         ];
-        let hooks = create_test_hooks();
+        let hooks = StorageHooks::default();
         let info = create_test_component();
 
         let state = ManifestState::default();
-        let sequence = CommandSequenceExecutor::new(input.into(), 0, &hooks);
-        let res = sequence.process(state, &info).unwrap_err();
+        let blocking = Blocking(&hooks);
+        let sequence = CommandSequenceExecutor::new(input.into(), 0, &blocking);
+        let res = sequence.process_blocking(state, &info).unwrap_err();
         assert_eq!(res, Error::UnsupportedParameter { parameter: 0 });
     }
 
@@ -869,24 +1896,26 @@ mod tests {
         let input: &[u8] = &std::vec![
             0x82, 0x0F, 0x82, 0x45, 0x82, 0x14, 0xA1, 0x05, 0x02, 0x43, 0x82, 0x0E, 0x05
         ];
-        let hooks = create_test_hooks();
+        let hooks = StorageHooks::default();
         let info = create_test_component();
 
         let state = ManifestState::default();
-        let sequence = CommandSequenceExecutor::new(input.into(), 0, &hooks);
-        let res = sequence.process(state, &info).unwrap();
+        let blocking = Blocking(&hooks);
+        let sequence = CommandSequenceExecutor::new(input.into(), 0, &blocking);
+        let res = sequence.process_blocking(state, &info).unwrap();
         assert_eq!(res.component_slot, Some(2));
     }
 
     #[test]
     fn try_each_empty() {
         let input: &[u8] = &std::vec![0x82, 0x0F, 0x81, 0x40];
-        let hooks = create_test_hooks();
+        let hooks = StorageHooks::default();
         let info = create_test_component();
 
         let state = ManifestState::default();
-        let sequence = CommandSequenceExecutor::new(input.into(), 0, &hooks);
-        let res = sequence.process(state.clone(), &info);
+        let blocking = Blocking(&hooks);
+        let sequence = CommandSequenceExecutor::new(input.into(), 0, &blocking);
+        let res = sequence.process_blocking(state.clone(), &info);
         assert_eq!(res, Ok(state));
 
         let sequence = CommandSequence::new(input.into(), 0);
@@ -894,4 +1923,939 @@ mod tests {
         let res = properties.unwrap();
         assert_eq!(res, CommandSequenceProperties::default());
     }
+
+    #[test]
+    fn walk_empty_sequences() {
+        // [15, [h'820E05', h''], 32, h''], the empty sequences are skipped like in the execution
+        let input: &[u8] =
+            &std::vec![0x84, 0x0F, 0x82, 0x43, 0x82, 0x0E, 0x05, 0x40, 0x18, 0x20, 0x40];
+        let hooks = StorageHooks::default();
+        let info = create_test_component();
+
+        let state = ManifestState::default();
+        let blocking = Blocking(&hooks);
+        let sequence = CommandSequenceExecutor::new(input.into(), 0, &blocking);
+        assert!(sequence.process_blocking(state, &info).is_ok());
+
+        let sequence = CommandSequence::new(input.into(), 0);
+        let mut found = heapless::Vec::<SuitCommand, 4>::new();
+        assert_eq!(sequence.collect_unsupported(&mut found), Ok(()));
+        assert!(found.is_empty());
+    }
+
+    #[test]
+    fn fetch() {
+        // Set the uri to "x" and fetch into the component
+        let input: &[u8] = &std::vec![0x84, 0x14, 0xA1, 0x15, 0x61, 0x78, 0x15, 0x0F];
+        let hooks = StorageHooks::default();
+        let info = create_test_component();
+
+        let state = ManifestState::default();
+        let blocking = Blocking(&hooks);
+        let sequence = CommandSequenceExecutor::new(input.into(), 0, &blocking);
+        let res = sequence.process_blocking(state, &info).unwrap();
+        assert_eq!(res.uri, Some("x"));
+        assert_eq!(hooks.buf.get(), [0x01, 0x02, 0x03, 0x04]);
+        assert_eq!(hooks.erased.get(), 1);
+
+        let sequence = CommandSequence::new(input.into(), 0);
+        let properties = sequence.properties();
+        let res = properties.unwrap();
+        assert_eq!(res, CommandSequenceProperties::HasSideEffects);
+    }
+
+    #[test]
+    fn fetch_integrated_payload() {
+        // 107({"#a": h'05060708'})
+        let envelope = &[
+            0xD8, 0x6B, 0xA1, 0x62, 0x23, 0x61, 0x44, 0x05, 0x06, 0x07, 0x08,
+        ];
+        let envelope = crate::SuitManifest::from_bytes(envelope)
+            .envelope()
+            .unwrap();
+        let payloads = Some(envelope.integrated_payloads());
+        // Set the uri to "#a" and fetch into the component
+        let input: &[u8] = &std::vec![0x84, 0x14, 0xA1, 0x15, 0x62, 0x23, 0x61, 0x15, 0x0F];
+        let hooks = StorageHooks::default();
+        let info = create_test_component();
+
+        let state = ManifestState::default();
+        let blocking = Blocking(&hooks);
+        let sequence =
+            CommandSequenceExecutor::new(input.into(), 0, &blocking).with_payloads(payloads);
+        assert!(sequence.process_blocking(state, &info).is_ok());
+        assert_eq!(hooks.buf.get(), [0x05, 0x06, 0x07, 0x08]);
+
+        // A fragment without integrated payload is left to the fetch hook
+        let input: &[u8] = &std::vec![0x84, 0x14, 0xA1, 0x15, 0x62, 0x23, 0x62, 0x15, 0x0F];
+        let state = ManifestState::default();
+        let blocking = Blocking(&hooks);
+        let sequence =
+            CommandSequenceExecutor::new(input.into(), 0, &blocking).with_payloads(payloads);
+        assert_eq!(
+            sequence.process_blocking(state, &info).unwrap_err(),
+            Error::ConditionMatchFail { position: 7 }
+        );
+    }
+
+    #[test]
+    fn fetch_and_verify() {
+        // Fetch into the component
+        let input: &[u8] = &std::vec![0x82, 0x15, 0x0F];
+        let hooks = StorageHooks {
+            verify_fetch: true,
+            ..Default::default()
+        };
+        let info = create_test_component();
+        let mut state = ManifestState {
+            uri: Some("x"),
+            ..Default::default()
+        };
+
+        // No digest to verify against
+        let blocking = Blocking(&hooks);
+        let sequence = CommandSequenceExecutor::new(input.into(), 0, &blocking);
+        let res = sequence.process_blocking(state.clone(), &info).unwrap_err();
+        assert_eq!(res, Error::ParameterNotSet { position: 1 });
+
+        // SHA-256 of h'01020304'
+        let digest_bytes: &[u8] = &std::vec![
+            0x9f, 0x64, 0xa7, 0x47, 0xe1, 0xb9, 0x7f, 0x13, 0x1f, 0xab, 0xb6, 0xb4, 0x47, 0x29,
+            0x6c, 0x9b, 0x6f, 0x02, 0x01, 0xe7, 0x9f, 0xb3, 0xc5, 0x35, 0x6e, 0x6c, 0x77, 0xe8,
+            0x9b, 0x6a, 0x80, 0x6a
+        ];
+        state.set_image_digest(SuitDigest::new(
+            SuitDigestAlgorithm::Sha256,
+            digest_bytes.into(),
+        ));
+        let blocking = Blocking(&hooks);
+        let sequence = CommandSequenceExecutor::new(input.into(), 0, &blocking);
+        sequence.process_blocking(state.clone(), &info).unwrap();
+        assert_eq!(hooks.buf.get(), [0x01, 0x02, 0x03, 0x04]);
+        assert_eq!(hooks.erased.get(), 1);
+
+        let digest_bytes: &[u8] = &[0u8; 32];
+        state.set_image_digest(SuitDigest::new(
+            SuitDigestAlgorithm::Sha256,
+            digest_bytes.into(),
+        ));
+        let blocking = Blocking(&hooks);
+        let sequence = CommandSequenceExecutor::new(input.into(), 0, &blocking);
+        let res = sequence.process_blocking(state.clone(), &info).unwrap_err();
+        assert_eq!(res, Error::DigestMismatch { position: 1 });
+        assert_eq!(hooks.buf.get(), [0u8; 4]);
+        // Erased before the first write and after the failed verification
+        assert_eq!(hooks.erased.get(), 3);
+
+        // A try each does not move on to the next sequence after the mismatch
+        let input: &[u8] = &std::vec![0x82, 0x0F, 0x82, 0x43, 0x82, 0x15, 0x0F, 0x41, 0x80];
+        let blocking = Blocking(&hooks);
+        let sequence = CommandSequenceExecutor::new(input.into(), 0, &blocking);
+        let res = sequence.process_blocking(state.clone(), &info).unwrap_err();
+        assert_eq!(res, Error::DigestMismatch { position: 3 });
+
+        // The partially fetched payload is erased when the fetch fails
+        state.uri = Some("y");
+        let input: &[u8] = &std::vec![0x82, 0x15, 0x0F];
+        let blocking = Blocking(&hooks);
+        let sequence = CommandSequenceExecutor::new(input.into(), 0, &blocking);
+        let res = sequence.process_blocking(state, &info).unwrap_err();
+        assert_eq!(res, Error::ConditionMatchFail { position: 1 });
+        assert_eq!(hooks.buf.get(), [0u8; 4]);
+        assert_eq!(hooks.erased.get(), 7);
+    }
+
+    #[test]
+    fn size_overflow() {
+        assert_eq!(access_end(usize::MAX - 2, 2), Ok(usize::MAX));
+        assert_eq!(
+            access_end(usize::MAX - 1, 2),
+            Err(Error::SizeOverflow {
+                offset: usize::MAX - 1,
+                len: 2
+            })
+        );
+
+        let hooks = StorageHooks::default();
+        let info = create_test_component();
+        let res = block_on(super::hash_component(
+            &Blocking(&hooks),
+            info.component(),
+            None,
+            4,
+            usize::MAX,
+            SuitDigestAlgorithm::Sha256,
+        ));
+        assert!(matches!(res, Err(Error::ReadOutOfBounds { offset: 0, .. })));
+
+        // Image size near the end of the address range
+        let mut state = ManifestState::default();
+        let digest_bytes: &[u8] = &[0u8; 32];
+        state.set_image_digest(SuitDigest::new(
+            SuitDigestAlgorithm::Sha256,
+            digest_bytes.into(),
+        ));
+        state.set_image_size(usize::MAX);
+        // Image match
+        let input: &[u8] = &[0x82, 0x03, 0x0F];
+        let blocking = Blocking(&hooks);
+        let sequence = CommandSequenceExecutor::new(input.into(), 0, &blocking);
+        let res = sequence.process_blocking(state, &info).unwrap_err();
+        assert_eq!(
+            res,
+            Error::ComponentConditionFailed {
+                index: 0,
+                command: 3,
+                position: 1,
+            }
+        );
+    }
+
+    #[test]
+    fn fetch_no_uri() {
+        let input: &[u8] = &std::vec![0x82, 0x15, 0x0F];
+        let hooks = StorageHooks::default();
+        let info = create_test_component();
+
+        let state = ManifestState::default();
+        let blocking = Blocking(&hooks);
+        let sequence = CommandSequenceExecutor::new(input.into(), 0, &blocking);
+        let res = sequence.process_blocking(state, &info).unwrap_err();
+        assert_eq!(res, Error::ParameterNotSet { position: 1 });
+        assert_eq!(hooks.buf.get(), [0u8; 4]);
+    }
+
+    #[test]
+    fn invoke() {
+        // Set the invoke args to h'AABB' and invoke the component
+        let input: &[u8] = &std::vec![0x84, 0x14, 0xA1, 0x17, 0x42, 0xAA, 0xBB, 0x17, 0x0F];
+        let hooks = InvokeHooks::default();
+        let info = create_test_component();
+
+        let state = ManifestState::default();
+        let blocking = Blocking(&hooks);
+        let sequence = CommandSequenceExecutor::new(input.into(), 0, &blocking);
+        sequence.process_blocking(state, &info).unwrap();
+        assert_eq!(*hooks.invoked.borrow(), Some(std::vec![0xAA, 0xBB]));
+    }
+
+    #[test]
+    fn run_sequence() {
+        // Run a nested sequence setting the component slot
+        let input: &[u8] = &std::vec![0x82, 0x18, 0x20, 0x45, 0x82, 0x14, 0xA1, 0x05, 0x01];
+        let hooks = StorageHooks::default();
+        let info = create_test_component();
+
+        let state = ManifestState::default();
+        let blocking = Blocking(&hooks);
+        let sequence = CommandSequenceExecutor::new(input.into(), 0, &blocking);
+        let res = sequence.process_blocking(state.clone(), &info);
+        assert_eq!(res, Ok(state));
+    }
+
+    #[test]
+    fn run_sequence_empty() {
+        let input: &[u8] = &std::vec![0x82, 0x18, 0x20, 0x40];
+        let hooks = StorageHooks::default();
+        let info = create_test_component();
+
+        let state = ManifestState::default();
+        let blocking = Blocking(&hooks);
+        let sequence = CommandSequenceExecutor::new(input.into(), 0, &blocking);
+        let res = sequence.process_blocking(state.clone(), &info);
+        assert_eq!(res, Ok(state));
+    }
+
+    #[test]
+    fn run_sequence_fail() {
+        // Nested sequence aborts
+        let input: &[u8] = &std::vec![0x82, 0x18, 0x20, 0x43, 0x82, 0x0E, 0x05];
+        let hooks = StorageHooks::default();
+        let info = create_test_component();
+
+        let state = ManifestState::default();
+        let blocking = Blocking(&hooks);
+        let sequence = CommandSequenceExecutor::new(input.into(), 0, &blocking);
+        let res = sequence.process_blocking(state, &info).unwrap_err();
+        assert!(matches!(res, Error::ManifestAbort { .. }));
+
+        let sequence = CommandSequence::new(input.into(), 0);
+        let properties = sequence.properties();
+        let res = properties.unwrap();
+        assert_eq!(res, CommandSequenceProperties::default());
+    }
+
+    #[test]
+    fn large_component_slot() {
+        let input: &[u8] = &std::vec![
+            0x84, 0x14, 0xA1, 0x05, 0x1B, 0x00, 0x00, 0x00, 0x01, 0x00, 0x00, 0x00, 0x01, 0x05,
+            0x0F
+        ];
+        let hooks = StorageHooks::default();
+        let info = create_test_component();
+
+        let state = ManifestState::default();
+        let blocking = Blocking(&hooks);
+        let sequence = CommandSequenceExecutor::new(input.into(), 0, &blocking);
+        let res = sequence.process_blocking(state, &info).unwrap();
+        assert_eq!(res.component_slot, Some(LARGE_SLOT));
+
+        // Differs from the slot of the component only above the u32 range
+        let input: &[u8] = &std::vec![
+            0x84, 0x14, 0xA1, 0x05, 0x1B, 0x00, 0x00, 0x00, 0x02, 0x00, 0x00, 0x00, 0x01, 0x05,
+            0x0F
+        ];
+        let state = ManifestState::default();
+        let blocking = Blocking(&hooks);
+        let sequence = CommandSequenceExecutor::new(input.into(), 0, &blocking);
+        let res = sequence.process_blocking(state, &info).unwrap_err();
+        assert_eq!(
+            res,
+            Error::ComponentSlotMismatch {
+                slot: 0x2_0000_0001,
+                position: 13,
+            }
+        );
+    }
+
+    #[test]
+    fn command_limit() {
+        let hooks = StorageHooks::default();
+        let info = create_test_component();
+
+        // Array claiming 2^30 elements
+        let input = [0x9A, 0x40, 0x00, 0x00, 0x00];
+        let blocking = Blocking(&hooks);
+        let sequence = CommandSequenceExecutor::new(input.as_slice().into(), 0, &blocking);
+        let res = sequence.process_blocking(ManifestState::default(), &info);
+        assert_eq!(res, Err(Error::InvalidCommandSequence { position: 0 }));
+
+        // Run sequence with 200 empty override parameters commands, twice
+        let mut inner = std::vec![0x99, 0x01, 0x90];
+        for _ in 0..200 {
+            inner.extend_from_slice(&[0x14, 0xA0]);
+        }
+        let mut input = std::vec![0x84];
+        for _ in 0..2 {
+            input.extend_from_slice(&[0x18, 0x20, 0x59, 0x01, 0x93]);
+            input.extend_from_slice(&inner);
+        }
+        let blocking = Blocking(&hooks);
+        let sequence = CommandSequenceExecutor::new(input.as_slice().into(), 0, &blocking);
+        let res = sequence.process_blocking(ManifestState::default(), &info);
+        assert!(matches!(res, Err(Error::InvalidCommandSequence { .. })));
+
+        // A single run sequence stays within the limit
+        input[0] = 0x82;
+        input.truncate(5 + inner.len() + 1);
+        let blocking = Blocking(&hooks);
+        let sequence = CommandSequenceExecutor::new(input.as_slice().into(), 0, &blocking);
+        assert!(sequence
+            .process_blocking(ManifestState::default(), &info)
+            .is_ok());
+    }
+
+    #[test]
+    fn nesting_limit() {
+        // Wrap the abort command in `depth` nested run sequence commands
+        let nested = |depth| {
+            let mut sequence = std::vec![0x82, 0x0E, 0x05];
+            for _ in 0..depth {
+                let mut outer = std::vec![0x82, 0x18, 0x20, 0x40 | sequence.len() as u8];
+                outer.extend_from_slice(&sequence);
+                sequence = outer;
+            }
+            sequence
+        };
+        let hooks = StorageHooks::default();
+        let info = create_test_component();
+
+        let input = nested(SUIT_MAX_NESTING);
+        let blocking = Blocking(&hooks);
+        let sequence = CommandSequenceExecutor::new(input.as_slice().into(), 0, &blocking);
+        let res = sequence.process_blocking(ManifestState::default(), &info);
+        assert!(matches!(res, Err(Error::ManifestAbort { .. })));
+        assert!(CommandSequence::new(input.as_slice().into(), 0)
+            .properties()
+            .is_ok());
+
+        let input = nested(SUIT_MAX_NESTING + 1);
+        let blocking = Blocking(&hooks);
+        let sequence = CommandSequenceExecutor::new(input.as_slice().into(), 0, &blocking);
+        let res = sequence.process_blocking(ManifestState::default(), &info);
+        assert_eq!(res, Err(Error::RecursionLimitExceeded));
+        assert_eq!(
+            CommandSequence::new(input.as_slice().into(), 0).properties(),
+            Err(Error::RecursionLimitExceeded)
+        );
+    }
+
+    #[test]
+    fn use_before() {
+        // Expires at 2000, the current time is 1000
+        let input: &[u8] = &std::vec![0x84, 0x14, 0xA1, 0x04, 0x19, 0x07, 0xD0, 0x04, 0x0F];
+        let hooks = StorageHooks::default();
+        let info = create_test_component();
+
+        let state = ManifestState::default();
+        let blocking = Blocking(&hooks);
+        let sequence = CommandSequenceExecutor::new(input.into(), 0, &blocking);
+        assert!(sequence.process_blocking(state, &info).is_ok());
+    }
+
+    #[test]
+    fn use_before_expired() {
+        // Expired at 1000
+        let input: &[u8] = &std::vec![0x84, 0x14, 0xA1, 0x04, 0x19, 0x03, 0xE8, 0x04, 0x0F];
+        let hooks = StorageHooks::default();
+        let info = ComponentInfo::new(Component::from_bytes(&COMPONENT_NAME), 2);
+
+        let state = ManifestState::default();
+        let blocking = Blocking(&hooks);
+        let sequence = CommandSequenceExecutor::new(input.into(), 0, &blocking);
+        let res = sequence.process_blocking(state, &info).unwrap_err();
+        assert_eq!(
+            res,
+            Error::ComponentConditionFailed {
+                index: 2,
+                command: SuitCommand::UseBefore.into(),
+                position: 7,
+            }
+        );
+    }
+
+    #[test]
+    fn check_content() {
+        // Content matches the (empty) component
+        let input: &[u8] =
+            &std::vec![0x84, 0x14, 0xA1, 0x12, 0x44, 0x00, 0x00, 0x00, 0x00, 0x06, 0x0F];
+        let hooks = StorageHooks::default();
+        let info = create_test_component();
+
+        let state = ManifestState::default();
+        let blocking = Blocking(&hooks);
+        let sequence = CommandSequenceExecutor::new(input.into(), 0, &blocking);
+        assert!(sequence.process_blocking(state, &info).is_ok());
+    }
+
+    #[test]
+    fn check_content_digest() {
+        // Digest of the (empty) component
+        let input: &[u8] = &std::vec![
+            0x84, 0x14, 0xA1, 0x03, 0x58, 0x24, 0x82, 0x2F, 0x58, 0x20, 0xDF, 0x3F, 0x61, 0x98,
+            0x04, 0xA9, 0x2F, 0xDB, 0x40, 0x57, 0x19, 0x2D, 0xC4, 0x3D, 0xD7, 0x48, 0xEA, 0x77,
+            0x8A, 0xDC, 0x52, 0xBC, 0x49, 0x8C, 0xE8, 0x05, 0x24, 0xC0, 0x14, 0xB8, 0x11, 0x19,
+            0x06, 0x0F
+        ];
+        let hooks = StorageHooks::default();
+        let info = create_test_component();
+
+        let state = ManifestState::default();
+        let blocking = Blocking(&hooks);
+        let sequence = CommandSequenceExecutor::new(input.into(), 0, &blocking);
+        assert!(sequence.process_blocking(state, &info).is_ok());
+
+        // The mismatching content takes precedence over the matching digest
+        let mut input = input.to_vec();
+        input[0] = 0x86;
+        input.splice(42..42, [0x14, 0xA1, 0x12, 0x41, 0x01]);
+        let state = ManifestState::default();
+        let blocking = Blocking(&hooks);
+        let sequence = CommandSequenceExecutor::new(input.as_slice().into(), 0, &blocking);
+        let res = sequence.process_blocking(state, &info).unwrap_err();
+        assert_eq!(
+            res,
+            Error::ComponentConditionFailed {
+                index: 0,
+                command: SuitCommand::CheckContent.into(),
+                position: 47,
+            }
+        );
+
+        // Neither the content nor the digest is set
+        let input: &[u8] = &std::vec![0x82, 0x06, 0x0F];
+        let state = ManifestState::default();
+        let blocking = Blocking(&hooks);
+        let sequence = CommandSequenceExecutor::new(input.into(), 0, &blocking);
+        let res = sequence.process_blocking(state, &info).unwrap_err();
+        assert_eq!(res, Error::ParameterNotSet { position: 1 });
+    }
+
+    #[test]
+    fn check_content_mismatch() {
+        // Last byte differs
+        let input: &[u8] =
+            &std::vec![0x84, 0x14, 0xA1, 0x12, 0x44, 0x00, 0x00, 0x00, 0x01, 0x06, 0x0F];
+        let hooks = StorageHooks::default();
+        let info = create_test_component();
+
+        let state = ManifestState::default();
+        let blocking = Blocking(&hooks);
+        let sequence = CommandSequenceExecutor::new(input.into(), 0, &blocking);
+        let res = sequence.process_blocking(state, &info).unwrap_err();
+        assert_eq!(
+            res,
+            Error::ComponentConditionFailed {
+                index: 0,
+                command: SuitCommand::CheckContent.into(),
+                position: 9,
+            }
+        );
+
+        // Content is shorter than the component
+        let input: &[u8] = &std::vec![0x84, 0x14, 0xA1, 0x12, 0x42, 0x00, 0x00, 0x06, 0x0F];
+        let state = ManifestState::default();
+        let blocking = Blocking(&hooks);
+        let sequence = CommandSequenceExecutor::new(input.into(), 0, &blocking);
+        let res = sequence.process_blocking(state, &info).unwrap_err();
+        assert_eq!(
+            res,
+            Error::ComponentConditionFailed {
+                index: 0,
+                command: SuitCommand::CheckContent.into(),
+                position: 7,
+            }
+        );
+    }
+
+    #[test]
+    fn soft_failure() {
+        // Nested sequence enables soft failure and aborts, processing continues afterwards
+        let input: &[u8] = &std::vec![
+            0x84, 0x18, 0x20, 0x47, 0x84, 0x14, 0xA1, 0x0D, 0xF5, 0x0E, 0x05, 0x14, 0xA1, 0x05,
+            0x01
+        ];
+        let hooks = StorageHooks::default();
+        let info = create_test_component();
+
+        let state = ManifestState::default();
+        let blocking = Blocking(&hooks);
+        let sequence = CommandSequenceExecutor::new(input.into(), 0, &blocking);
+        let res = sequence.process_blocking(state, &info).unwrap();
+        assert_eq!(res.component_slot, Some(1));
+        assert_eq!(res.soft_failure, None);
+    }
+
+    #[test]
+    fn soft_failure_directive() {
+        // A nested sequence disables strict order and enables soft failure, the failing invoke
+        // hook ends the nested sequence and the component slot is set afterwards
+        let input: &[u8] = &std::vec![
+            0x84, 0x18, 0x20, 0x49, 0x84, 0x14, 0xA2, 0x0C, 0xF4, 0x0D, 0xF5, 0x17, 0x0F, 0x14,
+            0xA1, 0x05, 0x01
+        ];
+        let hooks = StorageHooks::default();
+        let info = create_test_component();
+
+        let state = ManifestState::default();
+        let blocking = Blocking(&hooks);
+        let sequence = CommandSequenceExecutor::new(input.into(), 0, &blocking);
+        let res = sequence.process_blocking(state, &info).unwrap();
+        assert_eq!(res.component_slot, Some(1));
+
+        // A write without content is not a hook failure and fails the sequence
+        let input: &[u8] = &std::vec![
+            0x84, 0x18, 0x20, 0x49, 0x84, 0x14, 0xA2, 0x0C, 0xF4, 0x0D, 0xF5, 0x12, 0x0F, 0x14,
+            0xA1, 0x05, 0x01
+        ];
+        let state = ManifestState::default();
+        let blocking = Blocking(&hooks);
+        let sequence = CommandSequenceExecutor::new(input.into(), 0, &blocking);
+        let res = sequence.process_blocking(state, &info).unwrap_err();
+        assert_eq!(res, Error::ParameterNotSet { position: 11 });
+
+        // Soft failure of directives only applies inside try each and run sequence
+        let input: &[u8] = &std::vec![
+            0x86, 0x14, 0xA2, 0x0C, 0xF4, 0x0D, 0xF5, 0x17, 0x0F, 0x14, 0xA1, 0x05, 0x01
+        ];
+        let state = ManifestState::default();
+        let blocking = Blocking(&hooks);
+        let sequence = CommandSequenceExecutor::new(input.into(), 0, &blocking);
+        let res = sequence.process_blocking(state, &info).unwrap_err();
+        assert_eq!(
+            res,
+            Error::UnsupportedCommand {
+                command: SuitCommand::Invoke.into()
+            }
+        );
+
+        // In strict order the directive failure fails the sequence
+        let input: &[u8] = &std::vec![
+            0x84, 0x18, 0x20, 0x47, 0x84, 0x14, 0xA1, 0x0D, 0xF5, 0x17, 0x0F, 0x14, 0xA1, 0x05,
+            0x01
+        ];
+        let state = ManifestState::default();
+        let blocking = Blocking(&hooks);
+        let sequence = CommandSequenceExecutor::new(input.into(), 0, &blocking);
+        let res = sequence.process_blocking(state, &info).unwrap_err();
+        assert_eq!(
+            res,
+            Error::UnsupportedCommand {
+                command: SuitCommand::Invoke.into()
+            }
+        );
+    }
+
+    #[test]
+    fn soft_failure_scoped() {
+        // Soft failure set inside a try each arm does not leak out of it
+        let input: &[u8] = &std::vec![0x82, 0x0F, 0x81, 0x45, 0x82, 0x14, 0xA1, 0x0D, 0xF5];
+        let hooks = StorageHooks::default();
+        let info = create_test_component();
+
+        let state = ManifestState::default();
+        let blocking = Blocking(&hooks);
+        let sequence = CommandSequenceExecutor::new(input.into(), 0, &blocking);
+        let res = sequence.process_blocking(state, &info).unwrap();
+        assert_eq!(res.soft_failure, None);
+    }
+
+    #[test]
+    fn set_dependency_index() {
+        // Select and validate dependency 1, the component slot override applies to the component
+        let input: &[u8] = &std::vec![0x86, 0x0D, 0x01, 0x14, 0xA1, 0x05, 0x01, 0x0C, 0x00];
+        let hooks = DependencyHooks::new(0x0C);
+        let info = create_test_component();
+
+        let state = ManifestState::default();
+        let blocking = Blocking(&hooks);
+        let sequence = CommandSequenceExecutor::new(input.into(), 0, &blocking);
+        let res = sequence.process_blocking(state, &info).unwrap();
+        assert_eq!(res.component_slot, Some(1));
+        assert_eq!(hooks.resolved.get(), Some((1, false)));
+
+        // The dependent manifest aborts its validation
+        let hooks = DependencyHooks::new(0x0E);
+        let state = ManifestState::default();
+        let blocking = Blocking(&hooks);
+        let sequence = CommandSequenceExecutor::new(input.into(), 0, &blocking);
+        let res = sequence.process_blocking(state, &info);
+        assert_eq!(res.unwrap_err(), Error::DependencyFailed { index: 1 });
+
+        // The dependent manifest selects itself as dependency
+        let hooks = DependencyHooks::new(0x0D);
+        let state = ManifestState::default();
+        let blocking = Blocking(&hooks);
+        let sequence = CommandSequenceExecutor::new(input.into(), 0, &blocking);
+        let res = sequence.process_blocking(state, &info);
+        assert_eq!(res.unwrap_err(), Error::RecursionLimitExceeded);
+    }
+
+    #[test]
+    fn set_dependency_index_once() {
+        // The dependent manifest invokes its component during validation
+        let input: &[u8] = &std::vec![0x82, 0x0D, 0x01];
+        let hooks = DependencyHooks::new(0x17);
+        let blocking = Blocking(&hooks);
+        let info = create_test_component();
+
+        // Verifying the images skips the invoke, the dependency is resolved once per sequence
+        let sequence = CommandSequence::new(input.into(), 0);
+        for resolved in [Some((1, false)), None] {
+            let state = ManifestState::default();
+            block_on(sequence.verify_images::<MaxNesting>(state, &info, &blocking)).unwrap();
+            assert_eq!(hooks.resolved.take(), resolved);
+        }
+
+        // The full validation invokes the component, unsupported by the hooks
+        let sequence = CommandSequence::new(input.into(), 0);
+        let state = ManifestState::default();
+        let res = block_on(sequence.execute::<MaxNesting>(state, &info, &blocking, None));
+        assert_eq!(res.unwrap_err(), Error::DependencyFailed { index: 1 });
+    }
+
+    #[test]
+    fn set_dependency_index_prefix() {
+        // Dependency 1 has the prefix [h'01']
+        let dependencies = std::vec![0xA1, 0x01, 0xA1, 0x01, 0x81, 0x41, 0x01];
+        let input: &[u8] = &std::vec![0x82, 0x0D, 0x01];
+        let hooks = DependencyHooks::new(0x0C);
+        let info = create_test_component()
+            .with_dependencies(DependencyIter::new(&dependencies, 0).unwrap());
+
+        let state = ManifestState::default();
+        let blocking = Blocking(&hooks);
+        let sequence = CommandSequenceExecutor::new(input.into(), 0, &blocking);
+        sequence.process_blocking(state, &info).unwrap();
+        assert_eq!(hooks.resolved.get(), Some((1, true)));
+    }
+
+    #[test]
+    fn reporting_policy() {
+        let input: &[u8] = &std::vec![0x82, 0x0E, 0x05];
+        let hooks = ReportHooks::default();
+        let info = create_test_component();
+
+        let state = ManifestState::default();
+        let blocking = Blocking(&hooks);
+        let sequence = CommandSequenceExecutor::new(input.into(), 0, &blocking);
+        let _ = sequence.process_blocking(state, &info);
+        let (command, policy) = hooks.policy.get().unwrap();
+        assert_eq!(command, SuitCommand::Abort);
+        assert!(policy.send_record_on_success());
+        assert!(!policy.send_record_on_failure());
+        assert!(policy.add_sysinfo_on_success());
+        assert!(!policy.add_sysinfo_on_failure());
+    }
+
+    #[test]
+    fn report() {
+        // Fetch with all reporting bits, then abort recording failures only
+        let input: &[u8] = &std::vec![0x86, 0x14, 0xA1, 0x15, 0x61, 0x78, 0x15, 0x0F, 0x0E, 0x02];
+        let hooks = StorageHooks::default();
+        let info = create_test_component();
+        let report = RefCell::new(crate::report::SuitReport::<4>::new());
+
+        let state = ManifestState::default();
+        let blocking = Blocking(&hooks);
+        let sequence =
+            CommandSequenceExecutor::new(input.into(), 0, &blocking).with_report(Some(&report));
+        assert!(sequence.process_blocking(state, &info).is_err());
+        let report = report.into_inner();
+        let records = report.records();
+        assert_eq!(records.len(), 2);
+        assert_eq!(records[0].command(), SuitCommand::Fetch);
+        assert_eq!(records[0].result(), Ok(()));
+        assert!(records[0].sysinfo());
+        assert_eq!(records[1].command(), SuitCommand::Abort);
+        assert!(matches!(
+            records[1].result(),
+            Err(Error::ManifestAbort { .. })
+        ));
+        assert!(!records[1].sysinfo());
+        assert!(!report.is_truncated());
+    }
+
+    #[test]
+    fn emit_record() {
+        let input: &[u8] = &std::vec![0x84, 0x14, 0xA1, 0x15, 0x61, 0x78, 0x15, 0x02];
+        let hooks = ReportHooks::default();
+        let info = create_test_component();
+
+        let state = ManifestState::default();
+        let blocking = Blocking(&hooks);
+        let sequence = CommandSequenceExecutor::new(input.into(), 0, &blocking);
+        sequence.process_blocking(state, &info).unwrap();
+        // Successful fetch with a policy only reporting failures
+        assert!(hooks.records.borrow().is_empty());
+
+        let input: &[u8] = &std::vec![0x82, 0x0E, 0x02];
+        let state = ManifestState::default();
+        let blocking = Blocking(&hooks);
+        let sequence = CommandSequenceExecutor::new(input.into(), 0, &blocking);
+        assert!(sequence.process_blocking(state, &info).is_err());
+        let records = hooks.records.borrow();
+        assert_eq!(records.len(), 1);
+        assert_eq!(records[0].command(), SuitCommand::Abort);
+        assert_eq!(records[0].component_index(), 0);
+        assert!(records[0].result().is_err());
+    }
+
+    #[test]
+    fn set_component_identifier() {
+        // Select the component by identifier and abort
+        let mut input = std::vec![0x84, 0x0C, 0x81];
+        input.extend_from_slice(&COMPONENT_NAME);
+        input.extend_from_slice(&[0x0E, 0x00]);
+        let hooks = StorageHooks::default();
+        let info = create_test_component();
+
+        let state = ManifestState::default();
+        let blocking = Blocking(&hooks);
+        let sequence = CommandSequenceExecutor::new(input.as_slice().into(), 0, &blocking);
+        assert!(sequence.process_blocking(state, &info).is_err());
+
+        // Select an unknown component, the abort is skipped
+        let input: &[u8] = &std::vec![0x84, 0x0C, 0x81, 0x81, 0x41, 0xFF, 0x0E, 0x00];
+        let state = ManifestState::default();
+        let blocking = Blocking(&hooks);
+        let sequence = CommandSequenceExecutor::new(input.into(), 0, &blocking);
+        assert!(sequence.process_blocking(state, &info).is_ok());
+    }
+
+    #[test]
+    fn hash_component() {
+        let hooks = StorageHooks::default();
+        hooks.buf.set([0x61, 0x62, 0x63, 0x64]);
+        let info = create_test_component();
+
+        let hasher = block_on(super::hash_component(
+            &Blocking(&hooks),
+            info.component(),
+            None,
+            4,
+            3,
+            SuitDigestAlgorithm::Sha256,
+        ))
+        .unwrap();
+        // SHA-256 of "abc"
+        assert_eq!(
+            hasher.finalize().unwrap().as_slice(),
+            &[
+                0xba, 0x78, 0x16, 0xbf, 0x8f, 0x01, 0xcf, 0xea, 0x41, 0x41, 0x40, 0xde, 0x5d, 0xae,
+                0x22, 0x23, 0xb0, 0x03, 0x61, 0xa3, 0x96, 0x17, 0x7a, 0x9c, 0xb4, 0x10, 0xff, 0x61,
+                0xf2, 0x00, 0x15, 0xad
+            ]
+        );
+
+        let hasher = block_on(super::hash_component(
+            &Blocking(&hooks),
+            info.component(),
+            None,
+            4,
+            0,
+            SuitDigestAlgorithm::Sha256,
+        ))
+        .unwrap();
+        // SHA-256 of the empty input
+        assert_eq!(
+            hasher.finalize().unwrap().as_slice(),
+            &[
+                0xe3, 0xb0, 0xc4, 0x42, 0x98, 0xfc, 0x1c, 0x14, 0x9a, 0xfb, 0xf4, 0xc8, 0x99, 0x6f,
+                0xb9, 0x24, 0x27, 0xae, 0x41, 0xe4, 0x64, 0x9b, 0x93, 0x4c, 0xa4, 0x95, 0x99, 0x1b,
+                0x78, 0x52, 0xb8, 0x55
+            ]
+        );
+    }
+
+    #[test]
+    fn read_out_of_bounds() {
+        let hooks = StorageHooks::default();
+        hooks.buf.set([0x61, 0x62, 0x63, 0x64]);
+        let info = create_test_component();
+
+        let res = block_on(super::hash_component(
+            &Blocking(&hooks),
+            info.component(),
+            None,
+            4,
+            5,
+            SuitDigestAlgorithm::Sha256,
+        ));
+        assert_eq!(
+            res.err(),
+            Some(Error::ReadOutOfBounds {
+                offset: 0,
+                len: 5,
+                size: 4
+            })
+        );
+
+        let mut buf = [0; 2];
+        let res = block_on(read_checked(
+            &Blocking(&hooks),
+            info.component(),
+            None,
+            4,
+            usize::MAX,
+            &mut buf,
+        ));
+        assert!(matches!(res, Err(Error::ReadOutOfBounds { .. })));
+    }
+
+    #[cfg(feature = "encryption")]
+    const ENCRYPTION_INFO: [u8; 11] = [
+        // h'83 43A10103 A1054101 F6', COSE_Encrypt0 with A256GCM
+        0x4A, 0x83, 0x43, 0xA1, 0x01, 0x03, 0xA1, 0x05, 0x41, 0x01, 0xF6,
+    ];
+
+    #[test]
+    #[cfg(feature = "encryption")]
+    fn write_encrypted() {
+        let mut input = std::vec![0x84, 0x14, 0xA2, 0x13];
+        input.extend_from_slice(&ENCRYPTION_INFO);
+        input.extend_from_slice(&[0x12, 0x44, 0xFE, 0xFD, 0xFC, 0xFB, 0x12, 0x0F]);
+        let hooks = DecryptHooks::default();
+        let info = create_test_component();
+
+        let state = ManifestState::default();
+        let blocking = Blocking(&hooks);
+        let sequence = CommandSequenceExecutor::new(input.as_slice().into(), 0, &blocking);
+        sequence.process_blocking(state, &info).unwrap();
+        assert_eq!(hooks.storage.buf.get(), [0x01, 0x02, 0x03, 0x04]);
+        assert!(hooks.decrypted.get());
+    }
+
+    #[test]
+    #[cfg(feature = "encryption")]
+    fn write_encrypted_tag_mismatch() {
+        let mut input = std::vec![0x84, 0x14, 0xA2, 0x13];
+        input.extend_from_slice(&ENCRYPTION_INFO);
+        input.extend_from_slice(&[0x12, 0x44, 0xFE, 0xFD, 0xFC, 0xFB, 0x12, 0x0F]);
+        let hooks = DecryptHooks {
+            tag_mismatch: true,
+            ..Default::default()
+        };
+        let info = create_test_component();
+
+        // The unauthenticated plaintext is erased again
+        let state = ManifestState::default();
+        let blocking = Blocking(&hooks);
+        let sequence = CommandSequenceExecutor::new(input.as_slice().into(), 0, &blocking);
+        let res = sequence.process_blocking(state, &info);
+        assert_eq!(res.unwrap_err(), Error::AuthenticationFailure);
+        assert_eq!(hooks.storage.buf.get(), [0x00; 4]);
+        assert_eq!(hooks.storage.erased.get(), 2);
+    }
+
+    #[test]
+    #[cfg(feature = "encryption")]
+    fn write_encrypted_soft_failure() {
+        // A nested sequence with soft failure and without strict order writes with a bad tag
+        let mut input =
+            std::vec![0x84, 0x18, 0x20, 0x58, 0x1B, 0x84, 0x14, 0xA4, 0x0C, 0xF4, 0x0D, 0xF5, 0x13];
+        input.extend_from_slice(&ENCRYPTION_INFO);
+        input.extend_from_slice(&[0x12, 0x44, 0xFE, 0xFD, 0xFC, 0xFB, 0x12, 0x0F]);
+        input.extend_from_slice(&[0x14, 0xA1, 0x05, 0x01]);
+        let hooks = DecryptHooks {
+            tag_mismatch: true,
+            ..Default::default()
+        };
+        let info = create_test_component();
+
+        // The integrity failure is never a soft failure
+        let state = ManifestState::default();
+        let blocking = Blocking(&hooks);
+        let sequence = CommandSequenceExecutor::new(input.as_slice().into(), 0, &blocking);
+        let res = sequence.process_blocking(state, &info);
+        assert_eq!(res.unwrap_err(), Error::AuthenticationFailure);
+        assert_eq!(hooks.storage.buf.get(), [0x00; 4]);
+    }
+
+    #[test]
+    #[cfg(feature = "encryption")]
+    fn fetch_encrypted() {
+        let mut input = std::vec![0x84, 0x14, 0xA2, 0x13];
+        input.extend_from_slice(&ENCRYPTION_INFO);
+        input.extend_from_slice(&[0x15, 0x61, 0x78, 0x15, 0x0F]);
+        let hooks = DecryptHooks::default();
+        let info = create_test_component();
+
+        let state = ManifestState::default();
+        let blocking = Blocking(&hooks);
+        let sequence = CommandSequenceExecutor::new(input.as_slice().into(), 0, &blocking);
+        sequence.process_blocking(state, &info).unwrap();
+        assert_eq!(hooks.storage.buf.get(), [0xFE, 0xFD, 0xFC, 0xFB]);
+        assert!(hooks.decrypted.get());
+        assert_eq!(hooks.started.get(), 1);
+
+        // The decryption restarts for the next payload, whose chunks arrive out of order
+        let mut input = std::vec![0x84, 0x14, 0xA2, 0x13];
+        input.extend_from_slice(&ENCRYPTION_INFO);
+        input.extend_from_slice(&[0x15, 0x61, 0x7A, 0x15, 0x0F]);
+        let state = ManifestState::default();
+        let blocking = Blocking(&hooks);
+        let sequence = CommandSequenceExecutor::new(input.as_slice().into(), 0, &blocking);
+        let res = sequence.process_blocking(state, &info);
+        assert_eq!(
+            res.unwrap_err(),
+            Error::ChunkOutOfOrder {
+                offset: 2,
+                expected: 0
+            }
+        );
+        assert_eq!(hooks.started.get(), 2);
+        assert!(!hooks.decrypted.get());
+        assert_eq!(hooks.storage.buf.get(), [0x00; 4]);
+    }
 }
@@ -4,7 +4,10 @@ use crate::error::Error;
 use heapless::string::String;
 use itertools::Itertools;
 use minicbor::bytes::ByteSlice;
+use minicbor::data::Type;
 use minicbor::decode::{ArrayIter, Decode, Decoder};
+use minicbor::encode::{Encode, Encoder, Write};
+use minicbor::CborLen;
 
 /// Represent the component index parameter in the SUIT manifest.
 ///
@@ -44,12 +47,48 @@ impl<'a> ComponentIndex<'a> {
     }
 }
 
+impl<'a, C> Decode<'a, C> for ComponentIndex<'a> {
+    fn decode(d: &mut Decoder<'a>, _: &mut C) -> Result<Self, minicbor::decode::Error> {
+        let position = d.position();
+        match d.datatype()? {
+            Type::Bool => {
+                if d.bool()? {
+                    Ok(Self::All)
+                } else {
+                    Err(minicbor::decode::Error::message("component index false").at(position))
+                }
+            }
+            Type::U8 | Type::U16 | Type::U32 => Ok(Self::Index(d.u32()?)),
+            Type::Array => Ok(Self::Set(d.sub_cbor()?.into())),
+            t => Err(minicbor::decode::Error::type_mismatch(t).at(position)),
+        }
+    }
+}
+
 /// Represents a single component in a manifest
+///
+/// Equality compares the encoded identifiers byte by byte, use [`Component::identifier_eq`] to
+/// compare identifiers encoded with different CBOR lengths.
 #[derive(Copy, Clone, PartialEq, Debug)]
 pub struct Component<'a> {
     cbor: &'a [u8],
 }
 
+/// A [`Component`] owning the encoded identifier.
+#[cfg(feature = "alloc")]
+#[derive(Clone, PartialEq, Debug)]
+pub struct OwnedComponent {
+    cbor: alloc::vec::Vec<u8>,
+}
+
+#[cfg(feature = "alloc")]
+impl OwnedComponent {
+    /// Borrow the identifier as a [`Component`].
+    pub fn as_component(&self) -> Component<'_> {
+        Component::from_bytes(&self.cbor)
+    }
+}
+
 impl<'a, C> Decode<'a, C> for Component<'a> {
     fn decode(d: &mut Decoder<'a>, _: &mut C) -> Result<Self, minicbor::decode::Error> {
         let cbor = d.sub_cbor()?;
@@ -57,6 +96,25 @@ impl<'a, C> Decode<'a, C> for Component<'a> {
     }
 }
 
+impl<C> Encode<C> for Component<'_> {
+    fn encode<W: Write>(
+        &self,
+        e: &mut Encoder<W>,
+        _: &mut C,
+    ) -> Result<(), minicbor::encode::Error<W::Error>> {
+        // The identifier is already CBOR encoded
+        e.writer_mut()
+            .write_all(self.cbor)
+            .map_err(minicbor::encode::Error::write)
+    }
+}
+
+impl<C> CborLen<C> for Component<'_> {
+    fn cbor_len(&self, _: &mut C) -> usize {
+        self.cbor.len()
+    }
+}
+
 impl<'a> Component<'a> {
     /// Create a SUIT component from a byte slice.
     pub fn from_bytes(bytes: &'a impl AsRef<[u8]>) -> Component<'a> {
@@ -65,6 +123,56 @@ impl<'a> Component<'a> {
         }
     }
 
+    /// Copy the identifier into an [`OwnedComponent`] that outlives the manifest buffer.
+    #[cfg(feature = "alloc")]
+    pub fn into_owned(self) -> OwnedComponent {
+        OwnedComponent {
+            cbor: self.cbor.to_vec(),
+        }
+    }
+
+    /// Iterate over the byte string segments of the component identifier.
+    pub fn parts(&self) -> impl Iterator<Item = Result<&'a [u8], Error>> + 'a {
+        ComponentParts {
+            decoder: Decoder::new(self.cbor),
+            remaining: None,
+        }
+    }
+
+    /// Compare the decoded identifier segments with those of another component.
+    ///
+    /// Unlike `==`, identical identifiers match regardless of their CBOR encoding.
+    pub fn identifier_eq(&self, other: &Component) -> Result<bool, Error> {
+        if self == other {
+            return Ok(true);
+        }
+        let mut parts = self.parts();
+        let mut other_parts = other.parts();
+        loop {
+            match (parts.next().transpose()?, other_parts.next().transpose()?) {
+                (None, None) => return Ok(true),
+                (Some(part), Some(other_part)) if part == other_part => {}
+                _ => return Ok(false),
+            }
+        }
+    }
+
+    /// Check whether the leading segments of the identifier match those of the prefix.
+    ///
+    /// Used to verify that the components of a dependency fall under the component identifier
+    /// prefix declared in the dependency metadata. A prefix longer than the identifier never
+    /// matches.
+    pub fn starts_with(&self, prefix: &Component) -> Result<bool, Error> {
+        let mut parts = self.parts();
+        for prefix_part in prefix.parts() {
+            match parts.next().transpose()? {
+                Some(part) if part == prefix_part? => {}
+                _ => return Ok(false),
+            }
+        }
+        Ok(true)
+    }
+
     /// Combine the component into a string.
     #[allow(unstable_name_collisions)]
     pub fn as_string<const N: usize>(
@@ -72,14 +180,13 @@ impl<'a> Component<'a> {
         s: &mut String<N>,
         separator: &str,
     ) -> Result<(), Error> {
-        let mut decoder = Decoder::new(self.cbor);
-        decoder
-            .array_iter::<&ByteSlice>()?
-            .map(|b| match b {
-                Ok(b) => str::from_utf8(b).map_err(|e| Error::Utf8Error {
-                    position: e.valid_up_to(),
-                }),
-                Err(e) => Err(e.into()),
+        self.parts()
+            .map(|b| {
+                b.and_then(|b| {
+                    str::from_utf8(b).map_err(|e| Error::Utf8Error {
+                        position: e.valid_up_to(),
+                    })
+                })
             })
             .intersperse(Ok(separator))
             .try_for_each(|b| match b {
@@ -89,6 +196,73 @@ impl<'a> Component<'a> {
     }
 }
 
+/// Renders the identifier in CBOR diagnostic notation, separating the segments with `/`.
+///
+/// Segments with printable UTF-8 are shown as `'text'`, others as `h'hex'`.
+impl core::fmt::Display for Component<'_> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        for (index, part) in self.parts().enumerate() {
+            if index > 0 {
+                f.write_str("/")?;
+            }
+            let Ok(part) = part else {
+                return f.write_str("<malformed>");
+            };
+            match str::from_utf8(part) {
+                Ok(s) if !s.chars().any(char::is_control) => write!(f, "'{s}'")?,
+                _ => {
+                    f.write_str("h'")?;
+                    for byte in part {
+                        write!(f, "{byte:02x}")?;
+                    }
+                    f.write_str("'")?;
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Iterator over the byte string segments of a component identifier.
+struct ComponentParts<'a> {
+    decoder: Decoder<'a>,
+    /// Number of segments left, `None` until the array header is decoded
+    remaining: Option<u64>,
+}
+
+impl<'a> ComponentParts<'a> {
+    fn next_part(&mut self) -> Result<Option<&'a [u8]>, Error> {
+        let remaining = match self.remaining {
+            Some(remaining) => remaining,
+            None => {
+                let position = self.decoder.position();
+                self.decoder
+                    .array()?
+                    .ok_or(Error::UnexpectedIndefiniteLength { position })?
+            }
+        };
+        if remaining == 0 {
+            self.remaining = Some(0);
+            return Ok(None);
+        }
+        let part = self.decoder.bytes()?;
+        self.remaining = Some(remaining - 1);
+        Ok(Some(part))
+    }
+}
+
+impl<'a> Iterator for ComponentParts<'a> {
+    type Item = Result<&'a [u8], Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let res = self.next_part();
+        if res.is_err() {
+            self.remaining = Some(0);
+        }
+        res.transpose()
+    }
+}
+
 pub(crate) struct ComponentIter<'a, 'b> {
     array_iter: ArrayIter<'b, 'a, Component<'a>>,
 }
@@ -107,39 +281,189 @@ impl<'a, 'b> Iterator for ComponentIter<'a, 'b> {
     }
 }
 
+/// Metadata key of the dependency prefix.
+const DEPENDENCY_PREFIX: u32 = 1;
+
+/// Represents a single dependency in a manifest.
+///
+/// A dependency refers to another SUIT manifest by its dependency index.
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub struct Dependency<'a> {
+    index: u32,
+    metadata: &'a [u8],
+}
+
+impl<'a> Dependency<'a> {
+    /// Index of the dependency as used by [`crate::consts::SuitCommand::SetDependencyIndex`].
+    pub fn index(&self) -> u32 {
+        self.index
+    }
+
+    /// CBOR-encoded metadata of the dependency.
+    pub fn metadata(&self) -> &'a [u8] {
+        self.metadata
+    }
+
+    /// Component identifier prefix of the dependency from the metadata.
+    ///
+    /// Returns `None` when the metadata does not contain a prefix.
+    pub fn prefix(&self) -> Result<Option<Component<'a>>, Error> {
+        let mut decoder = Decoder::new(self.metadata);
+        let position = decoder.position();
+        let len = decoder
+            .map()?
+            .ok_or(Error::UnexpectedIndefiniteLength { position })?;
+        for _ in 0..len {
+            if matches!(decoder.datatype()?, Type::U8 | Type::U16 | Type::U32) {
+                if decoder.u32()? == DEPENDENCY_PREFIX {
+                    return Ok(Some(decoder.decode()?));
+                }
+            } else {
+                // Dependency extensions
+                decoder.skip()?;
+            }
+            decoder.skip()?;
+        }
+        Ok(None)
+    }
+}
+
+/// Iterator over the dependencies in the common section of a manifest.
+#[derive(Clone, Debug)]
+pub struct DependencyIter<'a> {
+    decoder: Decoder<'a>,
+    remaining: u64,
+    offset: usize,
+}
+
+impl<'a> DependencyIter<'a> {
+    pub(crate) fn new(cbor: &'a [u8], offset: usize) -> Result<Self, Error> {
+        let mut decoder = Decoder::new(cbor);
+        let position = decoder.position();
+        let remaining = decoder
+            .map()
+            .map_err(|e| Error::from(e).add_offset(offset))?
+            .ok_or(Error::UnexpectedIndefiniteLength {
+                position: position + offset,
+            })?;
+        Ok(Self {
+            decoder,
+            remaining,
+            offset,
+        })
+    }
+
+    pub(crate) fn empty() -> Self {
+        Self {
+            decoder: Decoder::new(&[]),
+            remaining: 0,
+            offset: 0,
+        }
+    }
+
+    fn decode_dependency(&mut self) -> Result<Dependency<'a>, Error> {
+        let index = self.decoder.u32()?;
+        let metadata = self.decoder.sub_cbor()?;
+        Ok(Dependency { index, metadata })
+    }
+}
+
+impl<'a> Iterator for DependencyIter<'a> {
+    type Item = Result<Dependency<'a>, Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.remaining > 0 {
+            self.remaining -= 1;
+            return Some(
+                self.decode_dependency()
+                    .map_err(|e| e.add_offset(self.offset)),
+            );
+        }
+        None
+    }
+}
+
 #[derive(Debug, Clone)]
 pub(crate) struct ComponentInfo<'a> {
     pub(crate) component: Component<'a>,
     pub(crate) index: u32,
+    dependencies: DependencyIter<'a>,
 }
 
 impl<'a> ComponentInfo<'a> {
     pub(crate) fn new(component: Component<'a>, index: u32) -> Self {
-        Self { component, index }
+        Self {
+            component,
+            index,
+            dependencies: DependencyIter::empty(),
+        }
+    }
+
+    /// Attach the dependencies from the common section of the manifest.
+    pub(crate) fn with_dependencies(mut self, dependencies: DependencyIter<'a>) -> Self {
+        self.dependencies = dependencies;
+        self
+    }
+
+    /// Find the dependency with the supplied index in the dependencies of the manifest.
+    pub(crate) fn dependency(&self, index: u32) -> Result<Option<Dependency<'a>>, Error> {
+        for dependency in self.dependencies.clone() {
+            let dependency = dependency?;
+            if dependency.index() == index {
+                return Ok(Some(dependency));
+            }
+        }
+        Ok(None)
     }
 
     pub(crate) fn component(&self) -> &'a Component<'_> {
         &self.component
     }
 
-    pub(crate) fn in_applylist(&self, decoder: &mut Decoder) -> Result<bool, Error> {
-        let position = decoder.position();
-        match decoder.datatype()? {
-            minicbor::data::Type::Bool => {
-                if decoder.bool()? {
-                    Ok(true)
-                } else {
-                    Err(Error::UnexpectedCbor { position })
+    /// Check whether the component is in the apply list.
+    ///
+    /// Lists of component identifiers are resolved through `match_identifier`, lists of indices
+    /// are matched numerically.
+    pub(crate) fn in_applylist(
+        &self,
+        decoder: &mut Decoder,
+        mut match_identifier: impl FnMut(&Component) -> Result<bool, Error>,
+    ) -> Result<bool, Error> {
+        match decoder.decode::<ComponentIndex>()? {
+            ComponentIndex::Set(arr) if Self::is_identifier_list(arr)? => {
+                let mut decoder = Decoder::new(arr);
+                for candidate in ComponentIter::new(&mut decoder)? {
+                    if match_identifier(&candidate?)? {
+                        return Ok(true);
+                    }
                 }
+                Ok(false)
             }
-            minicbor::data::Type::U8 | minicbor::data::Type::U16 | minicbor::data::Type::U32 => {
-                Ok(decoder.u32()? == self.index)
+            index => Ok(index.in_set(self.index)),
+        }
+    }
+
+    /// Check whether the set lists component identifiers instead of component indices.
+    ///
+    /// Every element must be of the same kind, a set mixing identifiers with other elements fails
+    /// with [`Error::TypeMismatch`].
+    fn is_identifier_list(arr: &ByteSlice) -> Result<bool, Error> {
+        let mut decoder = Decoder::new(arr);
+        let len = decoder.array()?;
+        let mut identifiers = None;
+        for _ in 0..len.unwrap_or(u64::MAX) {
+            let position = decoder.position();
+            let datatype = decoder.datatype()?;
+            if datatype == Type::Break {
+                break;
+            }
+            let identifier = datatype == Type::Array;
+            if *identifiers.get_or_insert(identifier) != identifier {
+                return Err(Error::TypeMismatch { position });
             }
-            minicbor::data::Type::Array => Ok(decoder
-                .array_iter::<u32>()?
-                .any(|x| x.is_ok_and(|i| i == self.index))),
-            _ => Err(Error::UnexpectedCbor { position }),
+            decoder.skip()?;
         }
+        Ok(identifiers.unwrap_or(false))
     }
 }
 
@@ -149,6 +473,18 @@ mod tests {
     extern crate std;
     use super::*;
 
+    #[test]
+    #[cfg(feature = "alloc")]
+    fn into_owned() {
+        let owned = {
+            let input = std::vec![0x82, 0x41, 0x00, 0x41, 0x01]; // [h'00', h'01']
+            Component::from_bytes(&input).into_owned()
+        };
+        let component = owned.as_component();
+        let parts: std::vec::Vec<_> = component.parts().map(|part| part.unwrap()).collect();
+        assert_eq!(parts, [[0x00], [0x01]]);
+    }
+
     #[test]
     fn decode_component() {
         let input = std::vec![0x81, 0x81, 0x41, 0x00]; // [[h'00']]
@@ -161,6 +497,57 @@ mod tests {
         assert_eq!(decoder.position(), input.len());
     }
 
+    #[test]
+    fn encode_component() {
+        let input = std::vec![0x82, 0x81, 0x41, 0x01, 0x82, 0x41, 0x02, 0x43, 0x73, 0x79, 0x73];
+        let mut decoder = Decoder::new(&input);
+        let components: [Component; 2] = decoder.decode().unwrap();
+
+        let mut buf = [0u8; 16];
+        let mut encoder = Encoder::new(buf.as_mut_slice());
+        encoder.encode(components).unwrap();
+        let len = minicbor::len(components);
+        assert_eq!(len, input.len());
+        assert_eq!(&buf[..len], input.as_slice());
+    }
+
+    #[test]
+    fn identifier_eq() {
+        let component = [0x81, 0x41, 0x00];
+        let component = Component::from_bytes(&component);
+        // [h'00'] with non-shortest lengths
+        let long = [0x98, 0x01, 0x58, 0x01, 0x00];
+        let long = Component::from_bytes(&long);
+        assert_ne!(component, long);
+        assert!(component.identifier_eq(&long).unwrap());
+
+        let other = [0x82, 0x41, 0x00, 0x41, 0x01];
+        assert!(!component
+            .identifier_eq(&Component::from_bytes(&other))
+            .unwrap());
+    }
+
+    #[test]
+    fn starts_with() {
+        // [h'00', h'01']
+        let component = [0x82, 0x41, 0x00, 0x41, 0x01];
+        let component = Component::from_bytes(&component);
+        let prefix = [0x81, 0x41, 0x00];
+        assert!(component
+            .starts_with(&Component::from_bytes(&prefix))
+            .unwrap());
+        assert!(component.starts_with(&component).unwrap());
+
+        let other = [0x81, 0x41, 0x01];
+        assert!(!component
+            .starts_with(&Component::from_bytes(&other))
+            .unwrap());
+        let longer = [0x83, 0x41, 0x00, 0x41, 0x01, 0x41, 0x02];
+        assert!(!component
+            .starts_with(&Component::from_bytes(&longer))
+            .unwrap());
+    }
+
     #[test]
     fn iter_components() {
         let input = std::vec![0x82, 0x81, 0x41, 0x01, 0x81, 0x41, 0x02];
@@ -172,6 +559,19 @@ mod tests {
         assert_eq!(component.cbor, std::vec!(0x81, 0x41, 0x02));
     }
 
+    #[test]
+    fn display() {
+        use std::string::ToString;
+
+        // [h'3030', h'737973']
+        let input = std::vec![0x82, 0x42, 0x30, 0x30, 0x43, 0x73, 0x79, 0x73];
+        assert_eq!(Component::from_bytes(&input).to_string(), "'00'/'sys'");
+
+        // [h'00', h'FF01']
+        let input = std::vec![0x82, 0x41, 0x00, 0x42, 0xFF, 0x01];
+        assert_eq!(Component::from_bytes(&input).to_string(), "h'00'/h'ff01'");
+    }
+
     #[test]
     fn component_utf8() {
         let input = std::vec![0x81, 0x82, 0x41, 0x61, 0x41, 0x62];
@@ -186,4 +586,135 @@ mod tests {
         let res = component.as_string(&mut s, "/");
         assert!(matches!(res, Err(Error::CapacityError)));
     }
+
+    #[test]
+    fn component_many_segments() {
+        // ['seg0', 'seg1', ..., 'seg7']
+        let mut input = std::vec![0x88];
+        for index in 0..8 {
+            input.extend_from_slice(&[0x44, b's', b'e', b'g', b'0' + index]);
+        }
+        let component = Component::from_bytes(&input);
+        assert_eq!(component.parts().count(), 8);
+        assert!(component
+            .parts()
+            .all(|part| part.is_ok_and(|p| p.len() == 4)));
+
+        let expected = "seg0/seg1/seg2/seg3/seg4/seg5/seg6/seg7";
+        let mut s: String<39> = String::new();
+        assert_eq!(component.as_string(&mut s, "/"), Ok(()));
+        assert_eq!(s.as_str(), expected);
+        let mut s: String<38> = String::new();
+        assert_eq!(component.as_string(&mut s, "/"), Err(Error::CapacityError));
+    }
+
+    #[test]
+    fn iter_dependencies() {
+        // {0: {1: [h'01']}, 1: {}}
+        let input = std::vec![0xA2, 0x00, 0xA1, 0x01, 0x81, 0x41, 0x01, 0x01, 0xA0];
+        let mut dependencies = DependencyIter::new(&input, 0).unwrap();
+        let dependency = dependencies.next().unwrap().unwrap();
+        assert_eq!(dependency.index(), 0);
+        assert_eq!(dependency.metadata(), &[0xA1, 0x01, 0x81, 0x41, 0x01]);
+        let dependency = dependencies.next().unwrap().unwrap();
+        assert_eq!(dependency.index(), 1);
+        assert_eq!(dependency.metadata(), &[0xA0]);
+        assert!(dependencies.next().is_none());
+    }
+
+    #[test]
+    fn dependency_prefix() {
+        // {0: {-1: 0, 1: [h'01']}, 1: {}}
+        let input = std::vec![0xA2, 0x00, 0xA2, 0x20, 0x00, 0x01, 0x81, 0x41, 0x01, 0x01, 0xA0];
+        let info = ComponentInfo::new(Component::from_bytes(&[0x81, 0x41, 0x00]), 0)
+            .with_dependencies(DependencyIter::new(&input, 0).unwrap());
+        let dependency = info.dependency(0).unwrap().unwrap();
+        assert_eq!(
+            dependency.prefix().unwrap(),
+            Some(Component::from_bytes(&[0x81, 0x41, 0x01]))
+        );
+        let dependency = info.dependency(1).unwrap().unwrap();
+        assert_eq!(dependency.prefix().unwrap(), None);
+        assert!(info.dependency(2).unwrap().is_none());
+    }
+
+    #[test]
+    fn decode_component_index() {
+        let input = std::vec![0xF5, 0x02, 0x82, 0x00, 0x01];
+        let mut decoder = Decoder::new(&input);
+        assert_eq!(
+            decoder.decode::<ComponentIndex>().unwrap(),
+            ComponentIndex::All
+        );
+        assert_eq!(
+            decoder.decode::<ComponentIndex>().unwrap(),
+            ComponentIndex::Index(2)
+        );
+        let set = decoder.decode::<ComponentIndex>().unwrap();
+        assert!(set.in_set(1));
+        assert!(!set.in_set(2));
+    }
+
+    #[test]
+    fn decode_component_index_false() {
+        let input = std::vec![0x00, 0xF4];
+        let mut decoder = Decoder::new(&input);
+        decoder.skip().unwrap();
+        let err: Error = decoder.decode::<ComponentIndex>().unwrap_err().into();
+        assert_eq!(err, Error::MalformedCbor { position: 1 });
+    }
+
+    #[test]
+    fn applylist() {
+        let info = ComponentInfo::new(Component::from_bytes(&[0x81, 0x41, 0x00]), 1);
+        let input = std::vec![0xF5, 0x00, 0x01, 0x82, 0x00, 0x01];
+        let mut decoder = Decoder::new(&input);
+        for expected in [true, false, true, true] {
+            let res = info.in_applylist(&mut decoder, |_| unreachable!()).unwrap();
+            assert_eq!(res, expected);
+        }
+    }
+
+    #[test]
+    fn applylist_identifiers() {
+        let info = ComponentInfo::new(Component::from_bytes(&[0x81, 0x41, 0x00]), 0);
+        // [[h'01'], [h'00']]
+        let input = std::vec![0x82, 0x81, 0x41, 0x01, 0x81, 0x41, 0x00];
+        let mut decoder = Decoder::new(&input);
+        let res = info
+            .in_applylist(&mut decoder, |c| Ok(c == info.component()))
+            .unwrap();
+        assert!(res);
+        let mut decoder = Decoder::new(&input);
+        assert!(!info.in_applylist(&mut decoder, |_| Ok(false)).unwrap());
+
+        // [[h'00'], 1] mixes an identifier with an index
+        let input = std::vec![0x82, 0x81, 0x41, 0x00, 0x01];
+        let mut decoder = Decoder::new(&input);
+        assert_eq!(
+            info.in_applylist(&mut decoder, |_| Ok(true)),
+            Err(Error::TypeMismatch { position: 4 })
+        );
+    }
+
+    #[test]
+    fn component_parts() {
+        // [h'00ff', h'61']
+        let input = std::vec![0x82, 0x42, 0x00, 0xFF, 0x41, 0x61];
+        let component = Component::from_bytes(&input);
+        let mut parts = component.parts();
+        assert_eq!(parts.next().unwrap().unwrap(), &[0x00, 0xFF]);
+        assert_eq!(parts.next().unwrap().unwrap(), b"a");
+        assert!(parts.next().is_none());
+    }
+
+    #[test]
+    fn component_parts_invalid() {
+        // [1]
+        let input = std::vec![0x81, 0x01];
+        let component = Component::from_bytes(&input);
+        let mut parts = component.parts();
+        assert!(parts.next().unwrap().is_err());
+        assert!(parts.next().is_none());
+    }
 }
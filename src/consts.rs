@@ -3,8 +3,23 @@ use minicbor::data::Tag;
 
 /// SUIT envelope tag.
 pub const SUIT_TAG_ENVELOPE: Tag = Tag::new(107);
-/// SUIT manifest encoding version support.
+/// SUIT manifest encoding version emitted by the builder.
 pub const SUIT_SUPPORTED_VERSION: u8 = 1;
+/// Lowest SUIT manifest encoding version accepted by the processor.
+pub const SUIT_MIN_VERSION: u8 = 1;
+/// Highest SUIT manifest encoding version accepted by the processor.
+pub const SUIT_MAX_VERSION: u8 = 1;
+
+/// Maximum nesting depth of command sequences through the try each and run sequence commands.
+///
+/// Bounds the stack usage when processing untrusted manifests.
+pub const SUIT_MAX_NESTING: usize = 4;
+
+/// Maximum number of commands in a command sequence.
+///
+/// Also bounds the total number of commands processed by a command sequence including its nested
+/// sequences, limiting the processing time of untrusted manifests.
+pub const SUIT_MAX_COMMANDS: usize = 256;
 
 /// SUIT command sections in order of execution
 pub const SUIT_COMMAND_SECTIONS: [Manifest; 5] = [
@@ -61,6 +76,8 @@ pub enum Manifest {
     CommonData = 3,
     /// URI where the full manifest can be found.
     ReferenceUri = 4,
+    /// Component identifier of the component the manifest represents.
+    ManifestComponentId = 5,
     /// SUIT command sequence to validate the result of applying the update is correct.
     ///
     /// Typical actions involve image validation.
@@ -88,6 +105,18 @@ pub enum Manifest {
     TextDescription = 23,
 }
 
+impl Manifest {
+    /// The envelope element holding the section when it is severed from the manifest.
+    pub(crate) fn severed_element(&self) -> Option<SuitEnvelope> {
+        match self {
+            Manifest::PayloadFetch => Some(SuitEnvelope::PayloadFetch),
+            Manifest::PayloadInstallation => Some(SuitEnvelope::PayloadInstallation),
+            Manifest::TextDescription => Some(SuitEnvelope::Text),
+            _ => None,
+        }
+    }
+}
+
 /// SUIT common section elements.
 ///
 /// See <https://datatracker.ietf.org/doc/html/draft-ietf-suit-manifest-34#name-suit-common-elements>
@@ -97,16 +126,57 @@ pub enum Manifest {
 pub enum SuitCommon {
     /// Unset detection.
     Unset = 0,
+    /// Map of dependency indices to the dependency metadata.
+    Dependencies = 1,
     /// List of component identifiers affected by this manifest.
     ComponentIdentifiers = 2,
     /// SUIT command sequence to execute prior to executing any other command sequence.
     CommonCommandSequence = 4,
 }
 
+/// SUIT text section elements describing the manifest.
+///
+/// See <https://datatracker.ietf.org/doc/html/draft-ietf-suit-manifest-34#name-suit-text-description>
+#[derive(Copy, Clone, Debug, num_enum::IntoPrimitive)]
+#[non_exhaustive]
+#[repr(i32)]
+pub enum SuitText {
+    /// Free text description of the manifest.
+    ManifestDescription = 1,
+    /// Free text description of the update.
+    UpdateDescription = 2,
+    /// JSON source used to generate the manifest.
+    ManifestJsonSource = 3,
+    /// YAML source used to generate the manifest.
+    ManifestYamlSource = 4,
+}
+
+/// SUIT text section elements describing a component.
+///
+/// See <https://datatracker.ietf.org/doc/html/draft-ietf-suit-manifest-34#name-suit-text-description>
+#[derive(Copy, Clone, Debug, num_enum::IntoPrimitive)]
+#[non_exhaustive]
+#[repr(i32)]
+pub enum SuitTextComponent {
+    /// Name of the vendor.
+    VendorName = 1,
+    /// Name of the model.
+    ModelName = 2,
+    /// Domain used to create the vendor identifier.
+    VendorDomain = 3,
+    /// Information used to create the class identifier.
+    ModelInfo = 4,
+    /// Free text description of the component.
+    ComponentDescription = 5,
+    /// Version of the component.
+    ComponentVersion = 6,
+}
+
 /// SUIT parameter numbers.
 ///
 /// See <https://datatracker.ietf.org/doc/html/draft-ietf-suit-manifest-34#name-suit-parameters>
 #[derive(Copy, Clone, Debug, num_enum::IntoPrimitive)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
 #[non_exhaustive]
 #[repr(i32)]
 pub enum SuitParameter {
@@ -128,6 +198,12 @@ pub enum SuitParameter {
     /// Argument for [`SuitCommand::ImageMatch`].
     // Decodes into a [`crate::digest::SuitDigest`].
     ImageDigest = 3,
+    /// Timestamp after which the manifest must no longer be processed.
+    ///
+    /// Contains the time in seconds since the UNIX epoch.
+    /// Argument for [`SuitCommand::UseBefore`].
+    /// See <https://datatracker.ietf.org/doc/html/draft-ietf-suit-update-management>
+    UseBefore = 4,
     /// Specify a slot within a slot.
     ComponentSlot = 5,
     /// Strict order execution of the current command sequence.
@@ -141,6 +217,11 @@ pub enum SuitParameter {
     /// Encodes a payload as direct byte string in the parameter.
     /// Argument for [`SuitCommand::WriteContent`].
     Content = 18,
+    /// Encryption info of an encrypted payload.
+    ///
+    /// Contains a COSE_Encrypt or COSE_Encrypt0 structure encoded as byte string.
+    /// Applies to [`SuitCommand::WriteContent`] and [`SuitCommand::Fetch`].
+    EncryptionInfo = 19,
     /// URI for a fetch command.
     /// Argument for [`SuitCommand::Fetch`].
     Uri = 21,
@@ -164,12 +245,13 @@ impl TryFrom<i32> for SuitParameter {
             1 => Self::VendorId,
             2 => Self::ClassId,
             3 => Self::ImageDigest,
-            4 => Self::ComponentSlot,
+            4 => Self::UseBefore,
             5 => Self::ComponentSlot,
             12 => Self::StrictOrder,
             13 => Self::SoftFailure,
             14 => Self::ImageSize,
             18 => Self::Content,
+            19 => Self::EncryptionInfo,
             21 => Self::Uri,
             22 => Self::SourceComponent,
             23 => Self::InvokeArgs,
@@ -183,6 +265,7 @@ impl TryFrom<i32> for SuitParameter {
 ///
 /// See <https://datatracker.ietf.org/doc/html/draft-ietf-suit-manifest-34#name-suit-commands>
 #[derive(Copy, Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
 #[non_exhaustive]
 #[repr(i32)]
 pub enum SuitCommand {
@@ -194,12 +277,20 @@ pub enum SuitCommand {
     ClassIdentifier = 2,
     /// Verify the content in the component based on the digest.
     ImageMatch = 3,
+    /// Check that the current time is before the timestamp in [`SuitParameter::UseBefore`].
+    UseBefore = 4,
     /// Verify if the component slot is valid for the current component.
     ComponentSlot = 5,
     /// Check the content in the component based on the [`SuitParameter::Content`].
     CheckContent = 6,
     /// Set the component index for the next commands in the sequence.
     SetComponentIndex = 12,
+    /// Select a dependency and validate it.
+    ///
+    /// The processor resolves and authenticates the dependency via the
+    /// [`crate::OperatingHooks`], then executes the image validation of the dependent manifest.
+    /// The following commands still apply to the selected components.
+    SetDependencyIndex = 13,
     /// Abort the manifest processing.
     Abort = 14,
     /// Sequentially execute a set of command sequences until one succeeds.
@@ -230,6 +321,10 @@ pub enum SuitCommand {
     ///
     /// The processort passes the command to the [`crate::OperatingHooks`] for the operating system to process the
     /// command.
+    ///
+    /// Only holds command numbers without a dedicated variant. The conversion from `i32` never
+    /// produces a custom command for a known number, so a `Custom(0)` converted to `i32` and back
+    /// becomes [`SuitCommand::Unset`].
     Custom(i32),
 }
 
@@ -240,9 +335,11 @@ impl From<i32> for SuitCommand {
             1 => SuitCommand::VendorIdentifier,
             2 => SuitCommand::ClassIdentifier,
             3 => SuitCommand::ImageMatch,
+            4 => SuitCommand::UseBefore,
             5 => SuitCommand::ComponentSlot,
             6 => SuitCommand::CheckContent,
             12 => SuitCommand::SetComponentIndex,
+            13 => SuitCommand::SetDependencyIndex,
             14 => SuitCommand::Abort,
             15 => SuitCommand::TryEach,
             18 => SuitCommand::WriteContent,
@@ -265,9 +362,11 @@ impl From<SuitCommand> for i32 {
             SuitCommand::VendorIdentifier => 1,
             SuitCommand::ClassIdentifier => 2,
             SuitCommand::ImageMatch => 3,
+            SuitCommand::UseBefore => 4,
             SuitCommand::ComponentSlot => 5,
             SuitCommand::CheckContent => 6,
             SuitCommand::SetComponentIndex => 12,
+            SuitCommand::SetDependencyIndex => 13,
             SuitCommand::Abort => 14,
             SuitCommand::TryEach => 15,
             SuitCommand::WriteContent => 18,
@@ -290,9 +389,11 @@ impl SuitCommand {
             SuitCommand::VendorIdentifier => true,
             SuitCommand::ClassIdentifier => true,
             SuitCommand::ImageMatch => true,
+            SuitCommand::UseBefore => true,
             SuitCommand::ComponentSlot => true,
             SuitCommand::CheckContent => true,
             SuitCommand::SetComponentIndex => false,
+            SuitCommand::SetDependencyIndex => false,
             SuitCommand::Abort => true,
             SuitCommand::TryEach => false,
             SuitCommand::WriteContent => true,
@@ -307,6 +408,35 @@ impl SuitCommand {
         }
     }
 
+    /// Returns true if the command is a condition on the current component.
+    ///
+    /// The abort condition is not tied to a component and excluded.
+    pub(crate) fn is_component_condition(&self) -> bool {
+        matches!(
+            self,
+            SuitCommand::VendorIdentifier
+                | SuitCommand::ClassIdentifier
+                | SuitCommand::ImageMatch
+                | SuitCommand::UseBefore
+                | SuitCommand::ComponentSlot
+                | SuitCommand::CheckContent
+                | SuitCommand::DeviceIdentifier
+        )
+    }
+
+    /// Returns true if the command is needed to verify the image digests.
+    pub(crate) fn verifies_images(&self) -> bool {
+        matches!(
+            self,
+            SuitCommand::OverrideParameters
+                | SuitCommand::SetComponentIndex
+                | SuitCommand::SetDependencyIndex
+                | SuitCommand::ImageMatch
+                | SuitCommand::TryEach
+                | SuitCommand::RunSequence
+        )
+    }
+
     /// Returns true if the command has side effects.
     ///
     /// Returns true for custom commands as these might have side effects.
@@ -322,3 +452,41 @@ impl SuitCommand {
         )
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn command_round_trip() {
+        for number in -1..=40 {
+            let command = SuitCommand::from(number);
+            assert_eq!(i32::from(command), number);
+            // Custom commands only hold unknown numbers
+            if let SuitCommand::Custom(_) = command {
+                assert!(
+                    ![0, 1, 2, 3, 4, 5, 6, 12, 13, 14, 15, 18, 20, 21, 22, 23, 24, 31, 32]
+                        .contains(&number)
+                );
+            }
+        }
+        assert_eq!(SuitCommand::from(0), SuitCommand::Unset);
+        assert_eq!(
+            SuitCommand::from(i32::from(SuitCommand::Custom(0))),
+            SuitCommand::Unset
+        );
+    }
+
+    #[test]
+    fn parameter_round_trip() {
+        for number in -1..=40 {
+            if let Ok(parameter) = SuitParameter::try_from(number) {
+                assert_eq!(i32::from(parameter), number);
+            }
+        }
+        assert!(matches!(
+            SuitParameter::try_from(0),
+            Ok(SuitParameter::Unset)
+        ));
+    }
+}
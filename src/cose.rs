@@ -0,0 +1,425 @@
+//! COSE header parsing.
+//!
+//! Minimal decoder for the COSE header parameters shared by the authentication and the payload
+//! encryption. Only the labels required by Dress‑Up are extracted, all other labels are skipped.
+//! See <https://www.rfc-editor.org/rfc/rfc9052#name-header-parameters>
+use minicbor::data::{Tag, Type};
+use minicbor::decode::Decoder;
+use minicbor::encode::write::Cursor;
+use minicbor::Encoder;
+
+use crate::error::Error;
+
+/// COSE_Sign1 tag.
+const COSE_SIGN1: Tag = Tag::new(18);
+/// COSE_Sign tag.
+const COSE_SIGN: Tag = Tag::new(98);
+/// Context string of the COSE_Sign1 signature structure.
+const SIGNATURE1: &str = "Signature1";
+/// Context string of the COSE_Sign signature structure.
+const SIGNATURE: &str = "Signature";
+
+/// COSE header label of the algorithm.
+const HEADER_ALG: i64 = 1;
+/// COSE header label of the key identifier.
+const HEADER_KID: i64 = 4;
+/// COSE header label of the initialization vector.
+const HEADER_IV: i64 = 5;
+
+/// Header parameters of a COSE structure.
+///
+/// Collects the parameters from both the protected and the unprotected header bucket.
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+pub struct CoseHeaders<'a> {
+    alg: Option<i64>,
+    kid: Option<&'a [u8]>,
+    iv: Option<&'a [u8]>,
+}
+
+impl<'a> CoseHeaders<'a> {
+    /// Decode the protected and the unprotected header following each other in a COSE structure.
+    ///
+    /// The protected header is the bstr wrapped map, the unprotected header a plain map.
+    pub fn decode(decoder: &mut Decoder<'a>) -> Result<Self, Error> {
+        let mut headers = Self::default();
+        headers.update_protected(decoder)?;
+        headers.update(decoder)?;
+        Ok(headers)
+    }
+
+    /// Decode the headers of a COSE message such as a COSE_Sign1 or COSE_Mac0 structure.
+    ///
+    /// The message tag is optional.
+    pub fn decode_message(message: &'a [u8]) -> Result<Self, Error> {
+        let mut decoder = Decoder::new(message);
+        if decoder.datatype()? == Type::Tag {
+            decoder.tag()?;
+        }
+        let position = decoder.position();
+        decoder
+            .array()?
+            .ok_or(Error::UnexpectedIndefiniteLength { position })?;
+        Self::decode(&mut decoder)
+    }
+
+    /// The algorithm identifier, label 1.
+    pub fn alg(&self) -> Option<i64> {
+        self.alg
+    }
+
+    /// The key identifier, label 4.
+    pub fn kid(&self) -> Option<&'a [u8]> {
+        self.kid
+    }
+
+    /// The initialization vector, label 5.
+    pub fn iv(&self) -> Option<&'a [u8]> {
+        self.iv
+    }
+
+    /// Set the key identifier from outside the headers, e.g. from a COSE recipient.
+    pub(crate) fn set_kid(&mut self, kid: Option<&'a [u8]>) {
+        self.kid = kid;
+    }
+
+    /// Collect the known labels from a header map, skipping any other label.
+    fn update(&mut self, decoder: &mut Decoder<'a>) -> Result<(), Error> {
+        let position = decoder.position();
+        let len = decoder
+            .map()?
+            .ok_or(Error::UnexpectedIndefiniteLength { position })?;
+        for _ in 0..len {
+            if !matches!(
+                decoder.datatype()?,
+                Type::U8 | Type::U16 | Type::U32 | Type::I8 | Type::I16 | Type::I32
+            ) {
+                decoder.skip()?;
+                decoder.skip()?;
+                continue;
+            }
+            match decoder.i64()? {
+                HEADER_ALG => self.alg = Some(decoder.i64()?),
+                HEADER_IV => self.iv = Some(decoder.bytes()?),
+                HEADER_KID => self.kid = Some(decoder.bytes()?),
+                _ => decoder.skip()?,
+            }
+        }
+        Ok(())
+    }
+
+    /// Collect the labels from the bstr wrapped protected header.
+    fn update_protected(&mut self, decoder: &mut Decoder<'a>) -> Result<(), Error> {
+        let protected = decoder.bytes()?;
+        if !protected.is_empty() {
+            let offset = decoder.position() - protected.len();
+            self.update(&mut Decoder::new(protected))
+                .map_err(|e| e.add_offset(offset))?;
+        }
+        Ok(())
+    }
+}
+
+/// Signature structure of an authentication block.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub(crate) enum CoseSignature {
+    /// COSE_Sign1 with a single signature.
+    Sign1,
+    /// COSE_Sign with an array of signatures.
+    Sign,
+}
+
+impl CoseSignature {
+    /// Determine the signature structure of a COSE message.
+    ///
+    /// Tagged messages are identified by their tag, untagged messages by the last element, which
+    /// is the signature for COSE_Sign1 and the signatures array for COSE_Sign. Returns
+    /// [`Error::UnsupportedCoseStructure`] for any other structure.
+    pub(crate) fn detect(message: &[u8]) -> Result<Self, Error> {
+        let mut decoder = Decoder::new(message);
+        if decoder.datatype()? == Type::Tag {
+            return match decoder.tag()? {
+                COSE_SIGN1 => Ok(Self::Sign1),
+                COSE_SIGN => Ok(Self::Sign),
+                _ => Err(Error::UnsupportedCoseStructure),
+            };
+        }
+        if decoder.datatype()? != Type::Array || decoder.array()? != Some(4) {
+            return Err(Error::UnsupportedCoseStructure);
+        }
+        for _ in 0..3 {
+            decoder.skip()?;
+        }
+        match decoder.datatype()? {
+            Type::Bytes => Ok(Self::Sign1),
+            Type::Array => Ok(Self::Sign),
+            _ => Err(Error::UnsupportedCoseStructure),
+        }
+    }
+}
+
+/// Iterate over the header parameters of the signers of a COSE_Sign message.
+///
+/// Yields the headers of every COSE_Signature in the signatures array, iteration ends after the
+/// first error.
+pub(crate) fn signers(
+    message: &[u8],
+) -> Result<impl Iterator<Item = Result<CoseHeaders<'_>, Error>>, Error> {
+    let mut decoder = Decoder::new(message);
+    if decoder.datatype()? == Type::Tag {
+        decoder.tag()?;
+    }
+    let position = decoder.position();
+    if decoder.array()? != Some(4) {
+        return Err(Error::UnexpectedCbor { position });
+    }
+    // Protected and unprotected body headers, payload
+    for _ in 0..3 {
+        decoder.skip()?;
+    }
+    let position = decoder.position();
+    let mut remaining = decoder
+        .array()?
+        .ok_or(Error::UnexpectedIndefiniteLength { position })?;
+    Ok(core::iter::from_fn(move || {
+        if remaining == 0 {
+            return None;
+        }
+        let position = decoder.position();
+        let signer = match decoder.array() {
+            Ok(Some(3)) => CoseHeaders::decode(&mut decoder)
+                .and_then(|headers| Ok(decoder.skip().map(|_| headers)?)),
+            Ok(_) => Err(Error::UnexpectedCbor { position }),
+            Err(e) => Err(e.into()),
+        };
+        remaining = if signer.is_ok() { remaining - 1 } else { 0 };
+        Some(signer)
+    }))
+}
+
+/// Reconstruct the signed bytes, the Sig_structure, of a COSE_Sign1 message.
+///
+/// The detached `payload` is used when the message does not carry the payload itself. For SUIT
+/// this is the manifest digest passed to the closure of [`crate::SuitManifest::authenticate`].
+/// This allows verifying the signature outside of Dress‑Up, for example with a hardware security
+/// module. Returns [`Error::CapacityError`] if the structure does not fit in `N` bytes.
+pub fn sig_structure<const N: usize>(
+    message: &[u8],
+    payload: &[u8],
+) -> Result<heapless::Vec<u8, N>, Error> {
+    let mut decoder = Decoder::new(message);
+    let position = decoder.position();
+    if decoder.datatype()? == Type::Tag && decoder.tag()? != COSE_SIGN1 {
+        return Err(Error::UnexpectedCbor { position });
+    }
+    let position = decoder.position();
+    if decoder.array()? != Some(4) {
+        return Err(Error::UnexpectedCbor { position });
+    }
+    let protected = decoder.bytes()?;
+    decoder.skip()?;
+    let payload = match decoder.datatype()? {
+        Type::Null => payload,
+        _ => decoder.bytes()?,
+    };
+    encode_sig_structure(SIGNATURE1, protected, None, payload)
+}
+
+/// Reconstruct the signed bytes, the Sig_structure, of the signer with index `signer` in a
+/// COSE_Sign message.
+///
+/// The counterpart of [`sig_structure`] for the COSE_Sign structures, the index is the one
+/// passed to the closure of [`crate::SuitManifest::authenticate_with_key`]. Returns
+/// [`Error::CapacityError`] if the structure does not fit in `N` bytes.
+pub fn signer_sig_structure<const N: usize>(
+    message: &[u8],
+    signer: usize,
+    payload: &[u8],
+) -> Result<heapless::Vec<u8, N>, Error> {
+    let mut decoder = Decoder::new(message);
+    let position = decoder.position();
+    if decoder.datatype()? == Type::Tag && decoder.tag()? != COSE_SIGN {
+        return Err(Error::UnexpectedCbor { position });
+    }
+    let position = decoder.position();
+    if decoder.array()? != Some(4) {
+        return Err(Error::UnexpectedCbor { position });
+    }
+    let body_protected = decoder.bytes()?;
+    decoder.skip()?;
+    let payload = match decoder.datatype()? {
+        Type::Null => {
+            decoder.skip()?;
+            payload
+        }
+        _ => decoder.bytes()?,
+    };
+    let position = decoder.position();
+    let len = decoder
+        .array()?
+        .ok_or(Error::UnexpectedIndefiniteLength { position })?;
+    if signer as u64 >= len {
+        return Err(Error::UnexpectedCbor { position });
+    }
+    for _ in 0..signer {
+        decoder.skip()?;
+    }
+    let position = decoder.position();
+    if decoder.array()? != Some(3) {
+        return Err(Error::UnexpectedCbor { position });
+    }
+    let sign_protected = decoder.bytes()?;
+    encode_sig_structure(SIGNATURE, body_protected, Some(sign_protected), payload)
+}
+
+/// Encode a Sig_structure with an empty external AAD.
+///
+/// The signer protected header is only part of the COSE_Sign structure.
+fn encode_sig_structure<const N: usize>(
+    context: &str,
+    body_protected: &[u8],
+    sign_protected: Option<&[u8]>,
+    payload: &[u8],
+) -> Result<heapless::Vec<u8, N>, Error> {
+    let mut buf = heapless::Vec::<u8, N>::new();
+    buf.resize_default(N).map_err(|_| Error::CapacityError)?;
+    let mut encoder = Encoder::new(Cursor::new(buf.as_mut_slice()));
+    let len = if sign_protected.is_some() { 5 } else { 4 };
+    encoder
+        .array(len)
+        .and_then(|e| e.str(context))
+        .and_then(|e| e.bytes(body_protected))
+        .map_err(|_| Error::CapacityError)?;
+    if let Some(sign_protected) = sign_protected {
+        encoder
+            .bytes(sign_protected)
+            .map_err(|_| Error::CapacityError)?;
+    }
+    encoder
+        // Empty external AAD
+        .bytes(&[])
+        .and_then(|e| e.bytes(payload))
+        .map_err(|_| Error::CapacityError)?;
+    let len = encoder.into_writer().position();
+    buf.truncate(len);
+    Ok(buf)
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod tests {
+    extern crate std;
+    use super::*;
+
+    #[test]
+    fn protected_and_unprotected() {
+        // h'A10126', {4: h'AA', "x": 1}
+        let input: &[u8] =
+            &std::vec![0x43, 0xA1, 0x01, 0x26, 0xA2, 0x04, 0x41, 0xAA, 0x61, 0x78, 0x01];
+        let headers = CoseHeaders::decode(&mut Decoder::new(input)).unwrap();
+        assert_eq!(headers.alg(), Some(-7));
+        assert_eq!(headers.kid(), Some([0xAA].as_slice()));
+        assert_eq!(headers.iv(), None);
+    }
+
+    #[test]
+    fn empty_protected() {
+        // h'', {1: -7}
+        let input: &[u8] = &std::vec![0x40, 0xA1, 0x01, 0x26];
+        let headers = CoseHeaders::decode(&mut Decoder::new(input)).unwrap();
+        assert_eq!(headers.alg(), Some(-7));
+        assert_eq!(headers.kid(), None);
+    }
+
+    #[test]
+    fn message() {
+        // 18([h'A10126', {4: h'01'}, null, h''])
+        let input: &[u8] =
+            &std::vec![0xD2, 0x84, 0x43, 0xA1, 0x01, 0x26, 0xA1, 0x04, 0x41, 0x01, 0xF6, 0x40];
+        let headers = CoseHeaders::decode_message(input).unwrap();
+        assert_eq!(headers.alg(), Some(-7));
+        assert_eq!(headers.kid(), Some([0x01].as_slice()));
+    }
+
+    #[test]
+    fn detached_sig_structure() {
+        // 18([h'A10126', {4: h'01'}, null, h'']) with payload h'AB'
+        let input: &[u8] =
+            &std::vec![0xD2, 0x84, 0x43, 0xA1, 0x01, 0x26, 0xA1, 0x04, 0x41, 0x01, 0xF6, 0x40];
+        let signed = sig_structure::<32>(input, &[0xAB]).unwrap();
+        // ["Signature1", h'A10126', h'', h'AB']
+        assert_eq!(
+            signed.as_slice(),
+            &[
+                0x84, 0x6A, 0x53, 0x69, 0x67, 0x6E, 0x61, 0x74, 0x75, 0x72, 0x65, 0x31, 0x43, 0xA1,
+                0x01, 0x26, 0x40, 0x41, 0xAB
+            ]
+        );
+
+        let res = sig_structure::<16>(input, &[0xAB]);
+        assert_eq!(res, Err(Error::CapacityError));
+    }
+
+    #[test]
+    fn signer_sig_structures() {
+        // 98([h'A10126', {}, null, [[h'', {4: h'01'}, h''], [h'A10126', {4: h'02'}, h'']]])
+        let input: &[u8] = &[
+            0xD8, 0x62, 0x84, 0x43, 0xA1, 0x01, 0x26, 0xA0, 0xF6, 0x82, 0x83, 0x40, 0xA1, 0x04,
+            0x41, 0x01, 0x40, 0x83, 0x43, 0xA1, 0x01, 0x26, 0xA1, 0x04, 0x41, 0x02, 0x40,
+        ];
+        let signed = signer_sig_structure::<32>(input, 1, &[0xAB]).unwrap();
+        // ["Signature", h'A10126', h'A10126', h'', h'AB']
+        assert_eq!(
+            signed.as_slice(),
+            &[
+                0x85, 0x69, 0x53, 0x69, 0x67, 0x6E, 0x61, 0x74, 0x75, 0x72, 0x65, 0x43, 0xA1, 0x01,
+                0x26, 0x43, 0xA1, 0x01, 0x26, 0x40, 0x41, 0xAB
+            ]
+        );
+        let signed = signer_sig_structure::<32>(input, 0, &[0xAB]).unwrap();
+        // ["Signature", h'A10126', h'', h'', h'AB']
+        assert_eq!(&signed[15..], &[0x40, 0x40, 0x41, 0xAB]);
+
+        assert_eq!(
+            signer_sig_structure::<32>(input, 2, &[0xAB]),
+            Err(Error::UnexpectedCbor { position: 9 })
+        );
+        // A COSE_Sign1 message has no signers
+        let sign1: &[u8] = &[0xD2, 0x84, 0x40, 0xA0, 0xF6, 0x40];
+        assert_eq!(
+            signer_sig_structure::<32>(sign1, 0, &[0xAB]),
+            Err(Error::UnexpectedCbor { position: 0 })
+        );
+    }
+
+    #[test]
+    fn signature_structure() {
+        // [h'', {}, null, h'']
+        let sign1: &[u8] = &[0x84, 0x40, 0xA0, 0xF6, 0x40];
+        assert_eq!(CoseSignature::detect(sign1), Ok(CoseSignature::Sign1));
+        // [h'', {}, null, [[h'', {4: h'01'}, h'']]]
+        let sign: &[u8] = &[
+            0x84, 0x40, 0xA0, 0xF6, 0x81, 0x83, 0x40, 0xA1, 0x04, 0x41, 0x01, 0x40,
+        ];
+        assert_eq!(CoseSignature::detect(sign), Ok(CoseSignature::Sign));
+        let mut signers = signers(sign).unwrap();
+        assert_eq!(
+            signers.next().unwrap().unwrap().kid(),
+            Some([0x01].as_slice())
+        );
+        assert!(signers.next().is_none());
+        // [h'', {}, null, {}]
+        let other: &[u8] = &[0x84, 0x40, 0xA0, 0xF6, 0xA0];
+        assert_eq!(
+            CoseSignature::detect(other),
+            Err(Error::UnsupportedCoseStructure)
+        );
+    }
+
+    #[test]
+    fn indefinite_protected() {
+        // h'BF0126FF', {}
+        let input: &[u8] = &std::vec![0x44, 0xBF, 0x01, 0x26, 0xFF, 0xA0];
+        let res = CoseHeaders::decode(&mut Decoder::new(input));
+        assert_eq!(res, Err(Error::UnexpectedIndefiniteLength { position: 1 }));
+    }
+}
@@ -2,42 +2,81 @@
 use minicbor::{bytes::ByteSlice, data::Type, encode::Write, CborLen, Decode, Encode, Encoder};
 
 use crate::error::Error;
+use ctutils::CtEq;
 #[allow(unused_imports)]
 use digest::ExtendableOutput;
 use digest::{FixedOutput, OutputSizeUser, Update};
 
 /// SUIT digest algorithm numbers.
 ///
-/// Contains digest algorithm numbers from the COSE algorithm registry.
+/// Contains digest algorithm numbers from the COSE algorithm registry. Digests without a COSE
+/// algorithm number, such as SHA-224, can't be expressed in a manifest. SHA-256 is always
+/// available, the other algorithms are enabled by the cargo feature of the same name.
 /// See <https://www.iana.org/assignments/cose/cose.xhtml#algorithms>
 #[derive(Copy, Clone, Debug, PartialEq, num_enum::IntoPrimitive, num_enum::TryFromPrimitive)]
 #[num_enum(error_type(name = Error, constructor = Error::digest_algo_error))]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
 #[non_exhaustive]
 #[repr(i64)]
 pub enum SuitDigestAlgorithm {
     /// SHA-256
     Sha256 = -16,
     /// SHAKE-128
+    #[cfg(feature = "shake128")]
     Shake128 = -18,
     /// SHA-384
+    #[cfg(feature = "sha384")]
     Sha384 = -43,
-    /// SHA-256
+    /// SHA-512
+    #[cfg(feature = "sha512")]
     Sha512 = -44,
     /// SHAKE-256
+    #[cfg(feature = "shake256")]
     Shake256 = -45,
 }
 
+/// Maximum size of a digest in bytes.
+pub const MAX_DIGEST_SIZE: usize = 64;
+
+impl SuitDigestAlgorithm {
+    /// Output length in bytes registered for an extendable output function.
+    ///
+    /// RFC 9054 registers SHAKE128 with a 256 bit and SHAKE256 with a 512 bit output, digests of
+    /// other lengths are not supported. `None` for the fixed length digests.
+    fn xof_output_size(self) -> Option<usize> {
+        match self {
+            #[cfg(feature = "shake128")]
+            Self::Shake128 => Some(32),
+            #[cfg(feature = "shake256")]
+            Self::Shake256 => Some(64),
+            _ => None,
+        }
+    }
+}
+
 /// A digest as provided by a manifest.
 ///
 /// Combines the digest and the algorithm.
+///
+/// Decodes from a SUIT_Digest structure, borrowing the digest bytes from the manifest.
 #[derive(Clone, Debug, PartialEq)]
-pub(crate) struct SuitDigest<'a> {
+pub struct SuitDigest<'a> {
     algo: SuitDigestAlgorithm,
     digest: &'a ByteSlice,
 }
 
+/// Hasher for one of the supported digest algorithms.
+///
+/// The processor matches digests from the manifest in constant time. The hasher itself allows
+/// computing a digest, for example to log the hash of a component next to the expected one.
 #[derive(Clone, Debug)]
-pub(crate) enum Hasher {
+pub struct Hasher {
+    inner: HasherInner,
+}
+
+/// Hash function state for each supported algorithm, kept out of the public API.
+#[derive(Clone, Debug)]
+enum HasherInner {
     Sha2(sha2::Sha256),
     #[cfg(feature = "sha384")]
     Sha384(sha2::Sha384),
@@ -54,41 +93,116 @@ impl<'a> SuitDigest<'a> {
         Self { algo, digest }
     }
 
+    /// The digest algorithm.
+    pub fn algorithm(&self) -> SuitDigestAlgorithm {
+        self.algo
+    }
+
+    /// The digest bytes.
+    pub fn digest(&self) -> &'a [u8] {
+        self.digest
+    }
+
+    /// Copy the digest into an [`OwnedSuitDigest`] that outlives the manifest buffer.
+    #[cfg(feature = "alloc")]
+    pub fn into_owned(self) -> OwnedSuitDigest {
+        OwnedSuitDigest {
+            algo: self.algo,
+            digest: self.digest.to_vec(),
+        }
+    }
+
+    /// Format the digest as lowercase hex into the string.
+    ///
+    /// Returns [`Error::CapacityError`] when the hex digest does not fit in the string.
+    pub fn fmt_hex<const N: usize>(
+        &self,
+        s: &mut heapless::string::String<N>,
+    ) -> Result<(), Error> {
+        crate::util::hex(self.digest, s)
+    }
+
+    /// Decode a SUIT_Digest structure.
+    ///
+    /// Unlike decoding through [`Decode`], an unknown or disabled algorithm and an extendable
+    /// output function digest of an unregistered length are reported as
+    /// [`Error::UnsupportedDigestAlgo`].
+    pub(crate) fn from_cbor(decoder: &mut minicbor::Decoder<'a>) -> Result<Self, Error> {
+        let mut probe = decoder.clone();
+        if probe.array()? == Some(2) {
+            let algorithm = SuitDigestAlgorithm::try_from(probe.i64()?)?;
+            let len = probe.bytes()?.len();
+            if algorithm.xof_output_size().is_some_and(|size| size != len) {
+                return Err(Error::digest_algo_error(algorithm.into()));
+            }
+        }
+        Ok(decoder.decode()?)
+    }
+
     pub(crate) fn hasher(&self) -> Result<Hasher, Error> {
         Hasher::new(self.algo)
     }
 
     pub(crate) fn match_hasher(&self, hasher: Hasher) -> Result<bool, Error> {
-        match (self.algo, hasher) {
-            (SuitDigestAlgorithm::Sha256, Hasher::Sha2(digest)) => {
+        match (self.algo, hasher.inner) {
+            (SuitDigestAlgorithm::Sha256, HasherInner::Sha2(digest)) => {
                 let output = digest.finalize_fixed();
-                Ok(**self.digest == *output)
+                Ok(self.digest.ct_eq(&output).to_bool())
             }
             #[cfg(feature = "sha384")]
-            (SuitDigestAlgorithm::Sha384, Hasher::Sha384(digest)) => {
+            (SuitDigestAlgorithm::Sha384, HasherInner::Sha384(digest)) => {
                 let output = digest.finalize_fixed();
-                Ok(**self.digest == *output)
+                Ok(self.digest.ct_eq(&output).to_bool())
             }
             #[cfg(feature = "sha512")]
-            (SuitDigestAlgorithm::Sha512, Hasher::Sha512(digest)) => {
+            (SuitDigestAlgorithm::Sha512, HasherInner::Sha512(digest)) => {
                 let output = digest.finalize_fixed();
-                Ok(**self.digest == *output)
+                Ok(self.digest.ct_eq(&output).to_bool())
             }
             #[cfg(feature = "shake128")]
-            (SuitDigestAlgorithm::Shake128, Hasher::Shake128(digest)) => {
-                let mut output = [0u8; 32];
-                digest.finalize_xof_into(&mut output);
-                Ok(**self.digest == output)
+            (SuitDigestAlgorithm::Shake128, HasherInner::Shake128(digest)) => {
+                self.match_xof(digest)
             }
             #[cfg(feature = "shake256")]
-            (SuitDigestAlgorithm::Shake256, Hasher::Shake256(digest)) => {
-                let mut output = [0u8; 64];
-                digest.finalize_xof_into(&mut output);
-                Ok(**self.digest == output)
+            (SuitDigestAlgorithm::Shake256, HasherInner::Shake256(digest)) => {
+                self.match_xof(digest)
             }
+            // Only reachable with more than one algorithm enabled
+            #[allow(unreachable_patterns)]
             (_, _) => Err(Error::ConditionMatchFail { position: 0 }),
         }
     }
+
+    /// Match an extendable output function against a digest of the registered output length.
+    ///
+    /// Returns [`Error::UnsupportedDigestAlgo`] for a digest of any other length.
+    fn match_xof(&self, digest: impl ExtendableOutput) -> Result<bool, Error> {
+        let size = self
+            .algo
+            .xof_output_size()
+            .filter(|size| *size == self.digest.len())
+            .ok_or(Error::digest_algo_error(self.algo.into()))?;
+        let mut output = [0u8; MAX_DIGEST_SIZE];
+        let output = output.get_mut(..size).ok_or(Error::CapacityError)?;
+        digest.finalize_xof_into(output);
+        Ok(self.digest.ct_eq(output).to_bool())
+    }
+}
+
+/// A [`SuitDigest`] owning the digest bytes.
+#[cfg(feature = "alloc")]
+#[derive(Clone, Debug, PartialEq)]
+pub struct OwnedSuitDigest {
+    algo: SuitDigestAlgorithm,
+    digest: alloc::vec::Vec<u8>,
+}
+
+#[cfg(feature = "alloc")]
+impl OwnedSuitDigest {
+    /// Borrow the digest as a [`SuitDigest`].
+    pub fn as_digest(&self) -> SuitDigest<'_> {
+        SuitDigest::new(self.algo, self.digest.as_slice().into())
+    }
 }
 
 impl<'a, C> Decode<'a, C> for SuitDigest<'a> {
@@ -131,67 +245,78 @@ impl<C> CborLen<C> for SuitDigest<'_> {
 }
 
 impl Hasher {
-    fn new(algo: SuitDigestAlgorithm) -> Result<Self, Error> {
-        Ok(match algo {
-            SuitDigestAlgorithm::Sha256 => Self::Sha2(sha2::Sha256::default()),
+    /// Create a hasher for the digest algorithm.
+    ///
+    /// Returns [`Error::UnsupportedDigestAlgo`] when the algorithm is not enabled.
+    pub fn new(algo: SuitDigestAlgorithm) -> Result<Self, Error> {
+        let inner = match algo {
+            SuitDigestAlgorithm::Sha256 => HasherInner::Sha2(sha2::Sha256::default()),
             #[cfg(feature = "sha384")]
-            SuitDigestAlgorithm::Sha384 => Self::Sha384(sha2::Sha384::default()),
+            SuitDigestAlgorithm::Sha384 => HasherInner::Sha384(sha2::Sha384::default()),
             #[cfg(feature = "sha512")]
-            SuitDigestAlgorithm::Sha512 => Self::Sha512(sha2::Sha512::default()),
+            SuitDigestAlgorithm::Sha512 => HasherInner::Sha512(sha2::Sha512::default()),
             #[cfg(feature = "shake128")]
-            SuitDigestAlgorithm::Shake128 => Self::Shake128(sha3::Shake128::default()),
+            SuitDigestAlgorithm::Shake128 => HasherInner::Shake128(sha3::Shake128::default()),
             #[cfg(feature = "shake256")]
-            SuitDigestAlgorithm::Shake256 => Self::Shake256(sha3::Shake256::default()),
-            #[allow(unreachable_patterns)]
-            algo => {
-                return Err(Error::UnsupportedDigestAlgo {
-                    algorithm: algo.into(),
-                })
-            }
-        })
+            SuitDigestAlgorithm::Shake256 => HasherInner::Shake256(sha3::Shake256::default()),
+        };
+        Ok(Self { inner })
     }
 
-    fn output_size(&self) -> usize {
-        match self {
-            Hasher::Sha2(_) => sha2::Sha256::output_size(),
+    /// Size of the digest in bytes.
+    pub fn output_size(&self) -> usize {
+        match self.inner {
+            HasherInner::Sha2(_) => sha2::Sha256::output_size(),
             #[cfg(feature = "sha384")]
-            Hasher::Sha384(_) => sha2::Sha384::output_size(),
+            HasherInner::Sha384(_) => sha2::Sha384::output_size(),
             #[cfg(feature = "sha512")]
-            Hasher::Sha512(_) => sha2::Sha512::output_size(),
+            HasherInner::Sha512(_) => sha2::Sha512::output_size(),
             #[cfg(feature = "shake128")]
-            Hasher::Shake128(_) => 32, // RFC 9054 defined
+            HasherInner::Shake128(_) => 32,
             #[cfg(feature = "shake256")]
-            Hasher::Shake256(_) => 64, // RFC 9054 defined
+            HasherInner::Shake256(_) => 64,
         }
     }
 
+    /// Finalize the hasher into the digest bytes.
+    ///
+    /// Returns [`Error::CapacityError`] if the digest exceeds [`MAX_DIGEST_SIZE`].
+    pub fn finalize(self) -> Result<heapless::Vec<u8, MAX_DIGEST_SIZE>, Error> {
+        let mut output = heapless::Vec::new();
+        output
+            .resize(self.output_size(), 0)
+            .map_err(|_| Error::CapacityError)?;
+        self.finalize_into(&mut output);
+        Ok(output)
+    }
+
     fn finalize_into(self, out: &mut [u8]) {
-        match self {
-            Hasher::Sha2(core_wrapper) => core_wrapper.finalize_into(out.into()),
+        match self.inner {
+            HasherInner::Sha2(core_wrapper) => core_wrapper.finalize_into(out.into()),
             #[cfg(feature = "sha384")]
-            Hasher::Sha384(core_wrapper) => core_wrapper.finalize_into(out.into()),
+            HasherInner::Sha384(core_wrapper) => core_wrapper.finalize_into(out.into()),
             #[cfg(feature = "sha512")]
-            Hasher::Sha512(core_wrapper) => core_wrapper.finalize_into(out.into()),
+            HasherInner::Sha512(core_wrapper) => core_wrapper.finalize_into(out.into()),
             #[cfg(feature = "shake128")]
-            Hasher::Shake128(core_wrapper) => core_wrapper.finalize_xof_into(out),
+            HasherInner::Shake128(core_wrapper) => core_wrapper.finalize_xof_into(out),
             #[cfg(feature = "shake256")]
-            Hasher::Shake256(core_wrapper) => core_wrapper.finalize_xof_into(out),
+            HasherInner::Shake256(core_wrapper) => core_wrapper.finalize_xof_into(out),
         }
     }
 }
 
 impl Update for Hasher {
     fn update(&mut self, data: &[u8]) {
-        match self {
-            Hasher::Sha2(core_wrapper) => core_wrapper.update(data),
+        match &mut self.inner {
+            HasherInner::Sha2(core_wrapper) => core_wrapper.update(data),
             #[cfg(feature = "sha384")]
-            Hasher::Sha384(core_wrapper) => core_wrapper.update(data),
+            HasherInner::Sha384(core_wrapper) => core_wrapper.update(data),
             #[cfg(feature = "sha512")]
-            Hasher::Sha512(core_wrapper) => core_wrapper.update(data),
+            HasherInner::Sha512(core_wrapper) => core_wrapper.update(data),
             #[cfg(feature = "shake128")]
-            Hasher::Shake128(core_wrapper) => core_wrapper.update(data),
+            HasherInner::Shake128(core_wrapper) => core_wrapper.update(data),
             #[cfg(feature = "shake256")]
-            Hasher::Shake256(core_wrapper) => core_wrapper.update(data),
+            HasherInner::Shake256(core_wrapper) => core_wrapper.update(data),
         }
     }
 }
@@ -202,6 +327,28 @@ mod tests {
     use super::*;
     extern crate std;
 
+    #[test]
+    #[cfg(feature = "alloc")]
+    fn into_owned() {
+        let owned = {
+            let bytes = std::vec![0xe3, 0xb0, 0xc4, 0x42];
+            let digest = SuitDigest::new(SuitDigestAlgorithm::Sha256, bytes.as_slice().into());
+            digest.into_owned()
+        };
+        let digest = owned.as_digest();
+        assert_eq!(digest.algorithm(), SuitDigestAlgorithm::Sha256);
+        assert_eq!(digest.digest(), &[0xe3, 0xb0, 0xc4, 0x42]);
+    }
+
+    #[test]
+    fn fmt_hex() {
+        let bytes: &[u8] = &[0xe3, 0xb0, 0xc4, 0x42];
+        let digest = SuitDigest::new(SuitDigestAlgorithm::Sha256, bytes.into());
+        let mut s = heapless::string::String::<8>::new();
+        digest.fmt_hex(&mut s).unwrap();
+        assert_eq!(s.as_str(), "e3b0c442");
+    }
+
     #[test]
     fn sha2() {
         let input: &[u8] = &std::vec![];
@@ -214,10 +361,12 @@ mod tests {
         let mut hasher = digest.hasher().unwrap();
         hasher.update(input);
         assert_eq!(hasher.output_size(), solution.len());
+        assert_eq!(hasher.clone().finalize().unwrap().as_slice(), solution);
         assert_eq!(digest.match_hasher(hasher), Ok(true));
     }
 
     #[test]
+    #[cfg(feature = "sha384")]
     fn sha384() {
         let input: &[u8] = &std::vec![0x8d, 0x45, 0xa5, 0x5d, 0x5c, 0xe1, 0xf9, 0x28, 0xe6];
         let solution: &[u8] = &std::vec![
@@ -234,6 +383,7 @@ mod tests {
     }
 
     #[test]
+    #[cfg(feature = "sha512")]
     fn sha512() {
         let input: &[u8] = &std::vec![0x16, 0x2b, 0x0c, 0xf9, 0xb3, 0x75, 0x0f, 0x94, 0x38];
         let solution: &[u8] = &std::vec![
@@ -251,6 +401,7 @@ mod tests {
     }
 
     #[test]
+    #[cfg(feature = "shake128")]
     fn shake128() {
         let input: &[u8] = &std::vec![
             0x22, 0x63, 0x4f, 0x6b, 0xa7, 0xb4, 0xfc, 0xca, 0xa3, 0xba, 0x40, 0x40, 0xb6, 0x64,
@@ -269,6 +420,35 @@ mod tests {
     }
 
     #[test]
+    #[cfg(feature = "shake128")]
+    fn shake128_truncated() {
+        let input: &[u8] = &std::vec![
+            0x22, 0x63, 0x4f, 0x6b, 0xa7, 0xb4, 0xfc, 0xca, 0xa3, 0xba, 0x40, 0x40, 0xb6, 0x64,
+            0xdb, 0xe5
+        ];
+        // First 16 bytes of the 32 byte output
+        let solution: &[u8] = &std::vec![
+            0x1a, 0x3e, 0x90, 0x82, 0x1c, 0xd0, 0xa8, 0x8e, 0x5a, 0x6d, 0xa7, 0x28, 0xba, 0xca,
+            0xa3, 0x0f
+        ];
+        let digest = SuitDigest::new(SuitDigestAlgorithm::Shake128, solution.into());
+        let mut hasher = digest.hasher().unwrap();
+        hasher.update(input);
+        let unsupported = Error::UnsupportedDigestAlgo { algorithm: -18 };
+        assert_eq!(digest.match_hasher(hasher.clone()), Err(unsupported));
+
+        let too_long = [0u8; MAX_DIGEST_SIZE + 1];
+        let digest = SuitDigest::new(SuitDigestAlgorithm::Shake128, too_long.as_slice().into());
+        assert_eq!(digest.match_hasher(hasher), Err(unsupported));
+
+        // [-18, h'1a3e9082']
+        let input: &[u8] = &std::vec![0x82, 0x31, 0x44, 0x1a, 0x3e, 0x90, 0x82];
+        let res = SuitDigest::from_cbor(&mut minicbor::Decoder::new(input));
+        assert_eq!(res, Err(unsupported));
+    }
+
+    #[test]
+    #[cfg(feature = "shake256")]
     fn shake256() {
         let input: &[u8] = &std::vec![
             0xdc, 0x88, 0x6d, 0xf3, 0xf6, 0x9c, 0x49, 0x51, 0x3d, 0xe3, 0x62, 0x7e, 0x94, 0x81,
@@ -290,6 +470,7 @@ mod tests {
     }
 
     #[test]
+    #[cfg(feature = "sha384")]
     fn algo_mismatch() {
         let input: &[u8] = &std::vec![];
         let solution: &[u8] = &std::vec![
@@ -345,4 +526,25 @@ mod tests {
         assert!(suit_err.is_type_mismatch());
         assert_eq!(suit_err.position(), None);
     }
+
+    #[test]
+    fn disabled_algorithm() {
+        // [-44, h'00']
+        let input: &[u8] = &std::vec![0x82, 0x38, 0x2B, 0x41, 0x00];
+        let res = SuitDigest::from_cbor(&mut minicbor::Decoder::new(input));
+        if cfg!(feature = "sha512") {
+            assert!(res.is_ok());
+        } else {
+            assert_eq!(res, Err(Error::UnsupportedDigestAlgo { algorithm: -44 }));
+        }
+    }
+
+    #[test]
+    fn sha1_unsupported() {
+        // COSE algorithm -14 is SHA-1, not SHA-224
+        assert_eq!(
+            SuitDigestAlgorithm::try_from(-14),
+            Err(Error::UnsupportedDigestAlgo { algorithm: -14 })
+        );
+    }
 }
@@ -0,0 +1,171 @@
+//! SUIT payload encryption.
+//!
+//! Parses the encryption info parameter from the SUIT firmware encryption extension. The
+//! decryption itself is left to the operating system via [`crate::OperatingHooks::decrypt`].
+//! See <https://datatracker.ietf.org/doc/html/draft-ietf-suit-firmware-encryption>
+use minicbor::data::{Tag, Type};
+use minicbor::decode::Decoder;
+
+use crate::{cose::CoseHeaders, error::Error};
+
+/// COSE_Encrypt tag.
+const COSE_ENCRYPT: Tag = Tag::new(96);
+/// COSE_Encrypt0 tag.
+const COSE_ENCRYPT0: Tag = Tag::new(16);
+
+/// Content encryption algorithm numbers.
+///
+/// Contains content encryption algorithm numbers from the COSE algorithm registry.
+/// See <https://www.iana.org/assignments/cose/cose.xhtml#algorithms>
+#[derive(Copy, Clone, Debug, PartialEq, num_enum::IntoPrimitive, num_enum::TryFromPrimitive)]
+#[num_enum(error_type(name = Error, constructor = Error::encryption_algo_error))]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[non_exhaustive]
+#[repr(i64)]
+pub enum EncryptionAlgorithm {
+    /// AES-GCM with a 256 bit key
+    A256Gcm = 3,
+}
+
+/// Encryption info of an encrypted payload.
+///
+/// Decoded from the COSE_Encrypt or COSE_Encrypt0 structure in the encryption info parameter.
+/// The payload itself is detached from the structure.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct EncryptionInfo<'a> {
+    algorithm: EncryptionAlgorithm,
+    iv: &'a [u8],
+    key_id: Option<&'a [u8]>,
+}
+
+impl<'a> EncryptionInfo<'a> {
+    /// The content encryption algorithm.
+    pub fn algorithm(&self) -> EncryptionAlgorithm {
+        self.algorithm
+    }
+
+    /// The initialization vector.
+    pub fn iv(&self) -> &'a [u8] {
+        self.iv
+    }
+
+    /// Identifier of the key, taken from the first recipient for COSE_Encrypt.
+    pub fn key_id(&self) -> Option<&'a [u8]> {
+        self.key_id
+    }
+}
+
+impl<'a> EncryptionInfo<'a> {
+    fn decode_structure(decoder: &mut Decoder<'a>) -> Result<Self, Error> {
+        let start = decoder.position();
+        if decoder.datatype()? == Type::Tag {
+            let tag = decoder.tag()?;
+            if tag != COSE_ENCRYPT && tag != COSE_ENCRYPT0 {
+                return Err(Error::UnexpectedCbor { position: start });
+            }
+        }
+        let position = decoder.position();
+        let len = decoder
+            .array()?
+            .ok_or(Error::UnexpectedIndefiniteLength { position })?;
+        if len != 3 && len != 4 {
+            return Err(Error::UnexpectedCbor { position });
+        }
+        let mut headers = CoseHeaders::decode(decoder)?;
+        // The ciphertext is detached
+        let position = decoder.position();
+        if decoder.datatype()? != Type::Null {
+            return Err(Error::UnexpectedCbor { position });
+        }
+        decoder.null()?;
+        if len == 4 {
+            let position = decoder.position();
+            let recipients = decoder
+                .array()?
+                .ok_or(Error::UnexpectedIndefiniteLength { position })?;
+            for index in 0..recipients {
+                if index == 0 && headers.kid().is_none() {
+                    headers.set_kid(Self::decode_recipient_kid(decoder)?);
+                } else {
+                    decoder.skip()?;
+                }
+            }
+        }
+        let algorithm = headers
+            .alg()
+            .ok_or(Error::UnexpectedCbor { position: start })?
+            .try_into()?;
+        let iv = headers
+            .iv()
+            .ok_or(Error::UnexpectedCbor { position: start })?;
+        Ok(Self {
+            algorithm,
+            iv,
+            key_id: headers.kid(),
+        })
+    }
+
+    /// Decode the key identifier from a COSE_recipient.
+    fn decode_recipient_kid(decoder: &mut Decoder<'a>) -> Result<Option<&'a [u8]>, Error> {
+        let position = decoder.position();
+        let len = decoder
+            .array()?
+            .ok_or(Error::UnexpectedIndefiniteLength { position })?;
+        if len != 3 && len != 4 {
+            return Err(Error::UnexpectedCbor { position });
+        }
+        let headers = CoseHeaders::decode(decoder)?;
+        // Encrypted key and nested recipients
+        for _ in 2..len {
+            decoder.skip()?;
+        }
+        Ok(headers.kid())
+    }
+
+    /// Decode the encryption info from the bstr wrapped parameter value.
+    pub(crate) fn from_cbor(decoder: &mut Decoder<'a>) -> Result<Self, Error> {
+        let info = decoder.bytes()?;
+        let offset = decoder.position() - info.len();
+        Self::decode_structure(&mut Decoder::new(info)).map_err(|e| e.add_offset(offset))
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod tests {
+    extern crate std;
+    use super::*;
+
+    #[test]
+    fn encrypt0() {
+        // 16([h'A10103', {5: h'0102', 4: h'AA'}, null])
+        let input = std::vec![
+            0xD0, 0x83, 0x43, 0xA1, 0x01, 0x03, 0xA2, 0x05, 0x42, 0x01, 0x02, 0x04, 0x41, 0xAA,
+            0xF6
+        ];
+        let info = EncryptionInfo::decode_structure(&mut Decoder::new(&input)).unwrap();
+        assert_eq!(info.algorithm(), EncryptionAlgorithm::A256Gcm);
+        assert_eq!(info.iv(), &[0x01, 0x02]);
+        assert_eq!(info.key_id(), Some([0xAA].as_slice()));
+    }
+
+    #[test]
+    fn encrypt_recipient() {
+        // 96([h'A10103', {5: h'01'}, null, [[h'', {4: h'BB'}, h'00']]])
+        let input = std::vec![
+            0xD8, 0x60, 0x84, 0x43, 0xA1, 0x01, 0x03, 0xA1, 0x05, 0x41, 0x01, 0xF6, 0x81, 0x83,
+            0x40, 0xA1, 0x04, 0x41, 0xBB, 0x41, 0x00
+        ];
+        let info = EncryptionInfo::decode_structure(&mut Decoder::new(&input)).unwrap();
+        assert_eq!(info.iv(), &[0x01]);
+        assert_eq!(info.key_id(), Some([0xBB].as_slice()));
+    }
+
+    #[test]
+    fn unsupported_algorithm() {
+        // [h'A10101', {5: h'01'}, null], A128GCM
+        let input = std::vec![0x83, 0x43, 0xA1, 0x01, 0x01, 0xA1, 0x05, 0x41, 0x01, 0xF6];
+        let res = EncryptionInfo::decode_structure(&mut Decoder::new(&input));
+        assert_eq!(res, Err(Error::UnsupportedEncryptionAlgo { algorithm: 1 }));
+    }
+}
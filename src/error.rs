@@ -1,6 +1,8 @@
 //! SUIT parsing errors.
 use core::convert::From;
 
+use crate::consts::SuitCommand;
+
 /// SUIT manifest parsing errors
 ///
 /// TODO! ensure error locations match the location within the manifest.
@@ -13,6 +15,29 @@ pub enum Error {
     AuthenticationFailure,
     /// String Capacity exhausted.
     CapacityError,
+    /// Payload does not fit in the component.
+    InsufficientCapacity {
+        /// Size of the payload in bytes.
+        needed: usize,
+        /// Capacity of the component in bytes.
+        available: usize,
+    },
+    /// Read from a component past the end of the component.
+    ReadOutOfBounds {
+        /// Offset of the read in bytes.
+        offset: usize,
+        /// Length of the read in bytes.
+        len: usize,
+        /// Size of the component in bytes.
+        size: usize,
+    },
+    /// Offset and length of a component access exceed the address range.
+    SizeOverflow {
+        /// Offset of the access in bytes.
+        offset: usize,
+        /// Length of the access in bytes.
+        len: usize,
+    },
     /// SUIT Condition match failure.
     ///
     /// Returned when a SUIT command condition did not match the expected.
@@ -20,15 +45,78 @@ pub enum Error {
         /// Position of the condition match failure in the manifest.
         position: usize,
     },
+    /// The manifest aborted the processing with an abort command.
+    ManifestAbort {
+        /// Position of the abort command in the manifest.
+        position: usize,
+    },
+    /// SUIT Condition failed for a component.
+    ///
+    /// Returned by the component conditions and by manifest level checks covering all components.
+    ComponentConditionFailed {
+        /// Index of the component in the manifest.
+        index: u32,
+        /// The failed condition.
+        command: i32,
+        /// Position of the failed condition in the manifest.
+        position: usize,
+    },
+    /// The component slot condition failed for the current component.
+    ComponentSlotMismatch {
+        /// The component slot expected by the manifest.
+        slot: u64,
+        /// Position of the failed condition in the manifest.
+        position: usize,
+    },
+    /// A chunk of a fetched payload arrived out of order.
+    ///
+    /// Payloads that are decrypted or verified while fetching must arrive in consecutive chunks.
+    ChunkOutOfOrder {
+        /// Offset of the chunk in the payload.
+        offset: usize,
+        /// Offset following the previous chunk.
+        expected: usize,
+    },
+    /// The digest of a fetched payload does not match the image digest.
+    ///
+    /// The payload is erased again. Unlike a failed condition this never ends a sequence as soft
+    /// failure or moves a try each on to the next sequence.
+    DigestMismatch {
+        /// Position of the fetch command in the manifest.
+        position: usize,
+    },
     /// SUIT Try Each command sequence failed every sequence.
     TryEachFail {
-        /// Position of the try each command failure in the manifest.
+        /// Position of the failed condition of the last sequence in the manifest.
+        position: usize,
+    },
+    /// SUIT Try Each sequence failed after executing a directive with side effects.
+    ///
+    /// The side effects on the components cannot be rolled back, the remaining sequences are not
+    /// tried.
+    TryEachSideEffect {
+        /// Position of the failure in the sequence with side effects.
         position: usize,
     },
     /// Unexpected end of the CBOR input.
     EndOfInput,
+    /// Digest algorithm named by the manifest is not permitted by the digest policy.
+    DigestAlgorithmNotPermitted {
+        /// The algorithm number.
+        algorithm: i64,
+    },
+    /// Map key occurs more than once in the map.
+    DuplicateKey {
+        /// Position of the repeated key in the manifest.
+        position: usize,
+    },
     /// Authentication structure is not valid.
     InvalidAuthenticationStructure,
+    /// Element of the authentication structure is not a bstr wrapped digest or COSE structure.
+    MalformedAuthBlock {
+        /// Index of the element in the authentication structure, the digest is at index 0.
+        index: usize,
+    },
     /// Invalid command sequence.
     InvalidCommandSequence {
         /// Position of the command sequence parsing failure in the manifest.
@@ -36,6 +124,24 @@ pub enum Error {
     },
     /// Invalid common section.
     InvalidCommonSection,
+    /// Command sequences or dependencies are nested deeper than
+    /// [`crate::consts::SUIT_MAX_NESTING`].
+    RecursionLimitExceeded,
+    /// A dependent manifest failed to authenticate or to validate.
+    DependencyFailed {
+        /// Index of the dependency.
+        index: u32,
+    },
+    /// CBOR item is not encoded deterministically.
+    NonCanonicalCbor {
+        /// Position of the non-deterministic CBOR element.
+        position: usize,
+    },
+    /// Severed element not found in the envelope, although the manifest contains its digest.
+    MissingSeveredElement {
+        /// The manifest section number of the severed element.
+        section: i16,
+    },
     /// No authentication object found inside the SUIT envelope.
     NoAuthObject,
     /// No common section found inside the SUIT manifest.
@@ -47,17 +153,39 @@ pub enum Error {
     },
     /// No component list inside the SUIT common section
     NoComponentList,
+    /// Component index not found in the component list of the manifest.
+    NoComponent {
+        /// Index of the component.
+        index: u32,
+    },
     /// No manifest object found inside the SUIT envelope.
     NoManifestObject,
     /// No manifest encoding version found inside the manifest object.
     NoManifestVersion,
     /// No manifest sequence number found inside the manifest object.
     NoSequenceNumber,
+    /// Sequence number of the manifest is not greater than the installed one.
+    SequenceNumberTooLow {
+        /// Sequence number of the manifest.
+        sequence_number: u64,
+        /// Sequence number stored for the component.
+        stored: u64,
+    },
     /// Parameter required for the condition is not set.
     ParameterNotSet {
         /// Position of the command for which the parameter is not set in the manifest.
         position: usize,
     },
+    /// CBOR element is malformed, e.g. invalid UTF-8 or a value exceeding its range.
+    MalformedCbor {
+        /// Position of the malformed CBOR element.
+        position: usize,
+    },
+    /// CBOR element has a different type or tag than expected by the decoder.
+    TypeMismatch {
+        /// Position of the mismatching CBOR element.
+        position: usize,
+    },
     /// CBOR element type at location is unexpected.
     UnexpectedCbor {
         /// Position of the unexpected CBOR element.
@@ -78,13 +206,23 @@ pub enum Error {
         /// The identifier of the component that is not supported.
         identifier: i64,
     },
+    /// Authentication block is neither a COSE_Sign1 nor a COSE_Sign structure.
+    UnsupportedCoseStructure,
     /// SUIT digest algorithm is not supported by the processor.
     UnsupportedDigestAlgo {
         /// The algorithm number.
         algorithm: i64,
     },
+    /// Content encryption algorithm is not supported by the processor.
+    UnsupportedEncryptionAlgo {
+        /// The algorithm number.
+        algorithm: i64,
+    },
     /// SUIT manifest version number is not supported by the processor.
-    UnsupportedManifestVersion,
+    UnsupportedManifestVersion {
+        /// The manifest encoding version found in the manifest.
+        version: u64,
+    },
     /// SUIT parameter is not supported by the processor.
     UnsupportedParameter {
         /// The parameter number.
@@ -102,21 +240,96 @@ impl Error {
         Error::UnsupportedDigestAlgo { algorithm: value }
     }
 
+    /// Position in the manifest where the error occurred, if the error carries one.
+    pub fn position(&self) -> Option<usize> {
+        match self {
+            Error::ConditionMatchFail { position }
+            | Error::ManifestAbort { position }
+            | Error::ComponentConditionFailed { position, .. }
+            | Error::ComponentSlotMismatch { position, .. }
+            | Error::DigestMismatch { position }
+            | Error::TryEachFail { position }
+            | Error::TryEachSideEffect { position }
+            | Error::DuplicateKey { position }
+            | Error::InvalidCommandSequence { position }
+            | Error::NonCanonicalCbor { position }
+            | Error::ParameterNotSet { position }
+            | Error::MalformedCbor { position }
+            | Error::TypeMismatch { position }
+            | Error::UnexpectedCbor { position }
+            | Error::UnexpectedIndefiniteLength { position }
+            | Error::Utf8Error { position } => Some(*position),
+            _ => None,
+        }
+    }
+
+    pub(crate) fn encryption_algo_error(value: i64) -> Self {
+        Error::UnsupportedEncryptionAlgo { algorithm: value }
+    }
+
+    /// Attribute a failed component condition to the index of the component it was checked for.
+    pub(crate) fn for_component(self, index: u32, command: SuitCommand) -> Self {
+        match self {
+            Error::ConditionMatchFail { position } if command.is_component_condition() => {
+                Error::ComponentConditionFailed {
+                    index,
+                    command: command.into(),
+                    position,
+                }
+            }
+            e => e,
+        }
+    }
+
     /// Use to modify error position on bytes-string wrapped CBOR
     pub(crate) fn add_offset(self, offset: usize) -> Self {
         match self {
             Error::ConditionMatchFail { position } => Error::ConditionMatchFail {
                 position: position + offset,
             },
+            Error::ManifestAbort { position } => Error::ManifestAbort {
+                position: position + offset,
+            },
+            Error::ComponentConditionFailed {
+                index,
+                command,
+                position,
+            } => Error::ComponentConditionFailed {
+                index,
+                command,
+                position: position + offset,
+            },
+            Error::ComponentSlotMismatch { slot, position } => Error::ComponentSlotMismatch {
+                slot,
+                position: position + offset,
+            },
+            Error::DigestMismatch { position } => Error::DigestMismatch {
+                position: position + offset,
+            },
             Error::TryEachFail { position } => Error::TryEachFail {
                 position: position + offset,
             },
+            Error::TryEachSideEffect { position } => Error::TryEachSideEffect {
+                position: position + offset,
+            },
+            Error::DuplicateKey { position } => Error::DuplicateKey {
+                position: position + offset,
+            },
             Error::InvalidCommandSequence { position } => Error::InvalidCommandSequence {
                 position: position + offset,
             },
+            Error::NonCanonicalCbor { position } => Error::NonCanonicalCbor {
+                position: position + offset,
+            },
             Error::ParameterNotSet { position } => Error::ParameterNotSet {
                 position: position + offset,
             },
+            Error::MalformedCbor { position } => Error::MalformedCbor {
+                position: position + offset,
+            },
+            Error::TypeMismatch { position } => Error::TypeMismatch {
+                position: position + offset,
+            },
             Error::UnexpectedCbor { position } => Error::UnexpectedCbor {
                 position: position + offset,
             },
@@ -136,26 +349,97 @@ impl core::fmt::Display for Error {
         match self {
             Self::AuthenticationFailure => write!(f, "authentication of manifest failed"),
             Self::CapacityError => write!(f, "string capacity exhausted"),
+            Self::InsufficientCapacity { needed, available } => {
+                write!(
+                    f,
+                    "payload of {needed} bytes exceeds capacity of {available} bytes"
+                )
+            }
+            Self::ReadOutOfBounds { offset, len, size } => {
+                write!(
+                    f,
+                    "read of {len} bytes at {offset} exceeds component size of {size} bytes"
+                )
+            }
+            Self::SizeOverflow { offset, len } => {
+                write!(f, "access of {len} bytes at {offset} overflows")
+            }
             Self::ConditionMatchFail { position } => write!(f, "condition mismatch at {position}"),
+            Self::ManifestAbort { position } => write!(f, "manifest aborted at {position}"),
+            Self::ComponentConditionFailed {
+                index,
+                command,
+                position,
+            } => {
+                write!(
+                    f,
+                    "condition {command} failed for component {index} at {position}"
+                )
+            }
+            Self::ComponentSlotMismatch { slot, position } => {
+                write!(f, "component slot {slot} mismatch at {position}")
+            }
+            Self::ChunkOutOfOrder { offset, expected } => {
+                write!(
+                    f,
+                    "payload chunk at {offset} out of order, expected {expected}"
+                )
+            }
+            Self::DigestMismatch { position } => {
+                write!(f, "fetched payload digest mismatch at {position}")
+            }
             Self::TryEachFail { position } => write!(f, "try each sequence failed at {position}"),
+            Self::TryEachSideEffect { position } => {
+                write!(
+                    f,
+                    "try each sequence with side effects failed at {position}"
+                )
+            }
             Self::EndOfInput => write!(f, "end of CBOR input"),
+            Self::DigestAlgorithmNotPermitted { algorithm } => {
+                write!(f, "digest algorithm {algorithm} not permitted")
+            }
+            Self::DuplicateKey { position } => write!(f, "duplicate map key at {position}"),
             Self::InvalidAuthenticationStructure => write!(f, "invalide authentication structure"),
+            Self::MalformedAuthBlock { index } => {
+                write!(f, "malformed authentication block at index {index}")
+            }
             Self::InvalidCommandSequence { position } => {
                 write!(f, "invalid command sequence at {position}")
             }
             Self::InvalidCommonSection => write!(f, "invalid common section found in manifest"),
+            Self::RecursionLimitExceeded => write!(f, "command sequences nested too deep"),
+            Self::DependencyFailed { index } => write!(f, "dependency {index} failed"),
+            Self::NonCanonicalCbor { position } => {
+                write!(f, "non-deterministic CBOR encoding at {position}")
+            }
+            Self::MissingSeveredElement { section } => {
+                write!(f, "severed element {section} missing from envelope")
+            }
             Self::NoAuthObject => write!(f, "no Authentication object in manifest"),
             Self::NoCommonSection => write!(f, "no common section found in manifest"),
             Self::NoCommandSection { section } => {
                 write!(f, "no command sequence {section} found in manifest")
             }
             Self::NoComponentList => write!(f, "no component list found in manifest"),
+            Self::NoComponent { index } => write!(f, "no component {index} in manifest"),
             Self::NoManifestObject => write!(f, "no Manifest object in manifest"),
             Self::NoManifestVersion => write!(f, "no Manifest version in manifest"),
             Self::NoSequenceNumber => write!(f, "no Manifest sequence number in manifest"),
+            Self::SequenceNumberTooLow {
+                sequence_number,
+                stored,
+            } => {
+                write!(
+                    f,
+                    "sequence number {sequence_number} not greater than installed {stored}"
+                )
+            }
             Self::ParameterNotSet { position } => {
                 write!(f, "parameter required for condition at {position} not set")
             }
+            Self::MalformedCbor { position } => write!(f, "malformed CBOR at {position}"),
+            Self::TypeMismatch { position } => write!(f, "CBOR type mismatch at {position}"),
             Self::UnexpectedCbor { position } => write!(f, "unexpected CBOR found at {position}"),
             Self::UnexpectedIndefiniteLength { position } => {
                 write!(
@@ -167,10 +451,16 @@ impl core::fmt::Display for Error {
             Self::UnsupportedComponentIdentifier { identifier } => {
                 write!(f, "component identifier {identifier} not supported")
             }
+            Self::UnsupportedCoseStructure => write!(f, "COSE structure not supported"),
             Self::UnsupportedDigestAlgo { algorithm } => {
                 write!(f, "digest algorithm {algorithm} not supported")
             }
-            Self::UnsupportedManifestVersion => write!(f, "manifest version not supported"),
+            Self::UnsupportedEncryptionAlgo { algorithm } => {
+                write!(f, "encryption algorithm {algorithm} not supported")
+            }
+            Self::UnsupportedManifestVersion { version } => {
+                write!(f, "manifest version {version} not supported")
+            }
             Self::UnsupportedParameter { parameter } => {
                 write!(f, "parameter {parameter} not supported")
             }
@@ -181,15 +471,226 @@ impl core::fmt::Display for Error {
     }
 }
 
+#[cfg(feature = "defmt")]
+impl defmt::Format for Error {
+    fn format(&self, f: defmt::Formatter<'_>) {
+        match self {
+            Self::AuthenticationFailure => defmt::write!(f, "authentication of manifest failed"),
+            Self::CapacityError => defmt::write!(f, "string capacity exhausted"),
+            Self::InsufficientCapacity { needed, available } => {
+                defmt::write!(
+                    f,
+                    "payload of {} bytes exceeds capacity of {} bytes",
+                    needed,
+                    available
+                )
+            }
+            Self::ReadOutOfBounds { offset, len, size } => {
+                defmt::write!(
+                    f,
+                    "read of {} bytes at {} exceeds component size of {} bytes",
+                    len,
+                    offset,
+                    size
+                )
+            }
+            Self::SizeOverflow { offset, len } => {
+                defmt::write!(f, "access of {} bytes at {} overflows", len, offset)
+            }
+            Self::ConditionMatchFail { position } => {
+                defmt::write!(f, "condition mismatch at {}", position)
+            }
+            Self::ManifestAbort { position } => {
+                defmt::write!(f, "manifest aborted at {}", position)
+            }
+            Self::ComponentConditionFailed {
+                index,
+                command,
+                position,
+            } => {
+                defmt::write!(
+                    f,
+                    "condition {} failed for component {} at {}",
+                    command,
+                    index,
+                    position
+                )
+            }
+            Self::ComponentSlotMismatch { slot, position } => {
+                defmt::write!(f, "component slot {} mismatch at {}", slot, position)
+            }
+            Self::ChunkOutOfOrder { offset, expected } => {
+                defmt::write!(
+                    f,
+                    "payload chunk at {} out of order, expected {}",
+                    offset,
+                    expected
+                )
+            }
+            Self::DigestMismatch { position } => {
+                defmt::write!(f, "fetched payload digest mismatch at {}", position)
+            }
+            Self::TryEachFail { position } => {
+                defmt::write!(f, "try each sequence failed at {}", position)
+            }
+            Self::TryEachSideEffect { position } => {
+                defmt::write!(
+                    f,
+                    "try each sequence with side effects failed at {}",
+                    position
+                )
+            }
+            Self::EndOfInput => defmt::write!(f, "end of CBOR input"),
+            Self::DigestAlgorithmNotPermitted { algorithm } => {
+                defmt::write!(f, "digest algorithm {} not permitted", algorithm)
+            }
+            Self::DuplicateKey { position } => {
+                defmt::write!(f, "duplicate map key at {}", position)
+            }
+            Self::InvalidAuthenticationStructure => {
+                defmt::write!(f, "invalide authentication structure")
+            }
+            Self::MalformedAuthBlock { index } => {
+                defmt::write!(f, "malformed authentication block at index {}", index)
+            }
+            Self::InvalidCommandSequence { position } => {
+                defmt::write!(f, "invalid command sequence at {}", position)
+            }
+            Self::InvalidCommonSection => {
+                defmt::write!(f, "invalid common section found in manifest")
+            }
+            Self::RecursionLimitExceeded => defmt::write!(f, "command sequences nested too deep"),
+            Self::DependencyFailed { index } => defmt::write!(f, "dependency {} failed", index),
+            Self::NonCanonicalCbor { position } => {
+                defmt::write!(f, "non-deterministic CBOR encoding at {}", position)
+            }
+            Self::MissingSeveredElement { section } => {
+                defmt::write!(f, "severed element {} missing from envelope", section)
+            }
+            Self::NoAuthObject => defmt::write!(f, "no Authentication object in manifest"),
+            Self::NoCommonSection => defmt::write!(f, "no common section found in manifest"),
+            Self::NoCommandSection { section } => {
+                defmt::write!(f, "no command sequence {} found in manifest", section)
+            }
+            Self::NoComponentList => defmt::write!(f, "no component list found in manifest"),
+            Self::NoComponent { index } => defmt::write!(f, "no component {} in manifest", index),
+            Self::NoManifestObject => defmt::write!(f, "no Manifest object in manifest"),
+            Self::NoManifestVersion => defmt::write!(f, "no Manifest version in manifest"),
+            Self::NoSequenceNumber => defmt::write!(f, "no Manifest sequence number in manifest"),
+            Self::SequenceNumberTooLow {
+                sequence_number,
+                stored,
+            } => {
+                defmt::write!(
+                    f,
+                    "sequence number {} not greater than installed {}",
+                    sequence_number,
+                    stored
+                )
+            }
+            Self::ParameterNotSet { position } => {
+                defmt::write!(
+                    f,
+                    "parameter required for condition at {} not set",
+                    position
+                )
+            }
+            Self::MalformedCbor { position } => {
+                defmt::write!(f, "malformed CBOR at {}", position)
+            }
+            Self::TypeMismatch { position } => {
+                defmt::write!(f, "CBOR type mismatch at {}", position)
+            }
+            Self::UnexpectedCbor { position } => {
+                defmt::write!(f, "unexpected CBOR found at {}", position)
+            }
+            Self::UnexpectedIndefiniteLength { position } => {
+                defmt::write!(
+                    f,
+                    "unexpected indefinite length cbor container at {}",
+                    position
+                )
+            }
+            Self::UnsupportedCommand { command } => {
+                defmt::write!(f, "command {} not supported", command)
+            }
+            Self::UnsupportedComponentIdentifier { identifier } => {
+                defmt::write!(f, "component identifier {} not supported", identifier)
+            }
+            Self::UnsupportedCoseStructure => defmt::write!(f, "COSE structure not supported"),
+            Self::UnsupportedDigestAlgo { algorithm } => {
+                defmt::write!(f, "digest algorithm {} not supported", algorithm)
+            }
+            Self::UnsupportedEncryptionAlgo { algorithm } => {
+                defmt::write!(f, "encryption algorithm {} not supported", algorithm)
+            }
+            Self::UnsupportedManifestVersion { version } => {
+                defmt::write!(f, "manifest version {} not supported", version)
+            }
+            Self::UnsupportedParameter { parameter } => {
+                defmt::write!(f, "parameter {} not supported", parameter)
+            }
+            Self::Utf8Error { position } => {
+                defmt::write!(f, "unable to interpret bytes as string at {}", position)
+            }
+        }
+    }
+}
+
 impl core::error::Error for Error {}
 
+#[cfg(feature = "std")]
+impl From<Error> for std::io::Error {
+    fn from(err: Error) -> Self {
+        std::io::Error::new(std::io::ErrorKind::InvalidData, err)
+    }
+}
+
 impl From<minicbor::decode::Error> for Error {
     fn from(err: minicbor::decode::Error) -> Self {
+        let position = err.position().unwrap_or(0);
         if err.is_end_of_input() {
             Self::EndOfInput
+        } else if err.is_type_mismatch() || err.is_tag_mismatch() {
+            Self::TypeMismatch { position }
         } else {
-            let position = err.position().unwrap_or(0);
-            Self::UnexpectedCbor { position }
+            Self::MalformedCbor { position }
         }
     }
 }
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn position() {
+        let err = Error::UnexpectedCbor { position: 3 }.add_offset(2);
+        assert_eq!(err.position(), Some(5));
+        assert_eq!(Error::NoCommonSection.position(), None);
+    }
+
+    #[test]
+    fn decode_error() {
+        // A text string where an unsigned integer is expected
+        let err: Error = minicbor::Decoder::new(&[0x61, 0x78])
+            .u8()
+            .unwrap_err()
+            .into();
+        assert_eq!(err, Error::TypeMismatch { position: 0 });
+
+        // 256 does not fit in an u8
+        let err: Error = minicbor::Decoder::new(&[0x19, 0x01, 0x00])
+            .u8()
+            .unwrap_err()
+            .into();
+        assert_eq!(err, Error::MalformedCbor { position: 0 });
+
+        let err: Error = minicbor::Decoder::new(&[0x19, 0x01])
+            .u16()
+            .unwrap_err()
+            .into();
+        assert_eq!(err, Error::EndOfInput);
+    }
+}
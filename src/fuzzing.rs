@@ -0,0 +1,114 @@
+//! Fuzzing entry point.
+//!
+//! Runs the decoding path of a SUIT envelope on arbitrary input, as a single target for fuzzers
+//! such as `cargo fuzz`. The authentication is skipped to reach the manifest decoding and the
+//! command sequences.
+use core::marker::PhantomData;
+
+use minicbor::decode::Decoder;
+
+use crate::component::Component;
+use crate::error::Error;
+use crate::{Authenticated, OperatingHooks, SuitManifest};
+
+/// Seed inputs for the fuzzer, taken from the unit tests.
+pub const SEEDS: &[&[u8]] = &[
+    // 107({2: h'', 3: h'A1 01 01'})
+    &[0xd8, 0x6b, 0xa2, 0x02, 0x40, 0x03, 0x43, 0xa1, 0x01, 0x01],
+    // 107({2: h'', 3: <<{1: 1, 3: <<{2: [[h'00']], 4: <<[12, 0]>>}>>, 7: <<[14, 5]>>}>>})
+    &[
+        0xd8, 0x6b, 0xa2, 0x02, 0x40, 0x03, 0x55, 0xa3, 0x01, 0x01, 0x03, 0x4b, 0xa2, 0x02, 0x81,
+        0x81, 0x41, 0x00, 0x04, 0x43, 0x82, 0x0c, 0x00, 0x07, 0x43, 0x82, 0x0e, 0x05,
+    ],
+    // 107({2: h'', 3: <<{1: 1, 3: <<{2: [[h'00']], 4: <<[12, 0]>>}>>, 7: <<[25, 15]>>}>>})
+    &[
+        0xd8, 0x6b, 0xa2, 0x02, 0x40, 0x03, 0x56, 0xa3, 0x01, 0x01, 0x03, 0x4b, 0xa2, 0x02, 0x81,
+        0x81, 0x41, 0x00, 0x04, 0x43, 0x82, 0x0c, 0x00, 0x07, 0x44, 0x82, 0x18, 0x19, 0x0f,
+    ],
+];
+
+/// Operating hooks accepting every condition, with an empty component of no capacity.
+struct FuzzHooks;
+
+impl OperatingHooks for FuzzHooks {
+    type ReadWriteBufferSize = generic_array::typenum::U16;
+
+    fn match_vendor_id(&self, _uuid: uuid::Uuid, _component: &Component) -> Result<bool, Error> {
+        Ok(true)
+    }
+
+    fn match_class_id(&self, _uuid: uuid::Uuid, _component: &Component) -> Result<bool, Error> {
+        Ok(true)
+    }
+
+    fn component_read(
+        &self,
+        _component: &Component,
+        _slot: Option<u64>,
+        _offset: usize,
+        bytes: &mut [u8],
+    ) -> Result<(), Error> {
+        bytes.fill(0);
+        Ok(())
+    }
+
+    fn component_write(
+        &self,
+        _component: &Component,
+        _slot: Option<u64>,
+        _offset: usize,
+        _bytes: &[u8],
+    ) -> Result<(), Error> {
+        Ok(())
+    }
+
+    fn component_size(&self, _component: &Component) -> Result<usize, Error> {
+        Ok(0)
+    }
+
+    fn component_capacity(&self, _component: &Component) -> Result<usize, Error> {
+        Ok(0)
+    }
+}
+
+/// Decode the envelope and the manifest and run the image validation, ignoring all errors.
+///
+/// Malformed input must result in an error, a panic is a bug reported by the fuzzer as crash.
+pub fn fuzz_parse(bytes: &[u8]) {
+    let _ = parse(bytes);
+}
+
+fn parse(bytes: &[u8]) -> Result<(), Error> {
+    let suit = SuitManifest::<Authenticated> {
+        decoder: Decoder::new(bytes),
+        strict: false,
+        phantom: PhantomData,
+    };
+    let envelope = suit.envelope()?;
+    let manifest = envelope.manifest()?;
+    let _ = manifest.version();
+    let _ = manifest.sequence_number();
+    manifest.execute_image_validation(&FuzzHooks)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn seeds() {
+        assert_eq!(parse(SEEDS[0]), Err(Error::NoCommandSection { section: 7 }));
+        assert_eq!(parse(SEEDS[1]), Err(Error::ManifestAbort { position: 19 }));
+        assert_eq!(
+            parse(SEEDS[2]),
+            Err(Error::UnsupportedCommand { command: 25 })
+        );
+        // Every truncation of the seeds fails without panicking
+        for seed in SEEDS {
+            for len in 0..seed.len() {
+                assert!(parse(&seed[..len]).is_err());
+                fuzz_parse(&seed[..len]);
+            }
+        }
+    }
+}
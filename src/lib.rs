@@ -2,6 +2,9 @@
 #![allow(dead_code)]
 #![deny(clippy::unwrap_used, clippy::expect_used, clippy::panic)]
 #![deny(missing_docs)]
+// The command executor futures are nested once per supported nesting level of command sequences
+// and dependencies, their layout exceeds the default limit.
+#![recursion_limit = "256"]
 
 //! Dress‑Up provides a parser-only implementation of the [SUIT][suit-rfc] manifest format,
 //! for `no_std` environments. It relies on [minicbor] for CBOR parsing.
@@ -24,16 +27,25 @@
 //! Dress‑Up supports the following features from the SUIT manifest specification:
 //!
 //! - Multiple components
+//! - Dependencies, resolved and authenticated through the operating system, with the image
+//!   validation of the dependent manifest run when a dependency is selected
+//! - Severable elements
+//! - Reporting policy, with records collected into a report
+//! - Basic manifest creation through the [`builder`] module, for tooling and tests
+//! - Encrypted payloads behind the `encryption` feature, decrypted through the operating system
+//! - Asynchronous operating hooks behind the `async` feature
+//! - Rollback protection against the sequence numbers of the installed components
+//! - Multiple public keys, selected by the COSE key identifier through the operating system
+//! - SHA-256 digests, with SHA-384, SHA-512, SHAKE128 and SHAKE256 behind the `sha384`, `sha512`,
+//!   `shake128` and `shake256` features
 //!
 //! Dress‑Up does not yet support the following features:
 //!
-//! - Severable elements
-//! - Reporting policy
+//! - Encoding the SUIT report
 //!
 //! Dress‑Up considers the following features out of scope and there are no plans to support these:
 //!
 //! - Parallel processing
-//! - Manifest creation
 //!
 //! ### SUIT command support
 //!
@@ -45,6 +57,7 @@
 //! | Vendor Identifier     | ✅ |
 //! | Class Identifier      | ✅ |
 //! | Image Match           | ✅ |
+//! | Use Before            | ✅ |
 //! | Component Slot        | ✅ |
 //! | Check Content         | ✅ |
 //! | Set Component Index   | ✅ |
@@ -54,10 +67,10 @@
 //! | Override Parameters   | ✅ |
 //! | Fetch                 | ✅ |
 //! | Copy                  | 🚧 |
-//! | Invoke                | 🚧 |
+//! | Invoke                | ✅ |
 //! | Device Identifier     | ✅ |
 //! | Swap                  | 🚧 |
-//! | Run Sequence          | 🚧 |
+//! | Run Sequence          | ✅ |
 //! | Custom commands       | 🚧 |
 //!
 //! ### Parameter support
@@ -69,15 +82,17 @@
 //! | Vendor ID             | ✅ |
 //! | Class ID              | ✅ |
 //! | Image Digest          | ✅ |
+//! | Use Before            | ✅ |
 //! | Component Slot        | ✅ |
-//! | Strict Order          | ❌ |
-//! | Soft Failure          | 🚧 |
+//! | Strict Order          | ✅ |
+//! | Soft Failure          | ✅ |
 //! | Image Size            | ✅ |
 //! | Content               | ✅ |
 //! | URI                   | ✅ |
-//! | Source Component      | 🚧 |
-//! | Invoke Args           | 🚧 |
+//! | Source Component      | ✅ |
+//! | Invoke Args           | ✅ |
 //! | Device ID             | ✅ |
+//! | Encryption Info       | ✅ |
 //!
 //! ## Overview
 //!
@@ -185,31 +200,65 @@
 //! # Ok::<(), Error>(())
 //! ```
 //! [suit-rfc]: https://datatracker.ietf.org/doc/html/draft-ietf-suit-manifest-34
+#[cfg(feature = "alloc")]
+extern crate alloc;
+#[cfg(feature = "std")]
+extern crate std;
+
+use core::cell::OnceCell;
 use core::marker::PhantomData;
 
 use minicbor::bytes::ByteSlice;
+use minicbor::data::Type;
 use minicbor::decode::Decoder;
 
+#[cfg(feature = "async")]
+pub mod asynchooks;
+#[cfg(not(feature = "async"))]
+mod asynchooks;
 pub mod auth;
+pub mod builder;
 mod cbor;
 pub mod command;
 pub mod component;
 pub mod consts;
+pub mod cose;
 pub mod digest;
+#[cfg(feature = "encryption")]
+pub mod encryption;
 pub mod error;
+#[cfg(feature = "fuzzing")]
+pub mod fuzzing;
 pub mod manifest;
 pub mod manifeststate;
 pub mod operatinghooks;
 pub mod report;
+#[cfg(test)]
+mod testhooks;
+pub mod text;
+pub mod util;
 
 use crate::auth::Authentication;
-use crate::cbor::SubCbor;
+use crate::cbor::{check_canonical, check_unique_keys, decode_key, MapIndex, SubCbor};
 use crate::consts::*;
+use crate::digest::SuitDigest;
 use crate::error::Error;
 use crate::manifest::Manifest;
 
+#[cfg(feature = "async")]
+pub use crate::asynchooks::AsyncOperatingHooks;
+#[cfg(feature = "fuzzing")]
+pub use crate::fuzzing::fuzz_parse;
 pub use crate::operatinghooks::OperatingHooks;
 
+/// Render CBOR in diagnostic notation.
+///
+/// Nested bstr wrapped structures, such as the manifest inside the envelope, are shown as `h'..'`.
+#[cfg(feature = "std")]
+pub fn diagnostic(cbor: &[u8]) -> impl core::fmt::Display + '_ {
+    minicbor::display(cbor)
+}
+
 /// Authentication state of the manifest
 pub trait AuthState {}
 /// Manifest is new.
@@ -228,31 +277,112 @@ impl AuthState for Authenticated {}
 #[derive(Clone)]
 pub struct SuitManifest<'a, S: AuthState> {
     decoder: Decoder<'a>,
+    strict: bool,
     phantom: PhantomData<S>,
 }
 
 /// SUIT envelope.
 ///
 /// Processes the elements inside the SUIT envelope.
-#[derive(Clone)]
+#[derive(Clone, Debug)]
 pub struct Envelope<'a, S: AuthState> {
     decoder: Decoder<'a>,
+    /// Positions of the envelope elements, built on the first lookup
+    index: OnceCell<MapIndex<{ INDEXED_ELEMENTS.len() }>>,
     phantom: PhantomData<S>,
 }
 
+/// Payloads integrated in the envelope map.
+///
+/// A copyable view on the envelope for the command sequence executor.
+#[derive(Copy, Clone, Debug)]
+pub(crate) struct IntegratedPayloads<'a> {
+    envelope: &'a [u8],
+    /// Position of the envelope map
+    position: usize,
+}
+
+impl<'a> IntegratedPayloads<'a> {
+    /// The payload under the fragment-only URI as key, `None` for other URIs.
+    pub(crate) fn get(&self, uri: &str) -> Result<Option<&'a [u8]>, Error> {
+        if !uri.starts_with('#') {
+            return Ok(None);
+        }
+        let mut decoder = Decoder::new(self.envelope);
+        decoder.set_position(self.position);
+        let position = decoder.position();
+        let len = decoder
+            .map()?
+            .ok_or(Error::UnexpectedIndefiniteLength { position })?;
+        for _ in 0..len {
+            let matches = match decoder.datatype()? {
+                Type::String => decoder.str()? == uri,
+                _ => {
+                    decoder.skip()?;
+                    false
+                }
+            };
+            if matches {
+                return Ok(Some(decoder.bytes()?));
+            }
+            decoder.skip()?;
+        }
+        Ok(None)
+    }
+}
+
+/// Envelope elements with their position recorded in the index of the envelope map.
+const INDEXED_ELEMENTS: [SuitEnvelope; 5] = [
+    SuitEnvelope::Authentication,
+    SuitEnvelope::Manifest,
+    SuitEnvelope::PayloadFetch,
+    SuitEnvelope::PayloadInstallation,
+    SuitEnvelope::Text,
+];
+
 impl<'a, S: AuthState> SuitManifest<'a, S> {
     /// Retrieve the envelope of the manifest.
+    ///
+    /// The envelope is either tagged with the SUIT envelope tag or a bare map when the transport
+    /// already identifies the content as SUIT envelope.
+    ///
+    /// For manifests created with [`SuitManifest::from_bytes_strict`], the envelope and the
+    /// manifest object are checked for deterministic encoding first.
     pub fn envelope(&self) -> Result<Envelope<'a, S>, Error> {
         let mut decoder = self.decoder.clone();
+        if self.strict {
+            check_canonical(&mut decoder.clone())?;
+        }
         let position = decoder.position();
-        let tag = decoder.tag()?;
-        if tag != SUIT_TAG_ENVELOPE {
-            return Err(Error::UnexpectedCbor { position });
+        match decoder.datatype()? {
+            Type::Tag => {
+                if decoder.tag()? != SUIT_TAG_ENVELOPE {
+                    return Err(Error::UnexpectedCbor { position });
+                }
+            }
+            Type::Map | Type::MapIndef => {}
+            _ => return Err(Error::UnexpectedCbor { position }),
         }
-        Ok(Envelope {
+        let envelope = Envelope {
             decoder,
+            index: OnceCell::new(),
             phantom: PhantomData,
-        })
+        };
+        if self.strict {
+            if let Some(manifest) = envelope.get_object(SuitEnvelope::Manifest)? {
+                check_canonical(&mut Decoder::new(manifest))?;
+            }
+        }
+        Ok(envelope)
+    }
+
+    /// Check the structure of the SUIT envelope.
+    ///
+    /// Verifies that the envelope is a definite length map without duplicate keys, containing
+    /// the authentication and manifest objects. This is a cheap well-formedness check, the objects
+    /// themselves are not decoded. Duplicate keys are reported as [`Error::DuplicateKey`].
+    pub fn decode(&self) -> Result<(), Error> {
+        self.envelope()?.check_structure()
     }
 }
 
@@ -261,16 +391,112 @@ impl<'a> SuitManifest<'a, New> {
     pub fn from_bytes(bytes: &'a impl AsRef<[u8]>) -> Self {
         Self {
             decoder: Decoder::new(bytes.as_ref()),
+            strict: false,
             phantom: PhantomData,
         }
     }
 
+    /// Parse the SUIT envelope from a byte slice.
+    ///
+    /// Checks the envelope tag and the structure of the envelope up front, see
+    /// [`SuitManifest::decode`]. Use [`SuitManifest::from_bytes`] to defer all checks.
+    pub fn parse(bytes: &'a impl AsRef<[u8]>) -> Result<Envelope<'a, New>, Error> {
+        let envelope = Self::from_bytes(bytes).envelope()?;
+        envelope.check_structure()?;
+        Ok(envelope)
+    }
+
+    /// Peek at the manifest version without authenticating the manifest.
+    ///
+    /// The value is not authenticated. Use it only to reject a manifest early, before spending
+    /// cycles on the signature verification.
+    pub fn peek_version(&self) -> Result<u8, Error> {
+        self.envelope()?.manifest()?.version()
+    }
+
+    /// Peek at the manifest sequence number without authenticating the manifest.
+    ///
+    /// The value is not authenticated. Use it only to reject stale manifests early, before
+    /// spending cycles on the signature verification.
+    pub fn peek_sequence_number(&self) -> Result<u64, Error> {
+        self.envelope()?.manifest()?.sequence_number()
+    }
+
+    /// Create a SUIT manifest from a byte slice, requiring deterministic CBOR encoding.
+    ///
+    /// The envelope and the manifest must use the shortest encoding for integers and lengths,
+    /// definite length items only and sorted map keys as required by RFC 9124. Violations are
+    /// reported as [`Error::NonCanonicalCbor`] when retrieving the envelope.
+    pub fn from_bytes_strict(bytes: &'a impl AsRef<[u8]>) -> Self {
+        Self {
+            strict: true,
+            ..Self::from_bytes(bytes)
+        }
+    }
+
     /// Authenticate a manifest.
     pub fn authenticate<F>(self, authenticate: F) -> Result<SuitManifest<'a, Authenticated>, Error>
     where
         F: Fn(&[u8], &[u8]) -> Result<bool, Error>,
+    {
+        self.authenticate_object(|auth_object| auth_object.authenticate(authenticate))
+    }
+
+    /// Authenticate a manifest with one of multiple provisioned public keys.
+    ///
+    /// The key identifier in the COSE header of each authentication block selects the key via
+    /// [`OperatingHooks::public_key_for_kid`]. The closure receives the key, the COSE structure,
+    /// the payload and the signer index. Fails with [`Error::AuthenticationFailure`] if no block
+    /// verifies with a matching key.
+    ///
+    /// Blocks may be COSE_Sign1 or COSE_Sign structures, other structures are rejected with
+    /// [`Error::UnsupportedCoseStructure`]. For a COSE_Sign structure the key identifier of every
+    /// signer selects a key and the closure is called with the full structure and the index of
+    /// the signer for each of them, the signed bytes are then built with
+    /// [`cose::signer_sig_structure`]. The signer index is `None` for COSE_Sign1 structures.
+    pub fn authenticate_with_key<F>(
+        self,
+        os_hooks: &impl OperatingHooks,
+        authenticate: F,
+    ) -> Result<SuitManifest<'a, Authenticated>, Error>
+    where
+        F: Fn(&[u8], &[u8], &[u8], Option<usize>) -> Result<bool, Error>,
+    {
+        self.authenticate_object(|auth_object| {
+            auth_object.authenticate_with_key(os_hooks, authenticate)
+        })
+    }
+
+    /// Authenticate a manifest signed by multiple authorities.
+    ///
+    /// Succeeds when the signatures of at least `required` distinct keys verify, supporting M-of-N
+    /// signing policies. Keys are selected as with [`SuitManifest::authenticate_with_key`], the
+    /// signers of a COSE_Sign block count like separate blocks and every key counts once. Fails with
+    /// [`Error::AuthenticationFailure`] if the threshold is not met, a `required` of zero is
+    /// never met.
+    pub fn verify_threshold<F>(
+        self,
+        os_hooks: &impl OperatingHooks,
+        required: usize,
+        authenticate: F,
+    ) -> Result<SuitManifest<'a, Authenticated>, Error>
+    where
+        F: Fn(&[u8], &[u8], &[u8], Option<usize>) -> Result<bool, Error>,
+    {
+        self.authenticate_object(|auth_object| {
+            auth_object.authenticate_threshold(os_hooks, required, authenticate)
+        })
+    }
+
+    fn authenticate_object<F>(
+        self,
+        authenticate: F,
+    ) -> Result<SuitManifest<'a, Authenticated>, Error>
+    where
+        F: FnOnce(Authentication) -> Result<(), Error>,
     {
         let envelope = self.envelope()?;
+        envelope.check_structure()?;
         // Consists of a bstr wrapped digest + *bstr wrapped auth blocks
         let manifest = envelope.get_object_wrapped(SuitEnvelope::Manifest)?;
         let auth_object = envelope.get_object(SuitEnvelope::Authentication)?;
@@ -279,10 +505,10 @@ impl<'a> SuitManifest<'a, New> {
             (None, _) => Err(Error::NoAuthObject),
             (_, None) => Err(Error::NoManifestObject),
             (Some(auth_object), Some(manifest)) => {
-                let auth_object = Authentication::new(auth_object, manifest)?;
-                auth_object.authenticate(authenticate)?;
+                authenticate(Authentication::new(auth_object, manifest)?)?;
                 Ok(SuitManifest::<Authenticated> {
                     decoder: self.decoder,
+                    strict: self.strict,
                     phantom: PhantomData,
                 })
             }
@@ -293,34 +519,63 @@ impl<'a> SuitManifest<'a, New> {
 impl<'a> SuitManifest<'a, Authenticated> {}
 
 impl<'a, S: AuthState> Envelope<'a, S> {
-    fn get_object(&self, search_key: SuitEnvelope) -> Result<Option<&'a ByteSlice>, Error> {
-        let mut decoder = self.decoder.clone();
-        decoder
-            .map_iter::<i16, &ByteSlice>()?
-            .find_map(|item| match item {
-                Ok((key, item)) if key == search_key.into() => Some(Ok(item)),
-                Err(e) => Some(Err(e.into())),
-                _ => None,
-            })
-            .transpose()
+    /// Decoder positioned at the value of an envelope element, `None` if the element is absent.
+    ///
+    /// Elements outside of the index are looked up by walking the envelope map.
+    fn element(&self, search_key: impl Into<i16>) -> Result<Option<Decoder<'a>>, Error> {
+        let key = search_key.into();
+        let indexed = INDEXED_ELEMENTS
+            .iter()
+            .any(|element| i16::from(*element) == key);
+        let position = if indexed {
+            MapIndex::get_or_build(&self.index, &self.decoder, &INDEXED_ELEMENTS)?.get(key)
+        } else {
+            self.find_key(key)?
+        };
+        Ok(position.map(|position| {
+            let mut decoder = self.decoder.clone();
+            decoder.set_position(position);
+            decoder
+        }))
     }
 
-    fn get_object_wrapped(&self, search_key: SuitEnvelope) -> Result<Option<&'a ByteSlice>, Error> {
+    /// Position of the value of the first element with the key in the envelope map.
+    fn find_key(&self, key: i16) -> Result<Option<usize>, Error> {
         let mut decoder = self.decoder.clone();
         let position = decoder.position();
-        let len = decoder.map()?.ok_or(Error::UnexpectedCbor { position })?;
+        let len = decoder
+            .map()?
+            .ok_or(Error::UnexpectedIndefiniteLength { position })?;
         for _ in 0..len {
-            let key = decoder.i16()?;
-            if key == search_key.into() {
-                let buffer = decoder.sub_cbor()?;
-                return Ok(Some(buffer.into()));
-            } else {
-                decoder.skip()?;
+            if decode_key(&mut decoder)? == Some(key) {
+                return Ok(Some(decoder.position()));
             }
+            decoder.skip()?;
         }
         Ok(None)
     }
 
+    fn get_object(&self, search_key: impl Into<i16>) -> Result<Option<&'a ByteSlice>, Error> {
+        self.element(search_key)?
+            .map(|mut decoder| Ok(decoder.bytes()?.into()))
+            .transpose()
+    }
+
+    fn check_structure(&self) -> Result<(), Error> {
+        check_unique_keys(&mut self.decoder.clone())?;
+        self.get_object_wrapped(SuitEnvelope::Authentication)?
+            .ok_or(Error::NoAuthObject)?;
+        self.get_object_wrapped(SuitEnvelope::Manifest)?
+            .ok_or(Error::NoManifestObject)?;
+        Ok(())
+    }
+
+    fn get_object_wrapped(&self, search_key: SuitEnvelope) -> Result<Option<&'a ByteSlice>, Error> {
+        self.element(search_key)?
+            .map(|mut decoder| Ok(decoder.sub_cbor()?.into()))
+            .transpose()
+    }
+
     /// Retrieve the raw authentication object.
     ///
     /// Returns a reference to a byte slice containing the CBOR-encoded authentication object.
@@ -329,6 +584,55 @@ impl<'a, S: AuthState> Envelope<'a, S> {
         auth_object.ok_or(Error::NoAuthObject)
     }
 
+    /// Check that the authentication object is well-formed without verifying it.
+    ///
+    /// The authentication object must be an array of a bstr wrapped SUIT digest followed by bstr
+    /// wrapped COSE structures. Returns [`Error::NoAuthObject`] without authentication object and
+    /// [`Error::MalformedAuthBlock`] for a malformed element, keeping these apart from signature
+    /// verification failures.
+    pub fn check_auth_object(&self) -> Result<(), Error> {
+        Authentication::check_structure(self.auth_object()?)
+    }
+
+    /// Retrieve the digest of the manifest from the authentication object.
+    ///
+    /// The digest is decoded without comparing it against the manifest, the authentication
+    /// compares it before verifying any authentication block.
+    pub fn manifest_digest(&self) -> Result<SuitDigest<'a>, Error> {
+        Authentication::decode(self.auth_object()?)?.manifest_digest()
+    }
+
+    /// Iterate over the COSE structures of the authentication blocks.
+    ///
+    /// The blocks are yielded without verifying them or the manifest digest. Iteration ends after
+    /// the first error.
+    pub fn auth_blocks(&self) -> impl Iterator<Item = Result<&'a [u8], Error>> + 'a {
+        let (blocks, err) = match self.auth_object().and_then(Authentication::decode) {
+            Ok(auth) => (Some(auth.blocks()), None),
+            Err(e) => (None, Some(Err(e))),
+        };
+        err.into_iter().chain(blocks.into_iter().flatten())
+    }
+
+    /// Retrieve a payload integrated in the envelope.
+    ///
+    /// Integrated payloads are carried as byte string under a text key of the envelope map and
+    /// referenced by a fragment-only URI, the key, such as `#firmware`. The fetch directive
+    /// installs them from the envelope instead of calling
+    /// [`OperatingHooks::component_fetch`]. Returns `None` when the envelope has no element with
+    /// the key or the URI is not a fragment.
+    pub fn integrated_payload(&self, uri: &str) -> Result<Option<&'a ByteSlice>, Error> {
+        Ok(self.integrated_payloads().get(uri)?.map(Into::into))
+    }
+
+    /// The integrated payloads, for the command sequence executor.
+    pub(crate) fn integrated_payloads(&self) -> IntegratedPayloads<'a> {
+        IntegratedPayloads {
+            envelope: self.decoder.input(),
+            position: self.decoder.position(),
+        }
+    }
+
     /// Retrieve the manifest object as CBOR.
     ///
     /// Returns a reference to a byte slice containing the CBOR-encoded manifest.
@@ -340,9 +644,17 @@ impl<'a, S: AuthState> Envelope<'a, S> {
     }
 
     /// Retrieve the inner manifest.
+    ///
+    /// Returns [`Error::DuplicateKey`] when a key occurs more than once in the manifest map.
     pub fn manifest(&self) -> Result<Manifest<'a, S>, Error> {
         let manifest_bytes = self.manifest_bytes()?;
-        Ok(Manifest::<S>::from_bytes(manifest_bytes))
+        check_unique_keys(&mut Decoder::new(manifest_bytes))?;
+        let envelope = Envelope {
+            decoder: self.decoder.clone(),
+            index: self.index.clone(),
+            phantom: PhantomData,
+        };
+        Ok(Manifest::<S>::from_bytes(manifest_bytes).with_envelope(envelope))
     }
 }
 
@@ -376,6 +688,21 @@ mod tests {
         key
     }
 
+    #[test]
+    #[cfg(feature = "std")]
+    fn diagnostic_notation() {
+        use std::string::ToString;
+
+        // 107({2: h'00', 3: h'A10101'})
+        let input = [
+            0xD8, 0x6B, 0xA2, 0x02, 0x41, 0x00, 0x03, 0x43, 0xA1, 0x01, 0x01,
+        ];
+        assert_eq!(
+            diagnostic(&input).to_string(),
+            "107({2: h'00', 3: h'a1 01 01'})"
+        );
+    }
+
     #[test]
     fn test_verify() {
         const PUB_KEY: &str = "
@@ -415,12 +742,325 @@ bz/m4rVlnIXbwK07HypLbAmBMcCjbazR14vTgdzfsJwFLbM5kdtzOLSolg==
             .unwrap();
     }
 
+    #[test]
+    fn manifest_digest() {
+        let input = hex::decode(
+            "d86ba2025873825824822f58206658ea560262696dd1f13b782239a064da\
+             7c6c5cbaf52fded428a6fc83c7e5af584ad28443a10126a0f65840408d08\
+             16f9b510749bf6a51b066951e08a4438f849eb092a1ac768eed9de696c1b\
+             1dd35d82ef149e6a73a61976ad2cfe78444b8064293350a122f332cb49f0\
+             da035871a50101020003585fa202818141000458568614a40150fa6b4a53\
+             d5ad5fdfbe9de663e4d41ffe02501492af1425695e48bf429b2d51f2ab45\
+             035824822f582000112233445566778899aabbccddeeff0123456789abcd\
+             effedcba98765432100e1987d0010f020f074382030f0943821702",
+        )
+        .unwrap();
+        let envelope = SuitManifest::from_bytes(&input).envelope().unwrap();
+        let digest = envelope.manifest_digest().unwrap();
+        assert_eq!(digest.algorithm(), digest::SuitDigestAlgorithm::Sha256);
+        assert_eq!(
+            digest.digest(),
+            hex::decode("6658ea560262696dd1f13b782239a064da7c6c5cbaf52fded428a6fc83c7e5af")
+                .unwrap()
+        );
+
+        // No authentication object
+        let input = &[0xd8, 0x6b, 0xa1, 0x03, 0x43, 0xa1, 0x01, 0x01];
+        let envelope = SuitManifest::from_bytes(&input).envelope().unwrap();
+        assert_eq!(envelope.manifest_digest(), Err(Error::NoAuthObject));
+    }
+
+    #[test]
+    fn test_verify_sig_structure() {
+        const PUB_KEY: &str = "
+-----BEGIN PUBLIC KEY-----
+MFkwEwYHKoZIzj0CAQYIKoZIzj0DAQcDQgAEhJaBGq4LqqvSYVcYnuzaJr6qi/Eb
+bz/m4rVlnIXbwK07HypLbAmBMcCjbazR14vTgdzfsJwFLbM5kdtzOLSolg==
+-----END PUBLIC KEY-----
+";
+
+        let manifest = hex::decode(
+            "d86ba2025873825824822f58206658ea560262696dd1f13b782239a064da\
+             7c6c5cbaf52fded428a6fc83c7e5af584ad28443a10126a0f65840408d08\
+             16f9b510749bf6a51b066951e08a4438f849eb092a1ac768eed9de696c1b\
+             1dd35d82ef149e6a73a61976ad2cfe78444b8064293350a122f332cb49f0\
+             da035871a50101020003585fa202818141000458568614a40150fa6b4a53\
+             d5ad5fdfbe9de663e4d41ffe02501492af1425695e48bf429b2d51f2ab45\
+             035824822f582000112233445566778899aabbccddeeff0123456789abcd\
+             effedcba98765432100e1987d0010f020f074382030f0943821702",
+        )
+        .unwrap();
+
+        // Verify the reconstructed signed bytes outside of the COSE library
+        let key = openssl::pkey::PKey::public_key_from_pem(PUB_KEY.as_bytes()).unwrap();
+        let manifest = SuitManifest::from_bytes(&manifest);
+        manifest
+            .authenticate(|cose, payload| {
+                let signed = crate::cose::sig_structure::<128>(cose, payload)?;
+                let mut decoder = Decoder::new(cose);
+                decoder.tag()?;
+                decoder.array()?;
+                decoder.skip()?;
+                decoder.skip()?;
+                decoder.skip()?;
+                let signature = decoder.bytes()?;
+                let r = openssl::bn::BigNum::from_slice(&signature[..32]).unwrap();
+                let s = openssl::bn::BigNum::from_slice(&signature[32..]).unwrap();
+                let signature = openssl::ecdsa::EcdsaSig::from_private_components(r, s)
+                    .unwrap()
+                    .to_der()
+                    .unwrap();
+                let mut verifier =
+                    openssl::sign::Verifier::new(openssl::hash::MessageDigest::sha256(), &key)
+                        .unwrap();
+                Ok(verifier.verify_oneshot(&signature, &signed).unwrap())
+            })
+            .unwrap();
+    }
+
     #[test]
     fn test_hang_on_eof() {
         let input = &[0xd8, 0x6b, 0xbf];
         let manifest = SuitManifest::from_bytes(&input);
         let envelope = manifest.envelope().unwrap();
         let auth_err = envelope.auth_object().unwrap_err();
-        assert_eq!(auth_err, Error::EndOfInput);
+        assert_eq!(auth_err, Error::UnexpectedIndefiniteLength { position: 2 });
+    }
+
+    #[test]
+    fn decode() {
+        // 107({2: h'', 3: h''})
+        let input = &[0xd8, 0x6b, 0xa2, 0x02, 0x40, 0x03, 0x40];
+        assert_eq!(SuitManifest::from_bytes(&input).decode(), Ok(()));
+
+        // Indefinite length map
+        let input = &[0xd8, 0x6b, 0xbf, 0x02, 0x40, 0x03, 0x40, 0xff];
+        assert_eq!(
+            SuitManifest::from_bytes(&input).decode(),
+            Err(Error::UnexpectedIndefiniteLength { position: 2 })
+        );
+
+        // Duplicate manifest key
+        let input = &[0xd8, 0x6b, 0xa3, 0x02, 0x40, 0x03, 0x40, 0x03, 0x40];
+        assert_eq!(
+            SuitManifest::from_bytes(&input).decode(),
+            Err(Error::DuplicateKey { position: 7 })
+        );
+
+        // Missing manifest
+        let input = &[0xd8, 0x6b, 0xa1, 0x02, 0x40];
+        assert_eq!(
+            SuitManifest::from_bytes(&input).decode(),
+            Err(Error::NoManifestObject)
+        );
+    }
+
+    #[test]
+    fn untagged_envelope() {
+        // 107({2: h'', 3: h'A1 01 01'})
+        let input = &[0xd8, 0x6b, 0xa2, 0x02, 0x40, 0x03, 0x43, 0xa1, 0x01, 0x01];
+        let envelope = SuitManifest::from_bytes(&input).envelope().unwrap();
+        assert_eq!(envelope.manifest().unwrap().version(), Ok(1));
+
+        // {2: h'', 3: h'A1 01 01'}
+        let input = &[0xa2, 0x02, 0x40, 0x03, 0x43, 0xa1, 0x01, 0x01];
+        let envelope = SuitManifest::from_bytes(&input).envelope().unwrap();
+        assert_eq!(envelope.manifest().unwrap().version(), Ok(1));
+
+        // Other tag
+        let input = &[0xd8, 0x6c, 0xa2, 0x02, 0x40, 0x03, 0x43, 0xa1, 0x01, 0x01];
+        assert!(matches!(
+            SuitManifest::from_bytes(&input).envelope(),
+            Err(Error::UnexpectedCbor { position: 0 })
+        ));
+    }
+
+    #[test]
+    fn envelope_keys() {
+        // 107({2: h'', 65536: h'', 3: h'A1 01 01'})
+        let input = &[
+            0xd8, 0x6b, 0xa3, 0x02, 0x40, 0x1a, 0x00, 0x01, 0x00, 0x00, 0x40, 0x03, 0x43, 0xa1,
+            0x01, 0x01,
+        ];
+        let envelope = SuitManifest::from_bytes(&input).envelope().unwrap();
+        // The malformed key fails every lookup, also of the elements before it
+        assert_eq!(
+            envelope.auth_object(),
+            Err(Error::UnexpectedCbor { position: 5 })
+        );
+        assert_eq!(
+            envelope.manifest_bytes(),
+            Err(Error::UnexpectedCbor { position: 5 })
+        );
+
+        // 107({"x": 1, 2: h'', 3: h'A1 01 01'})
+        let input = &[
+            0xd8, 0x6b, 0xa3, 0x61, 0x78, 0x01, 0x02, 0x40, 0x03, 0x43, 0xa1, 0x01, 0x01,
+        ];
+        let envelope = SuitManifest::from_bytes(&input).envelope().unwrap();
+        assert!(envelope.auth_object().is_ok());
+        assert_eq!(envelope.manifest().unwrap().version(), Ok(1));
+    }
+
+    #[test]
+    fn envelope_index() {
+        // 107({2: h'', 3: h'A1 01 01'})
+        let input = &[0xd8, 0x6b, 0xa2, 0x02, 0x40, 0x03, 0x43, 0xa1, 0x01, 0x01];
+        let envelope = SuitManifest::from_bytes(&input).envelope().unwrap();
+        assert!(envelope.index.get().is_none());
+        assert!(envelope.auth_object().is_ok());
+        assert!(envelope.index.get().is_some());
+        assert_eq!(envelope.manifest().unwrap().version(), Ok(1));
+        assert_eq!(envelope.get_object(SuitEnvelope::Text), Ok(None));
+
+        // 107({_ 2: h'', 3: h'A1 01 01'}), deterministic CBOR forbids indefinite lengths
+        let input = &[
+            0xd8, 0x6b, 0xbf, 0x02, 0x40, 0x03, 0x43, 0xa1, 0x01, 0x01, 0xff,
+        ];
+        let envelope = SuitManifest::from_bytes(&input).envelope().unwrap();
+        assert_eq!(
+            envelope.auth_object(),
+            Err(Error::UnexpectedIndefiniteLength { position: 2 })
+        );
+        assert_eq!(
+            envelope.get_object(SuitEnvelope::Text),
+            Err(Error::UnexpectedIndefiniteLength { position: 2 })
+        );
+        assert_eq!(
+            envelope.get_object_wrapped(SuitEnvelope::Manifest),
+            Err(Error::UnexpectedIndefiniteLength { position: 2 })
+        );
+    }
+
+    #[test]
+    fn integrated_payload() {
+        // 107({2: h'', 3: h'A1 01 01', "#a": h'0102'})
+        let input = &[
+            0xd8, 0x6b, 0xa3, 0x02, 0x40, 0x03, 0x43, 0xa1, 0x01, 0x01, 0x62, 0x23, 0x61, 0x42,
+            0x01, 0x02,
+        ];
+        let envelope = SuitManifest::from_bytes(&input).envelope().unwrap();
+        let payload: &[u8] = &[0x01, 0x02];
+        assert_eq!(envelope.integrated_payload("#a"), Ok(Some(payload.into())));
+        assert_eq!(envelope.integrated_payload("#b"), Ok(None));
+        // Only fragment-only URIs reference integrated payloads
+        assert_eq!(envelope.integrated_payload("a"), Ok(None));
+        assert_eq!(envelope.manifest().unwrap().version(), Ok(1));
+
+        // Integrated payload that is not a byte string
+        let input = &[
+            0xd8, 0x6b, 0xa2, 0x03, 0x43, 0xa1, 0x01, 0x01, 0x62, 0x23, 0x61, 0x01,
+        ];
+        let envelope = SuitManifest::from_bytes(&input).envelope().unwrap();
+        assert_eq!(
+            envelope.integrated_payload("#a"),
+            Err(Error::TypeMismatch { position: 11 })
+        );
+    }
+
+    #[test]
+    fn parse() {
+        // 107({2: h'', 3: h'A1 01 01'})
+        let input = &[0xd8, 0x6b, 0xa2, 0x02, 0x40, 0x03, 0x43, 0xa1, 0x01, 0x01];
+        let envelope = SuitManifest::parse(&input).unwrap();
+        assert_eq!(envelope.manifest().unwrap().version(), Ok(1));
+
+        // Not a SUIT envelope
+        let input = &[0x82, 0x02, 0x40];
+        assert!(matches!(
+            SuitManifest::parse(&input),
+            Err(Error::UnexpectedCbor { position: 0 })
+        ));
+
+        // Missing authentication object
+        let input = &[0xd8, 0x6b, 0xa1, 0x03, 0x40];
+        assert!(matches!(
+            SuitManifest::parse(&input),
+            Err(Error::NoAuthObject)
+        ));
+    }
+
+    #[test]
+    fn peek() {
+        // 107({2: h'', 3: h'A2 01 01 02 05'})
+        let input = &[
+            0xd8, 0x6b, 0xa2, 0x02, 0x40, 0x03, 0x45, 0xa2, 0x01, 0x01, 0x02, 0x05,
+        ];
+        let suit = SuitManifest::from_bytes(&input);
+        assert_eq!(suit.peek_version(), Ok(1));
+        assert_eq!(suit.peek_sequence_number(), Ok(5));
+
+        // 107({2: h'', 3: h'A1 01 01'})
+        let input = &[0xd8, 0x6b, 0xa2, 0x02, 0x40, 0x03, 0x43, 0xa1, 0x01, 0x01];
+        let suit = SuitManifest::from_bytes(&input);
+        assert_eq!(suit.peek_sequence_number(), Err(Error::NoSequenceNumber));
+    }
+
+    #[test]
+    fn strict() {
+        // 107({2: h'', 3: h'A1 01 01'})
+        let input = &[0xd8, 0x6b, 0xa2, 0x02, 0x40, 0x03, 0x43, 0xa1, 0x01, 0x01];
+        assert!(SuitManifest::from_bytes_strict(&input).envelope().is_ok());
+
+        // Envelope keys out of order
+        let input = &[0xd8, 0x6b, 0xa2, 0x03, 0x43, 0xa1, 0x01, 0x01, 0x02, 0x40];
+        assert!(SuitManifest::from_bytes(&input).envelope().is_ok());
+        assert!(matches!(
+            SuitManifest::from_bytes_strict(&input).envelope(),
+            Err(Error::NonCanonicalCbor { position: 8 })
+        ));
+
+        // Manifest version encoded in two bytes
+        let input = &[
+            0xd8, 0x6b, 0xa2, 0x02, 0x40, 0x03, 0x44, 0xa1, 0x01, 0x18, 0x01,
+        ];
+        assert!(matches!(
+            SuitManifest::from_bytes_strict(&input).envelope(),
+            Err(Error::NonCanonicalCbor { position: 2 })
+        ));
+
+        // Indefinite length envelope
+        let input = &[0xd8, 0x6b, 0xbf, 0x02, 0x40, 0xff];
+        assert!(matches!(
+            SuitManifest::from_bytes_strict(&input).envelope(),
+            Err(Error::NonCanonicalCbor { position: 2 })
+        ));
+    }
+
+    #[test]
+    fn duplicate_key() {
+        // 107({2: h'', 3: h'A0', 3: h'A1 01 01'}), second manifest rejected before authentication
+        let input = &[
+            0xd8, 0x6b, 0xa3, 0x02, 0x40, 0x03, 0x41, 0xa0, 0x03, 0x43, 0xa1, 0x01, 0x01,
+        ];
+        let res = SuitManifest::from_bytes(&input).authenticate(|_, _| Ok(true));
+        assert!(matches!(res, Err(Error::DuplicateKey { position: 8 })));
+
+        // 107({3: h'A2 01 01 01 01'}), duplicate manifest version
+        let input = &[0xd8, 0x6b, 0xa1, 0x03, 0x45, 0xa2, 0x01, 0x01, 0x01, 0x01];
+        let envelope = SuitManifest::from_bytes(&input).envelope().unwrap();
+        assert!(matches!(
+            envelope.manifest(),
+            Err(Error::DuplicateKey { position: 3 })
+        ));
+
+        // 107({3: h'A2 01 01 1801 01'}), duplicate manifest version with a non-minimal key
+        let input = &[
+            0xd8, 0x6b, 0xa1, 0x03, 0x46, 0xa2, 0x01, 0x01, 0x18, 0x01, 0x01,
+        ];
+        let envelope = SuitManifest::from_bytes(&input).envelope().unwrap();
+        assert!(matches!(
+            envelope.manifest(),
+            Err(Error::DuplicateKey { position: 3 })
+        ));
+
+        // 107({2: h'', 0x1803: h'A0', 3: h'A0'}), non-minimal duplicate manifest key
+        let input = &[
+            0xd8, 0x6b, 0xa3, 0x02, 0x40, 0x18, 0x03, 0x41, 0xa0, 0x03, 0x41, 0xa0,
+        ];
+        assert_eq!(
+            SuitManifest::from_bytes(&input).decode(),
+            Err(Error::DuplicateKey { position: 9 })
+        );
     }
 }
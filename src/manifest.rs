@@ -1,24 +1,49 @@
 //! Inner SUIT manifest.
+use core::cell::{OnceCell, RefCell};
 use core::marker::PhantomData;
 
+use digest::Update;
 use minicbor::bytes::ByteSlice;
-use minicbor::data::Token;
+use minicbor::data::{Token, Type};
 use minicbor::decode::Decoder;
 
-use crate::cbor::SubCbor;
-use crate::command::CommandSequence;
-use crate::component::{ComponentInfo, ComponentIter};
+use crate::asynchooks::{block_on, AsyncOperatingHooks, Blocking};
+use crate::cbor::{MapIndex, SubCbor};
+use crate::command::{CommandSequence, CommandSequenceIterator, MaxNesting, Nesting};
+use crate::component::{Component, ComponentInfo, ComponentIter, DependencyIter};
+use crate::consts::SuitCommand;
+use crate::digest::{SuitDigest, SuitDigestAlgorithm};
 use crate::error::Error;
 use crate::manifeststate::ManifestState;
-use crate::{AuthState, Authenticated, OperatingHooks};
+use crate::report::{RecordSink, SuitReport};
+use crate::text::TextSection;
+use crate::{AuthState, Authenticated, Envelope, OperatingHooks};
 
 /// Inner SUIT manifest.
 #[derive(Debug, Clone)]
 pub struct Manifest<'a, S: AuthState> {
     decoder: Decoder<'a>,
+    envelope: Option<Envelope<'a, S>>,
+    /// Positions of the manifest elements, built on the first lookup
+    index: OnceCell<MapIndex<{ INDEXED_ELEMENTS.len() }>>,
     phantom: PhantomData<S>,
 }
 
+/// Manifest elements with their position recorded in the index of the manifest map.
+const INDEXED_ELEMENTS: [crate::consts::Manifest; 11] = [
+    crate::consts::Manifest::EncodingVersion,
+    crate::consts::Manifest::SequenceNumber,
+    crate::consts::Manifest::CommonData,
+    crate::consts::Manifest::ReferenceUri,
+    crate::consts::Manifest::ManifestComponentId,
+    crate::consts::Manifest::ImageValidation,
+    crate::consts::Manifest::ImageLoading,
+    crate::consts::Manifest::ImageInvocation,
+    crate::consts::Manifest::PayloadFetch,
+    crate::consts::Manifest::PayloadInstallation,
+    crate::consts::Manifest::TextDescription,
+];
+
 fn try_into_u64(token: Token) -> Result<u64, Error> {
     match token {
         Token::U8(n) => Ok(n.into()),
@@ -33,47 +58,178 @@ impl<'a, S: AuthState> Manifest<'a, S> {
     pub(crate) fn from_bytes<STATE: AuthState>(bytes: &'a ByteSlice) -> Manifest<'a, STATE> {
         Manifest::<'a, STATE> {
             decoder: Decoder::new(bytes),
+            envelope: None,
+            index: OnceCell::new(),
             phantom: PhantomData,
         }
     }
 
+    /// Attach the envelope containing the manifest, used to retrieve severed elements.
+    pub(crate) fn with_envelope(mut self, envelope: Envelope<'a, S>) -> Self {
+        self.envelope = Some(envelope);
+        self
+    }
+
     /// Retrieve the SUIT manifest encoding version number in the manifest.
+    ///
+    /// Versions outside of [`crate::consts::SUIT_MIN_VERSION`] to
+    /// [`crate::consts::SUIT_MAX_VERSION`] are rejected.
     pub fn version(&self) -> Result<u8, Error> {
-        let mut decoder = self.decoder.clone();
-        let len = decoder.map()?.ok_or(Error::UnexpectedIndefiniteLength {
-            position: decoder.position(),
-        })?;
-        for _ in 0..len {
-            let key = decoder.i16()?;
-            if key == crate::consts::Manifest::EncodingVersion.into() {
-                let version = decoder.u8()?;
-                if version == crate::consts::SUIT_SUPPORTED_VERSION {
-                    return Ok(version);
-                } else {
-                    return Err(Error::UnsupportedManifestVersion);
-                }
-            } else {
-                decoder.skip()?;
-            }
-        }
-        Err(Error::NoManifestVersion)
+        let mut decoder = self
+            .element(crate::consts::Manifest::EncodingVersion)?
+            .ok_or(Error::NoManifestVersion)?;
+        let version = decoder.u64()?;
+        let supported = crate::consts::SUIT_MIN_VERSION..=crate::consts::SUIT_MAX_VERSION;
+        u8::try_from(version)
+            .ok()
+            .filter(|version| supported.contains(version))
+            .ok_or(Error::UnsupportedManifestVersion { version })
     }
 
     /// Retrieve the manifest sequence number in the manifest.
     pub fn sequence_number(&self) -> Result<u64, Error> {
+        let mut decoder = self
+            .element(crate::consts::Manifest::SequenceNumber)?
+            .ok_or(Error::NoSequenceNumber)?;
+        Ok(decoder.u64()?)
+    }
+
+    /// Retrieve the URI where the full manifest can be found.
+    ///
+    /// Returns `None` when the manifest has no reference URI.
+    pub fn reference_uri(&self) -> Result<Option<&'a str>, Error> {
+        self.element(crate::consts::Manifest::ReferenceUri)?
+            .map(|mut decoder| Ok(decoder.str()?))
+            .transpose()
+    }
+
+    /// Iterate over the top-level elements of the manifest.
+    ///
+    /// Yields the key and the encoded value of every element, including keys unknown to this
+    /// crate. Iteration ends after the first error.
+    pub fn sections(&self) -> impl Iterator<Item = Result<(i16, &'a ByteSlice), Error>> + 'a {
         let mut decoder = self.decoder.clone();
-        let len = decoder.map()?.ok_or(Error::UnexpectedIndefiniteLength {
-            position: decoder.position(),
-        })?;
-        for _ in 0..len {
-            let key = decoder.i16()?;
-            if key == crate::consts::Manifest::SequenceNumber.into() {
-                return Ok(decoder.u64()?);
-            } else {
-                decoder.skip()?;
+        let position = decoder.position();
+        let (mut remaining, err) = match decoder.map() {
+            Ok(Some(len)) => (len, None),
+            Ok(None) => (0, Some(Err(Error::UnexpectedIndefiniteLength { position }))),
+            Err(e) => (0, Some(Err(e.into()))),
+        };
+        err.into_iter().chain(core::iter::from_fn(move || {
+            if remaining == 0 {
+                return None;
             }
+            let section = decoder
+                .i16()
+                .and_then(|key| Ok((key, decoder.sub_cbor()?.into())))
+                .map_err(Error::from);
+            remaining = if section.is_ok() { remaining - 1 } else { 0 };
+            Some(section)
+        }))
+    }
+
+    /// Iterate over the commands in a command sequence without executing them.
+    ///
+    /// Yields every command of the sequence together with its encoded argument, nested command
+    /// sequences are not entered.
+    pub fn commands(
+        sequence: &'a ByteSlice,
+    ) -> impl Iterator<Item = Result<(SuitCommand, &'a ByteSlice), Error>> + 'a {
+        let (commands, err) = match CommandSequenceIterator::new(sequence, 0) {
+            Ok(commands) => (Some(commands), None),
+            Err(e) => (None, Some(Err(e))),
+        };
+        err.into_iter().chain(
+            commands
+                .into_iter()
+                .flatten()
+                .map(|command| command.map(|c| (c.command, c.argument_bytes.into()))),
+        )
+    }
+
+    /// Retrieve the component identifier of the component the manifest itself represents.
+    ///
+    /// Returns `None` when the manifest has no manifest component identifier.
+    pub fn manifest_component_id(&self) -> Result<Option<Component<'a>>, Error> {
+        self.element(crate::consts::Manifest::ManifestComponentId)?
+            .map(|mut decoder| Ok(decoder.decode()?))
+            .transpose()
+    }
+
+    /// Summarize the manifest for a human-readable overview.
+    ///
+    /// Collects the version, the sequence number, the number of components and the presence of
+    /// the common, payload installation, payload fetch and text sections. Severed sections count
+    /// as present, they are not retrieved from the envelope.
+    pub fn summary(&self) -> Result<ManifestSummary, Error> {
+        let common = self.inline_section(crate::consts::Manifest::CommonData)?;
+        let components = match common {
+            Some((cbor, offset)) => CommonSection::new(cbor, offset)?.component_count()?,
+            None => 0,
+        };
+        Ok(ManifestSummary {
+            version: self.version()?,
+            sequence_number: self.sequence_number()?,
+            components,
+            common: common.is_some(),
+            install: self
+                .element(crate::consts::Manifest::PayloadInstallation)?
+                .is_some(),
+            fetch: self
+                .element(crate::consts::Manifest::PayloadFetch)?
+                .is_some(),
+            text: self
+                .element(crate::consts::Manifest::TextDescription)?
+                .is_some(),
+        })
+    }
+
+    /// Walk all command sequences for commands this crate does not implement.
+    ///
+    /// Collects the copy and swap directives, custom commands and unset commands of the shared
+    /// sequence and of all command sections, including nested sequences, without executing
+    /// anything. Severed sections are not retrieved from the envelope and not scanned. Returns
+    /// [`Error::CapacityError`] if more than `N` different commands are found.
+    pub fn scan_unsupported<const N: usize>(&self) -> Result<heapless::Vec<SuitCommand, N>, Error> {
+        let mut found = heapless::Vec::new();
+        if let Some((cbor, offset)) = self.inline_section(crate::consts::Manifest::CommonData)? {
+            CommonSection::new(cbor, offset)?
+                .shared_sequence()
+                .collect_unsupported(&mut found)?;
         }
-        Err(Error::NoSequenceNumber)
+        for section in crate::consts::SUIT_COMMAND_SECTIONS {
+            if let Some((cbor, offset)) = self.inline_section(section)? {
+                CommandSequence::new(cbor, offset).collect_unsupported(&mut found)?;
+            }
+        }
+        Ok(found)
+    }
+
+    /// Value of a section contained in the manifest itself, `None` if the section is absent or
+    /// severed.
+    fn inline_section(
+        &self,
+        section: crate::consts::Manifest,
+    ) -> Result<Option<(&'a ByteSlice, usize)>, Error> {
+        let Some(mut decoder) = self.element(section)? else {
+            return Ok(None);
+        };
+        if decoder.datatype()? == Type::Array {
+            return Ok(None);
+        }
+        let value = decoder.bytes()?;
+        let offset = decoder.position() - value.len();
+        Ok(Some((value.into(), offset)))
+    }
+
+    /// Decoder positioned at the value of a manifest element, `None` if the element is absent.
+    fn element(&self, element: crate::consts::Manifest) -> Result<Option<Decoder<'a>>, Error> {
+        let index = MapIndex::get_or_build(&self.index, &self.decoder, &INDEXED_ELEMENTS)?;
+        Ok(index.get(element.into()).map(|position| {
+            let mut decoder = self.decoder.clone();
+            decoder.set_position(position);
+            decoder
+        }))
     }
 }
 
@@ -82,29 +238,118 @@ impl<'a> Manifest<'a, Authenticated> {
         &self,
         section: crate::consts::Manifest,
     ) -> Result<Option<(&'a ByteSlice, usize)>, Error> {
-        let mut decoder = self.decoder.clone();
-        let len = decoder.map()?.ok_or(Error::UnexpectedCbor {
-            position: decoder.position(),
-        })?;
-        for _ in 0..len {
-            let key = decoder.i16()?;
-            let offset = decoder.position();
-            if key == section.into() {
-                let value = decoder.bytes()?;
-                return Ok(Some((value.into(), offset)));
-            } else {
-                decoder.skip()?;
-            }
+        let Some(mut decoder) = self.element(section)? else {
+            return Ok(None);
+        };
+        let offset = decoder.position();
+        if decoder.datatype()? == Type::Array {
+            // Severed element, the manifest only contains the digest
+            let digest = SuitDigest::from_cbor(&mut decoder)?;
+            return self
+                .find_severed_section(section, &digest, offset)
+                .map(Some);
+        }
+        let value = decoder.bytes()?;
+        // Positions inside the section are relative to the wrapped content
+        let offset = decoder.position() - value.len();
+        Ok(Some((value.into(), offset)))
+    }
+
+    /// Retrieve a severed element from the envelope and verify it against the digest from the
+    /// manifest.
+    ///
+    /// A digest without the element in the envelope fails with [`Error::MissingSeveredElement`],
+    /// a stripped section must not pass as an absent one.
+    fn find_severed_section(
+        &self,
+        section: crate::consts::Manifest,
+        digest: &SuitDigest,
+        position: usize,
+    ) -> Result<(&'a ByteSlice, usize), Error> {
+        let element = section
+            .severed_element()
+            .ok_or(Error::UnexpectedCbor { position })?;
+        let missing = Error::MissingSeveredElement {
+            section: section.into(),
+        };
+        let envelope = self.envelope.as_ref().ok_or(missing)?;
+        let wrapped = envelope.get_object_wrapped(element)?.ok_or(missing)?;
+        // The digest covers the bstr wrapped element
+        let mut hasher = digest.hasher()?;
+        hasher.update(wrapped);
+        if !digest
+            .match_hasher(hasher)
+            .map_err(|e| e.add_offset(position))?
+        {
+            return Err(Error::ConditionMatchFail { position });
         }
-        Ok(None)
+        let value = Decoder::new(wrapped).bytes()?;
+        Ok((value.into(), 0))
     }
 
     fn find_command_sequence(
         &self,
         section: crate::consts::Manifest,
     ) -> Result<Option<CommandSequence<'a>>, Error> {
-        self.find_section(section)
-            .map(|o| o.map(|(cbor, offset)| CommandSequence::new(cbor, offset)))
+        let payloads = self.envelope.as_ref().map(Envelope::integrated_payloads);
+        self.find_section(section).map(|o| {
+            o.map(|(cbor, offset)| CommandSequence::new(cbor, offset).with_payloads(payloads))
+        })
+    }
+
+    /// Retrieve the payload fetch command sequence.
+    ///
+    /// Returns the CBOR-encoded command sequence, retrieved from the envelope when the sequence is
+    /// severed from the manifest. Returns `None` when the manifest has no payload fetch sequence.
+    pub fn fetch_sequence(&self) -> Result<Option<&'a ByteSlice>, Error> {
+        self.find_section(crate::consts::Manifest::PayloadFetch)
+            .map(|o| o.map(|(cbor, _)| cbor))
+    }
+
+    /// Retrieve the payload installation command sequence.
+    ///
+    /// Returns the CBOR-encoded command sequence, retrieved from the envelope when the sequence is
+    /// severed from the manifest. Returns `None` when the manifest has no payload installation
+    /// sequence.
+    pub fn install_sequence(&self) -> Result<Option<&'a ByteSlice>, Error> {
+        self.find_section(crate::consts::Manifest::PayloadInstallation)
+            .map(|o| o.map(|(cbor, _)| cbor))
+    }
+
+    /// Retrieve the image validation command sequence.
+    ///
+    /// Returns `None` when the manifest has no image validation sequence. The sequence is executed
+    /// by [`Self::execute_image_validation`].
+    pub fn validate_sequence(&self) -> Result<Option<&'a ByteSlice>, Error> {
+        self.find_section(crate::consts::Manifest::ImageValidation)
+            .map(|o| o.map(|(cbor, _)| cbor))
+    }
+
+    /// Retrieve the image loading command sequence.
+    ///
+    /// Returns `None` when the manifest has no image loading sequence. The sequence is executed by
+    /// [`Self::execute_image_loading`].
+    pub fn load_sequence(&self) -> Result<Option<&'a ByteSlice>, Error> {
+        self.find_section(crate::consts::Manifest::ImageLoading)
+            .map(|o| o.map(|(cbor, _)| cbor))
+    }
+
+    /// Retrieve the image invocation command sequence.
+    ///
+    /// Returns `None` when the manifest has no image invocation sequence. The sequence is executed
+    /// by [`Self::execute_invoke`].
+    pub fn invoke_sequence(&self) -> Result<Option<&'a ByteSlice>, Error> {
+        self.find_section(crate::consts::Manifest::ImageInvocation)
+            .map(|o| o.map(|(cbor, _)| cbor))
+    }
+
+    /// Retrieve the text section of the manifest.
+    ///
+    /// The text section is retrieved from the envelope when it is severed from the manifest.
+    /// Returns `None` when the manifest has no text section.
+    pub fn text(&self) -> Result<Option<TextSection<'a>>, Error> {
+        self.find_section(crate::consts::Manifest::TextDescription)
+            .map(|o| o.map(|(cbor, _)| TextSection::new(cbor)))
     }
 
     fn get_common(&self) -> Result<CommonSection<'a>, Error> {
@@ -113,6 +358,13 @@ impl<'a> Manifest<'a, Authenticated> {
             .and_then(|(cbor, offset)| CommonSection::new(cbor, offset))
     }
 
+    /// Iterate over the dependencies listed in the common section of the manifest.
+    ///
+    /// Returns an empty iterator when the manifest does not have any dependencies.
+    pub fn dependencies(&self) -> Result<DependencyIter<'a>, Error> {
+        self.get_common()?.dependencies()
+    }
+
     // Checks if the first command in a command sequence is a SetComponentIndex, if there is more
     // than one component in the manifest
     fn check_sequences(&self) -> Result<bool, Error> {
@@ -164,8 +416,32 @@ impl<'a> Manifest<'a, Authenticated> {
         &self,
         os_hooks: &impl OperatingHooks,
         section: crate::consts::Manifest,
+        report: Option<&RefCell<dyn RecordSink>>,
+    ) -> Result<(), Error> {
+        self.execute_section_components(os_hooks, section, None, report)
+    }
+
+    /// Execute a section for all components, or only for the component with the supplied index.
+    fn execute_section_components(
+        &self,
+        os_hooks: &impl OperatingHooks,
+        section: crate::consts::Manifest,
+        index: Option<u32>,
+        report: Option<&RefCell<dyn RecordSink>>,
+    ) -> Result<(), Error> {
+        block_on(self.run_section::<MaxNesting>(&Blocking(os_hooks), section, index, report))
+    }
+
+    /// Execute a section for all components, or only for the component with the supplied index.
+    ///
+    /// The nesting depth left for nested sequences and dependencies is encoded in `N`.
+    pub(crate) async fn run_section<N: Nesting>(
+        &self,
+        os_hooks: &impl AsyncOperatingHooks,
+        section: crate::consts::Manifest,
+        index: Option<u32>,
+        report: Option<&RefCell<dyn RecordSink>>,
     ) -> Result<(), Error> {
-        let start_state = ManifestState::default();
         let command_section =
             self.find_command_sequence(section)?
                 .ok_or(Error::NoCommandSection {
@@ -183,15 +459,38 @@ impl<'a> Manifest<'a, Authenticated> {
             let idx = idx.try_into().map_err(|_| Error::UnexpectedCbor {
                 position: self.decoder.position(),
             })?;
-            let component_info = ComponentInfo::new(component, idx);
+            if index.is_some_and(|index| index != idx) {
+                continue;
+            }
+            let component_info =
+                ComponentInfo::new(component, idx).with_dependencies(common.dependencies()?);
 
-            let state =
-                common
-                    .shared_sequence()
-                    .execute(start_state.clone(), &component_info, os_hooks)?;
-            command_section.execute(state, &component_info, os_hooks)?;
+            let state = common
+                .component_state::<N>(&component_info, os_hooks, report)
+                .await?;
+            command_section
+                .execute::<N>(state, &component_info, os_hooks, report)
+                .await?;
+            if index.is_some() {
+                return Ok(());
+            }
         }
-        Ok(())
+        match index {
+            Some(index) => Err(Error::NoComponent { index }),
+            None => Ok(()),
+        }
+    }
+
+    fn execute_section_with_report<const N: usize>(
+        &self,
+        os_hooks: &impl OperatingHooks,
+        section: crate::consts::Manifest,
+        report: &mut SuitReport<N>,
+    ) -> Result<(), Error> {
+        let cell = RefCell::new(core::mem::take(report));
+        let res = self.execute_section_with_common(os_hooks, section, Some(&cell));
+        *report = cell.into_inner();
+        res
     }
 
     /// Execute the command sequence in the payload fetch section.
@@ -199,7 +498,7 @@ impl<'a> Manifest<'a, Authenticated> {
     /// The command sequence in the common section is executed before the command sequence in the
     /// payload fetch is executed.
     pub fn execute_payload_fetch(&self, os_hooks: &impl OperatingHooks) -> Result<(), Error> {
-        self.execute_section_with_common(os_hooks, crate::consts::Manifest::PayloadFetch)
+        self.execute_section_with_common(os_hooks, crate::consts::Manifest::PayloadFetch, None)
     }
 
     /// Execute the command sequence in the payload installation section.
@@ -210,7 +509,28 @@ impl<'a> Manifest<'a, Authenticated> {
         &self,
         os_hooks: &impl OperatingHooks,
     ) -> Result<(), Error> {
-        self.execute_section_with_common(os_hooks, crate::consts::Manifest::PayloadInstallation)
+        self.execute_section_with_common(
+            os_hooks,
+            crate::consts::Manifest::PayloadInstallation,
+            None,
+        )
+    }
+
+    /// Execute the command sequence in the payload installation section, recording the command
+    /// results in the report.
+    ///
+    /// Only commands with a reporting policy requesting a record are added to the report. The
+    /// report is populated up to the point of failure when the execution fails.
+    pub fn execute_payload_installation_with_report<const N: usize>(
+        &self,
+        os_hooks: &impl OperatingHooks,
+        report: &mut SuitReport<N>,
+    ) -> Result<(), Error> {
+        self.execute_section_with_report(
+            os_hooks,
+            crate::consts::Manifest::PayloadInstallation,
+            report,
+        )
     }
 
     /// Execute the command sequence in the image validation section.
@@ -218,7 +538,38 @@ impl<'a> Manifest<'a, Authenticated> {
     /// The command sequence in the common section is executed before the command sequence in the
     /// image validation is executed.
     pub fn execute_image_validation(&self, os_hooks: &impl OperatingHooks) -> Result<(), Error> {
-        self.execute_section_with_common(os_hooks, crate::consts::Manifest::ImageValidation)
+        self.execute_section_with_common(os_hooks, crate::consts::Manifest::ImageValidation, None)
+    }
+
+    /// Execute the command sequence in the image validation section for a single component.
+    ///
+    /// Only the component with the supplied index is processed, starting from the parameters set
+    /// by the command sequence in the common section. Returns [`Error::NoComponent`] if the
+    /// manifest lists no component with this index.
+    pub fn execute_image_validation_component(
+        &self,
+        index: u32,
+        os_hooks: &impl OperatingHooks,
+    ) -> Result<(), Error> {
+        self.execute_section_components(
+            os_hooks,
+            crate::consts::Manifest::ImageValidation,
+            Some(index),
+            None,
+        )
+    }
+
+    /// Execute the command sequence in the image validation section, recording the command
+    /// results in the report.
+    ///
+    /// Only commands with a reporting policy requesting a record are added to the report. The
+    /// report is populated up to the point of failure when the execution fails.
+    pub fn execute_image_validation_with_report<const N: usize>(
+        &self,
+        os_hooks: &impl OperatingHooks,
+        report: &mut SuitReport<N>,
+    ) -> Result<(), Error> {
+        self.execute_section_with_report(os_hooks, crate::consts::Manifest::ImageValidation, report)
     }
 
     /// Execute the command sequence in the image loading section.
@@ -226,7 +577,7 @@ impl<'a> Manifest<'a, Authenticated> {
     /// The command sequence in the common section is executed before the command sequence in the
     /// image loading is executed.
     pub fn execute_image_loading(&self, os_hooks: &impl OperatingHooks) -> Result<(), Error> {
-        self.execute_section_with_common(os_hooks, crate::consts::Manifest::ImageLoading)
+        self.execute_section_with_common(os_hooks, crate::consts::Manifest::ImageLoading, None)
     }
 
     /// Execute the command sequence in the image loading section.
@@ -234,74 +585,414 @@ impl<'a> Manifest<'a, Authenticated> {
     /// The command sequence in the common section is executed before the command sequence in the
     /// invoke is executed.
     pub fn execute_invoke(&self, os_hooks: &impl OperatingHooks) -> Result<(), Error> {
-        self.execute_section_with_common(os_hooks, crate::consts::Manifest::ImageInvocation)
+        self.execute_section_with_common(os_hooks, crate::consts::Manifest::ImageInvocation, None)
     }
 
-    /// Execute all command sequences in the manifest.
-    pub fn execute_full(&self, os_hooks: &impl OperatingHooks) -> Result<(), Error> {
+    #[cfg(feature = "async")]
+    async fn execute_section_with_report_async<const N: usize>(
+        &self,
+        os_hooks: &impl AsyncOperatingHooks,
+        section: crate::consts::Manifest,
+        report: &mut SuitReport<N>,
+    ) -> Result<(), Error> {
+        let cell = RefCell::new(core::mem::take(report));
+        let res = self
+            .run_section::<MaxNesting>(os_hooks, section, None, Some(&cell))
+            .await;
+        *report = cell.into_inner();
+        res
+    }
+
+    /// Execute the command sequence in the payload fetch section with the asynchronous operating
+    /// hooks.
+    ///
+    /// See [`Self::execute_payload_fetch`].
+    #[cfg(feature = "async")]
+    pub async fn execute_payload_fetch_async(
+        &self,
+        os_hooks: &impl AsyncOperatingHooks,
+    ) -> Result<(), Error> {
+        self.run_section::<MaxNesting>(os_hooks, crate::consts::Manifest::PayloadFetch, None, None)
+            .await
+    }
+
+    /// Execute the command sequence in the payload installation section with the asynchronous
+    /// operating hooks.
+    ///
+    /// See [`Self::execute_payload_installation`].
+    #[cfg(feature = "async")]
+    pub async fn execute_payload_installation_async(
+        &self,
+        os_hooks: &impl AsyncOperatingHooks,
+    ) -> Result<(), Error> {
+        self.run_section::<MaxNesting>(
+            os_hooks,
+            crate::consts::Manifest::PayloadInstallation,
+            None,
+            None,
+        )
+        .await
+    }
+
+    /// Execute the command sequence in the payload installation section with the asynchronous
+    /// operating hooks, recording the command results in the report.
+    ///
+    /// See [`Self::execute_payload_installation_with_report`].
+    #[cfg(feature = "async")]
+    pub async fn execute_payload_installation_with_report_async<const N: usize>(
+        &self,
+        os_hooks: &impl AsyncOperatingHooks,
+        report: &mut SuitReport<N>,
+    ) -> Result<(), Error> {
+        self.execute_section_with_report_async(
+            os_hooks,
+            crate::consts::Manifest::PayloadInstallation,
+            report,
+        )
+        .await
+    }
+
+    /// Execute the command sequence in the image validation section with the asynchronous
+    /// operating hooks.
+    ///
+    /// See [`Self::execute_image_validation`].
+    #[cfg(feature = "async")]
+    pub async fn execute_image_validation_async(
+        &self,
+        os_hooks: &impl AsyncOperatingHooks,
+    ) -> Result<(), Error> {
+        self.run_section::<MaxNesting>(
+            os_hooks,
+            crate::consts::Manifest::ImageValidation,
+            None,
+            None,
+        )
+        .await
+    }
+
+    /// Execute the command sequence in the image validation section with the asynchronous
+    /// operating hooks, recording the command results in the report.
+    ///
+    /// See [`Self::execute_image_validation_with_report`].
+    #[cfg(feature = "async")]
+    pub async fn execute_image_validation_with_report_async<const N: usize>(
+        &self,
+        os_hooks: &impl AsyncOperatingHooks,
+        report: &mut SuitReport<N>,
+    ) -> Result<(), Error> {
+        self.execute_section_with_report_async(
+            os_hooks,
+            crate::consts::Manifest::ImageValidation,
+            report,
+        )
+        .await
+    }
+
+    /// Execute the command sequence in the image loading section with the asynchronous operating
+    /// hooks.
+    ///
+    /// See [`Self::execute_image_loading`].
+    #[cfg(feature = "async")]
+    pub async fn execute_image_loading_async(
+        &self,
+        os_hooks: &impl AsyncOperatingHooks,
+    ) -> Result<(), Error> {
+        self.run_section::<MaxNesting>(os_hooks, crate::consts::Manifest::ImageLoading, None, None)
+            .await
+    }
+
+    /// Execute the command sequence in the invoke section with the asynchronous operating hooks.
+    ///
+    /// See [`Self::execute_invoke`].
+    #[cfg(feature = "async")]
+    pub async fn execute_invoke_async(
+        &self,
+        os_hooks: &impl AsyncOperatingHooks,
+    ) -> Result<(), Error> {
+        self.run_section::<MaxNesting>(
+            os_hooks,
+            crate::consts::Manifest::ImageInvocation,
+            None,
+            None,
+        )
+        .await
+    }
+
+    /// Verify the image digests of all components without installing anything.
+    ///
+    /// Runs the shared sequence and the image validation sequence for every component, processing
+    /// only the parameter overrides, the component selection and the image match conditions. All
+    /// other conditions and directives are skipped, so the platform state is never modified.
+    /// Selected dependencies are authenticated and verify their image digests the same way, once
+    /// per sequence. Returns [`Error::ComponentConditionFailed`] with the index of the first mismatching
+    /// component.
+    pub fn verify_images(&self, os_hooks: &impl OperatingHooks) -> Result<(), Error> {
+        block_on(self.run_verify_images::<MaxNesting>(&Blocking(os_hooks)))
+    }
+
+    /// Verify the image digests of all components with the asynchronous operating hooks.
+    ///
+    /// See [`Self::verify_images`].
+    #[cfg(feature = "async")]
+    pub async fn verify_images_async(
+        &self,
+        os_hooks: &impl AsyncOperatingHooks,
+    ) -> Result<(), Error> {
+        self.run_verify_images::<MaxNesting>(os_hooks).await
+    }
+
+    /// Verify the image digests of all components.
+    ///
+    /// The nesting depth left for nested sequences and dependencies is encoded in `N`.
+    pub(crate) async fn run_verify_images<N: Nesting>(
+        &self,
+        os_hooks: &impl AsyncOperatingHooks,
+    ) -> Result<(), Error> {
+        let validation = self.find_command_sequence(crate::consts::Manifest::ImageValidation)?;
+        let common = self.get_common()?;
+        let mut component_decoder = Decoder::new(common.components);
+        for (idx, component) in ComponentIter::new(&mut component_decoder)
+            .map_err(|e| e.add_offset(common.component_offset))?
+            .enumerate()
+        {
+            let component = component.map_err(|e| e.add_offset(common.component_offset))?;
+            let index = idx.try_into().map_err(|_| Error::UnexpectedCbor {
+                position: self.decoder.position(),
+            })?;
+            let image_mismatch = |e| match e {
+                Error::ConditionMatchFail { position } | Error::TryEachFail { position } => {
+                    Error::ComponentConditionFailed {
+                        index,
+                        command: SuitCommand::ImageMatch.into(),
+                        position,
+                    }
+                }
+                e => e,
+            };
+            let component_info =
+                ComponentInfo::new(component, index).with_dependencies(common.dependencies()?);
+            let state = common
+                .shared_sequence()
+                .verify_images::<N>(ManifestState::default(), &component_info, os_hooks)
+                .await
+                .map_err(image_mismatch)?;
+            if let Some(validation) = &validation {
+                validation
+                    .verify_images::<N>(state, &component_info, os_hooks)
+                    .await
+                    .map_err(image_mismatch)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Check the sequence number of the manifest against the installed components.
+    ///
+    /// The sequence number must be strictly greater than the one stored for every component in
+    /// the manifest, see [`OperatingHooks::stored_sequence_number`]. Returns
+    /// [`Error::SequenceNumberTooLow`] otherwise.
+    pub fn check_sequence_number(&self, os_hooks: &impl OperatingHooks) -> Result<(), Error> {
+        let sequence_number = self.sequence_number()?;
+        let common = self.get_common()?;
+        let mut component_decoder = Decoder::new(common.components);
+        for component in ComponentIter::new(&mut component_decoder)
+            .map_err(|e| e.add_offset(common.component_offset))?
+        {
+            let component = component.map_err(|e| e.add_offset(common.component_offset))?;
+            let stored = os_hooks.stored_sequence_number(&component)?;
+            if sequence_number <= stored {
+                return Err(Error::SequenceNumberTooLow {
+                    sequence_number,
+                    stored,
+                });
+            }
+        }
+        Ok(())
+    }
+
+    /// Check the algorithms of all image digests in the manifest against a digest policy.
+    ///
+    /// Scans the image digest parameters of the shared sequence and of every command section,
+    /// including nested sequences, without executing any command. Returns
+    /// [`Error::DigestAlgorithmNotPermitted`] for the first digest with an algorithm outside of
+    /// `allowed`.
+    pub fn enforce_digest_policy(&self, allowed: &[SuitDigestAlgorithm]) -> Result<(), Error> {
+        self.get_common()?
+            .shared_sequence()
+            .check_digest_algorithms(allowed)?;
         for section in crate::consts::SUIT_COMMAND_SECTIONS {
-            let res = self.execute_section_with_common(os_hooks, section);
-            // Ignore NoCommandSequence errors
-            if res.is_err_and(|e| !matches!(e, Error::NoCommandSection { .. })) {
-                return res;
+            if let Some(sequence) = self.find_command_sequence(section)? {
+                sequence.check_digest_algorithms(allowed)?;
             }
         }
         Ok(())
     }
+
+    /// Execute all command sequences in the manifest.
+    ///
+    /// Returns the [`ProcessOutcome`] on success, with the manifest sequence number the caller
+    /// persists for rollback protection.
+    pub fn execute_full(&self, os_hooks: &impl OperatingHooks) -> Result<ProcessOutcome, Error> {
+        let mut outcome = ProcessOutcome {
+            sequence_number: self.sequence_number()?,
+            components: self.get_common()?.component_count()?,
+            sections: [false; crate::consts::SUIT_COMMAND_SECTIONS.len()],
+        };
+        for (section, executed) in crate::consts::SUIT_COMMAND_SECTIONS
+            .into_iter()
+            .zip(outcome.sections.iter_mut())
+        {
+            match self.execute_section_with_common(os_hooks, section, None) {
+                Ok(()) => *executed = true,
+                // Ignore NoCommandSequence errors
+                Err(Error::NoCommandSection { .. }) => {}
+                Err(e) => return Err(e),
+            }
+        }
+        Ok(outcome)
+    }
+
+    /// Execute all command sequences in the manifest, discarding the [`ProcessOutcome`].
+    pub fn execute_all(&self, os_hooks: &impl OperatingHooks) -> Result<(), Error> {
+        self.execute_full(os_hooks).map(|_| ())
+    }
+}
+
+/// Outcome of successfully executing all command sequences of a manifest.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct ProcessOutcome {
+    sequence_number: u64,
+    components: usize,
+    /// Executed sections, in the order of [`crate::consts::SUIT_COMMAND_SECTIONS`]
+    sections: [bool; crate::consts::SUIT_COMMAND_SECTIONS.len()],
+}
+
+impl ProcessOutcome {
+    /// The sequence number of the processed manifest.
+    pub fn sequence_number(&self) -> u64 {
+        self.sequence_number
+    }
+
+    /// The number of components the command sequences were executed for.
+    pub fn components(&self) -> usize {
+        self.components
+    }
+
+    /// Whether the manifest contains the command section and it was executed.
+    pub fn executed(&self, section: crate::consts::Manifest) -> bool {
+        self.sections().any(|s| i16::from(s) == i16::from(section))
+    }
+
+    /// Iterate over the executed command sections in the order of execution.
+    pub fn sections(&self) -> impl Iterator<Item = crate::consts::Manifest> + '_ {
+        crate::consts::SUIT_COMMAND_SECTIONS
+            .into_iter()
+            .zip(self.sections)
+            .filter_map(|(section, executed)| executed.then_some(section))
+    }
+}
+
+/// Overview of a manifest, see [`Manifest::summary`].
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct ManifestSummary {
+    /// The manifest encoding version.
+    pub version: u8,
+    /// The sequence number of the manifest.
+    pub sequence_number: u64,
+    /// The number of components in the common section.
+    pub components: usize,
+    /// Whether the manifest has a common section.
+    pub common: bool,
+    /// Whether the manifest has a payload installation section.
+    pub install: bool,
+    /// Whether the manifest has a payload fetch section.
+    pub fetch: bool,
+    /// Whether the manifest has a text section.
+    pub text: bool,
 }
 
 struct CommonSection<'a> {
     components: &'a ByteSlice,
     component_offset: usize,
     shared_sequence: CommandSequence<'a>,
+    dependencies: Option<&'a ByteSlice>,
+    dependency_offset: usize,
 }
 
 impl<'a> CommonSection<'a> {
     fn new(cbor: &'a ByteSlice, offset: usize) -> Result<Self, Error> {
-        let (components, component_offset, shared_sequence) = Self::decode_common(cbor, offset)?;
-        // todo: fix offset
-        Ok(Self {
-            components,
-            component_offset,
-            shared_sequence,
-        })
+        Self::decode_common(cbor, offset)
     }
 
-    fn decode_common(
-        cbor: &'a ByteSlice,
-        offset: usize,
-    ) -> Result<(&'a ByteSlice, usize, CommandSequence<'a>), Error> {
-        // Only contains the component identifiers and the common command sequence
+    fn decode_common(cbor: &'a ByteSlice, offset: usize) -> Result<Self, Error> {
+        // Contains the dependencies, the component identifiers and the common command sequence
         let mut decoder = Decoder::new(cbor);
         let mut components = None;
         let mut component_offset = 0;
+        let mut dependencies = None;
+        let mut dependency_offset = 0;
         let mut commands = None;
-        let len = decoder.map()?.ok_or(Error::InvalidCommonSection)?;
+        let len = decoder
+            .map()?
+            .ok_or(Error::UnexpectedIndefiniteLength { position: offset })?;
         for _ in 0..len {
             let key = decoder.i16()?;
             match key {
+                1 => {
+                    dependency_offset = decoder.position() + offset;
+                    dependencies = Some(decoder.sub_cbor()?.into());
+                }
                 2 => {
-                    component_offset = decoder.position();
+                    component_offset = decoder.position() + offset;
                     components = Some(decoder.sub_cbor()?.into());
                 }
                 4 => {
-                    let position = decoder.position() + offset;
-                    commands = Some(CommandSequence::new(decoder.bytes()?.into(), position));
+                    let sequence = decoder.bytes()?;
+                    let position = decoder.position() - sequence.len() + offset;
+                    commands = Some(CommandSequence::new(sequence.into(), position));
                 }
                 _ => return Err(Error::InvalidCommonSection),
             }
         }
-        if let (Some(components), Some(commands)) = (components, commands) {
-            Ok((components, component_offset, commands))
-        } else {
-            Err(Error::InvalidCommonSection)
-        }
+        // Both are optional, a missing element behaves as an empty one
+        const EMPTY_ARRAY: &[u8] = &[0x80];
+        Ok(Self {
+            components: components.unwrap_or(EMPTY_ARRAY.into()),
+            component_offset,
+            shared_sequence: commands
+                .unwrap_or_else(|| CommandSequence::new(EMPTY_ARRAY.into(), offset)),
+            dependencies,
+            dependency_offset,
+        })
     }
 
     fn shared_sequence<'b>(&'b self) -> &'b CommandSequence<'a> {
         &self.shared_sequence
     }
 
+    /// Parameter state of a component after the shared sequence.
+    ///
+    /// The shared sequence runs for every component from a fresh state. Parameters set before the
+    /// first set component index apply to all components, parameters set after it only to the
+    /// selected components, so overrides never leak from one component into another.
+    async fn component_state<N: Nesting>(
+        &self,
+        component_info: &'a ComponentInfo<'a>,
+        os_hooks: &impl AsyncOperatingHooks,
+        report: Option<&'a RefCell<dyn RecordSink>>,
+    ) -> Result<ManifestState<'a>, Error> {
+        self.shared_sequence
+            .execute::<N>(ManifestState::default(), component_info, os_hooks, report)
+            .await
+    }
+
+    fn dependencies(&self) -> Result<DependencyIter<'a>, Error> {
+        match self.dependencies {
+            Some(dependencies) => DependencyIter::new(dependencies, self.dependency_offset),
+            None => Ok(DependencyIter::empty()),
+        }
+    }
+
     fn component_count(&self) -> Result<usize, Error> {
         if let Some(num_components) = Decoder::new(self.components)
             .array()
@@ -331,3 +1022,778 @@ impl<'a> CommonSection<'a> {
             .map(|p| p.valid_shared_sequence())
     }
 }
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod tests {
+    extern crate std;
+    use super::*;
+    use crate::testhooks::NullHooks;
+    use crate::New;
+
+    // SHA-256 digest of h'43820C00'
+    const SEVERED_DIGEST: [u8; 36] = [
+        0x82, 0x2F, 0x58, 0x20, 0xDB, 0x29, 0x62, 0x32, 0x82, 0xDA, 0x41, 0xEC, 0xE4, 0xEB, 0x83,
+        0xF2, 0x30, 0x2F, 0x36, 0xFC, 0x70, 0x48, 0x99, 0x13, 0xFB, 0xFB, 0x7E, 0x85, 0xCE, 0xFA,
+        0x0E, 0xDB, 0x0A, 0xDB, 0x4F, 0xE1,
+    ];
+
+    fn severed_manifest(key: u8) -> std::vec::Vec<u8> {
+        let mut manifest = std::vec![0xA2, 0x01, 0x01, key];
+        manifest.extend_from_slice(&SEVERED_DIGEST);
+        manifest
+    }
+
+    #[test]
+    fn severed_payload_fetch() {
+        // {1: 1, 16: [-16, h'...']}, fetch sequence severed into the envelope
+        let manifest: &[u8] = &severed_manifest(0x10);
+        let envelope: &[u8] = &std::vec![0xA1, 0x10, 0x43, 0x82, 0x0C, 0x00];
+
+        let detached = Manifest::<Authenticated>::from_bytes::<Authenticated>(manifest.into());
+        assert_eq!(
+            detached.fetch_sequence(),
+            Err(Error::MissingSeveredElement { section: 16 })
+        );
+
+        let envelope = Envelope {
+            decoder: Decoder::new(envelope),
+            index: OnceCell::new(),
+            phantom: PhantomData,
+        };
+        let manifest = detached.with_envelope(envelope);
+        let sequence: &[u8] = &std::vec![0x82, 0x0C, 0x00];
+        assert_eq!(manifest.fetch_sequence().unwrap(), Some(sequence.into()));
+    }
+
+    #[test]
+    fn index() {
+        // {99: "x", 2: 5, 1: 1, 2: 6}, only the first sequence number counts
+        let manifest: &[u8] =
+            &std::vec![0xA4, 0x18, 0x63, 0x61, 0x78, 0x02, 0x05, 0x01, 0x01, 0x02, 0x06];
+        let manifest = Manifest::<Authenticated>::from_bytes::<Authenticated>(manifest.into());
+        assert!(manifest.index.get().is_none());
+        assert_eq!(manifest.sequence_number(), Ok(5));
+        assert!(manifest.index.get().is_some());
+        assert_eq!(manifest.version(), Ok(1));
+        assert_eq!(manifest.reference_uri(), Ok(None));
+    }
+
+    #[test]
+    fn payload_installation() {
+        // {1: 1, 20: h'820C00'}
+        let manifest: &[u8] = &std::vec![0xA2, 0x01, 0x01, 0x14, 0x43, 0x82, 0x0C, 0x00];
+        let manifest = Manifest::<Authenticated>::from_bytes::<Authenticated>(manifest.into());
+        let sequence: &[u8] = &std::vec![0x82, 0x0C, 0x00];
+        assert_eq!(manifest.install_sequence().unwrap(), Some(sequence.into()));
+        assert_eq!(manifest.fetch_sequence().unwrap(), None);
+    }
+
+    #[test]
+    fn version() {
+        let manifest: &[u8] = &std::vec![0xA1, 0x01, 0x01];
+        let manifest = Manifest::<New>::from_bytes::<New>(manifest.into());
+        assert_eq!(manifest.version().unwrap(), 1);
+
+        // {1: 2}
+        let manifest: &[u8] = &std::vec![0xA1, 0x01, 0x02];
+        let manifest = Manifest::<New>::from_bytes::<New>(manifest.into());
+        assert_eq!(
+            manifest.version(),
+            Err(Error::UnsupportedManifestVersion { version: 2 })
+        );
+
+        // {1: 256}
+        let manifest: &[u8] = &std::vec![0xA1, 0x01, 0x19, 0x01, 0x00];
+        let manifest = Manifest::<New>::from_bytes::<New>(manifest.into());
+        assert_eq!(
+            manifest.version(),
+            Err(Error::UnsupportedManifestVersion { version: 256 })
+        );
+    }
+
+    #[test]
+    fn sequence_number() {
+        // {1: 1, 2: 7}
+        let manifest: &[u8] = &std::vec![0xA2, 0x01, 0x01, 0x02, 0x07];
+        let manifest = Manifest::<New>::from_bytes::<New>(manifest.into());
+        assert_eq!(manifest.sequence_number().unwrap(), 7);
+
+        let manifest: &[u8] = &std::vec![0xA1, 0x01, 0x01];
+        let manifest = Manifest::<New>::from_bytes::<New>(manifest.into());
+        assert_eq!(manifest.sequence_number(), Err(Error::NoSequenceNumber));
+    }
+
+    #[test]
+    fn reference_uri() {
+        // {1: 1, 4: "x"}
+        let manifest: &[u8] = &std::vec![0xA2, 0x01, 0x01, 0x04, 0x61, 0x78];
+        let manifest = Manifest::<New>::from_bytes::<New>(manifest.into());
+        assert_eq!(manifest.reference_uri().unwrap(), Some("x"));
+
+        let manifest: &[u8] = &std::vec![0xA1, 0x01, 0x01];
+        let manifest = Manifest::<New>::from_bytes::<New>(manifest.into());
+        assert_eq!(manifest.reference_uri().unwrap(), None);
+    }
+
+    #[test]
+    fn manifest_component_id() {
+        // {1: 1, 5: [h'00']}
+        let manifest: &[u8] = &std::vec![0xA2, 0x01, 0x01, 0x05, 0x81, 0x41, 0x00];
+        let manifest = Manifest::<New>::from_bytes::<New>(manifest.into());
+        let identifier: &[u8] = &[0x81, 0x41, 0x00];
+        assert_eq!(
+            manifest.manifest_component_id().unwrap(),
+            Some(Component::from_bytes(&identifier))
+        );
+
+        let manifest: &[u8] = &std::vec![0xA1, 0x01, 0x01];
+        let manifest = Manifest::<New>::from_bytes::<New>(manifest.into());
+        assert_eq!(manifest.manifest_component_id().unwrap(), None);
+    }
+
+    #[test]
+    fn common_unexpected_key() {
+        // {1: 1, 3: h'A3 02 818141 00 04 41 80 05 00'}, key 5 is not part of the common section
+        let manifest: &[u8] = &std::vec![
+            0xA2, 0x01, 0x01, 0x03, 0x4B, 0xA3, 0x02, 0x81, 0x81, 0x41, 0x00, 0x04, 0x41, 0x80,
+            0x05, 0x00
+        ];
+        let manifest = Manifest::<Authenticated>::from_bytes::<Authenticated>(manifest.into());
+        assert!(matches!(
+            manifest.dependencies(),
+            Err(Error::InvalidCommonSection)
+        ));
+        assert_eq!(
+            manifest.verify_images(&NullHooks::default()),
+            Err(Error::InvalidCommonSection)
+        );
+    }
+
+    #[test]
+    fn common_indefinite_length() {
+        // {1: 1, 3: h'BF 02 818141 00 04 41 80 FF'}
+        let manifest: &[u8] = &std::vec![
+            0xA2, 0x01, 0x01, 0x03, 0x4A, 0xBF, 0x02, 0x81, 0x81, 0x41, 0x00, 0x04, 0x41, 0x80,
+            0xFF
+        ];
+        let manifest = Manifest::<Authenticated>::from_bytes::<Authenticated>(manifest.into());
+        assert!(matches!(
+            manifest.dependencies(),
+            Err(Error::UnexpectedIndefiniteLength { position: 5 })
+        ));
+        assert_eq!(
+            manifest.summary(),
+            Err(Error::UnexpectedIndefiniteLength { position: 5 })
+        );
+
+        // {_ 1: 1}
+        let manifest: &[u8] = &std::vec![0xBF, 0x01, 0x01, 0xFF];
+        let manifest = Manifest::<New>::from_bytes::<New>(manifest.into());
+        assert_eq!(
+            manifest.version(),
+            Err(Error::UnexpectedIndefiniteLength { position: 0 })
+        );
+    }
+
+    #[test]
+    fn sections() {
+        // {1: 1, 4: "x", 99: h'00'}
+        let manifest: &[u8] =
+            &std::vec![0xA3, 0x01, 0x01, 0x04, 0x61, 0x78, 0x18, 0x63, 0x41, 0x00];
+        let manifest = Manifest::<New>::from_bytes::<New>(manifest.into());
+        let mut sections = manifest.sections();
+        let version: &[u8] = &[0x01];
+        assert_eq!(sections.next(), Some(Ok((1, version.into()))));
+        let uri: &[u8] = &[0x61, 0x78];
+        assert_eq!(sections.next(), Some(Ok((4, uri.into()))));
+        let unknown: &[u8] = &[0x41, 0x00];
+        assert_eq!(sections.next(), Some(Ok((99, unknown.into()))));
+        assert_eq!(sections.next(), None);
+
+        let manifest: &[u8] = &std::vec![0xBF, 0x01, 0x01, 0xFF];
+        let manifest = Manifest::<New>::from_bytes::<New>(manifest.into());
+        let mut sections = manifest.sections();
+        assert_eq!(
+            sections.next(),
+            Some(Err(Error::UnexpectedIndefiniteLength { position: 0 }))
+        );
+        assert_eq!(sections.next(), None);
+    }
+
+    #[test]
+    fn commands() {
+        // [20, {5: 2}, 3, 15]
+        let sequence: &[u8] = &std::vec![0x84, 0x14, 0xA1, 0x05, 0x02, 0x03, 0x0F];
+        let mut commands = Manifest::<New>::commands(sequence.into());
+        let parameters: &[u8] = &[0xA1, 0x05, 0x02];
+        assert_eq!(
+            commands.next(),
+            Some(Ok((SuitCommand::OverrideParameters, parameters.into())))
+        );
+        let policy: &[u8] = &[0x0F];
+        assert_eq!(
+            commands.next(),
+            Some(Ok((SuitCommand::ImageMatch, policy.into())))
+        );
+        assert_eq!(commands.next(), None);
+
+        let sequence: &[u8] = &std::vec![0x81, 0x14];
+        let mut commands = Manifest::<New>::commands(sequence.into());
+        assert_eq!(
+            commands.next(),
+            Some(Err(Error::InvalidCommandSequence { position: 0 }))
+        );
+        assert_eq!(commands.next(), None);
+    }
+
+    #[test]
+    fn image_sequences() {
+        // {1: 1, 7: h'820C00', 9: h'820C01'}
+        let manifest: &[u8] = &std::vec![
+            0xA3, 0x01, 0x01, 0x07, 0x43, 0x82, 0x0C, 0x00, 0x09, 0x43, 0x82, 0x0C, 0x01
+        ];
+        let manifest = Manifest::<Authenticated>::from_bytes::<Authenticated>(manifest.into());
+        let validate: &[u8] = &std::vec![0x82, 0x0C, 0x00];
+        let invoke: &[u8] = &std::vec![0x82, 0x0C, 0x01];
+        assert_eq!(**manifest.validate_sequence().unwrap().unwrap(), *validate);
+        assert_eq!(**manifest.invoke_sequence().unwrap().unwrap(), *invoke);
+        assert_eq!(manifest.load_sequence(), Ok(None));
+    }
+
+    #[test]
+    fn severed_payload_installation() {
+        // {1: 1, 20: [-16, h'...']}, installation sequence severed into the envelope
+        let manifest: &[u8] = &severed_manifest(0x14);
+        let envelope: &[u8] = &std::vec![0xA1, 0x14, 0x43, 0x82, 0x0C, 0x00];
+        let envelope = Envelope {
+            decoder: Decoder::new(envelope),
+            index: OnceCell::new(),
+            phantom: PhantomData,
+        };
+        let manifest = Manifest::<Authenticated>::from_bytes::<Authenticated>(manifest.into())
+            .with_envelope(envelope);
+        let sequence: &[u8] = &std::vec![0x82, 0x0C, 0x00];
+        assert_eq!(manifest.install_sequence().unwrap(), Some(sequence.into()));
+    }
+
+    #[test]
+    fn severed_element_missing() {
+        // {1: 1, 2: 1, 3: h'A2 02 818141 00 04 43 820C00', 20: [-16, h'...']}, the envelope lacks
+        // the installation sequence the manifest holds the digest of
+        let mut manifest = std::vec![
+            0xA4, 0x01, 0x01, 0x02, 0x01, 0x03, 0x4B, 0xA2, 0x02, 0x81, 0x81, 0x41, 0x00, 0x04,
+            0x43, 0x82, 0x0C, 0x00, 0x14
+        ];
+        manifest.extend_from_slice(&SEVERED_DIGEST);
+        let manifest: &[u8] = &manifest;
+        let envelope: &[u8] = &std::vec![0xA1, 0x10, 0x43, 0x82, 0x0C, 0x00];
+        let envelope = Envelope {
+            decoder: Decoder::new(envelope),
+            index: OnceCell::new(),
+            phantom: PhantomData,
+        };
+        let manifest = Manifest::<Authenticated>::from_bytes::<Authenticated>(manifest.into())
+            .with_envelope(envelope);
+        let missing = Error::MissingSeveredElement { section: 20 };
+        assert_eq!(manifest.install_sequence(), Err(missing));
+        assert_eq!(
+            manifest.execute_full(&NullHooks::default()).err(),
+            Some(missing)
+        );
+    }
+
+    #[test]
+    fn severed_digest_mismatch() {
+        // Envelope contains a different installation sequence than the digest covers
+        let manifest: &[u8] = &severed_manifest(0x14);
+        let envelope: &[u8] = &std::vec![0xA1, 0x14, 0x43, 0x82, 0x0C, 0x01];
+        let envelope = Envelope {
+            decoder: Decoder::new(envelope),
+            index: OnceCell::new(),
+            phantom: PhantomData,
+        };
+        let manifest = Manifest::<Authenticated>::from_bytes::<Authenticated>(manifest.into())
+            .with_envelope(envelope);
+        assert_eq!(
+            manifest.install_sequence(),
+            Err(Error::ConditionMatchFail { position: 4 })
+        );
+    }
+
+    #[test]
+    fn component_state() {
+        use crate::builder::{ManifestBuilder, Parameter, SequenceCommand};
+        use crate::digest::SuitDigestAlgorithm;
+
+        let vendor = uuid::Uuid::from_bytes([0xAA; 16]);
+        let first = [0x81, 0x41, 0x00];
+        let second = [0x81, 0x41, 0x01];
+        let components = [
+            Component::from_bytes(&first),
+            Component::from_bytes(&second),
+        ];
+        let shared = [Parameter::VendorId(vendor)];
+        let first_digest = [Parameter::ImageDigest {
+            algorithm: SuitDigestAlgorithm::Sha256,
+            digest: &[0x01; 32],
+        }];
+        let second_digest = [Parameter::ImageDigest {
+            algorithm: SuitDigestAlgorithm::Sha256,
+            digest: &[0x02; 32],
+        }];
+        let commands = [
+            SequenceCommand::OverrideParameters(&shared),
+            SequenceCommand::SetComponentIndex(0),
+            SequenceCommand::OverrideParameters(&first_digest),
+            SequenceCommand::SetComponentIndex(1),
+            SequenceCommand::OverrideParameters(&second_digest),
+        ];
+        let builder = ManifestBuilder::new()
+            .components(&components)
+            .common_sequence(&commands);
+        let mut buf = [0u8; 256];
+        let len = builder.encode_into(&mut buf).unwrap();
+        let manifest = Manifest::<Authenticated>::from_bytes::<Authenticated>(buf[..len].into());
+        let common = manifest.get_common().unwrap();
+        let hooks = NullHooks::default();
+
+        let first_info = ComponentInfo::new(components[0], 0);
+        let state =
+            block_on(common.component_state::<MaxNesting>(&first_info, &Blocking(&hooks), None))
+                .unwrap();
+        assert_eq!(state.vendor_id, Some(vendor));
+        let digest: &[u8] = &[0x01; 32];
+        assert_eq!(
+            state.image_digest,
+            Some(SuitDigest::new(SuitDigestAlgorithm::Sha256, digest.into()))
+        );
+
+        let second_info = ComponentInfo::new(components[1], 1);
+        let state =
+            block_on(common.component_state::<MaxNesting>(&second_info, &Blocking(&hooks), None))
+                .unwrap();
+        assert_eq!(state.vendor_id, Some(vendor));
+        let digest: &[u8] = &[0x02; 32];
+        assert_eq!(
+            state.image_digest,
+            Some(SuitDigest::new(SuitDigestAlgorithm::Sha256, digest.into()))
+        );
+    }
+
+    #[test]
+    fn image_validation_component() {
+        use crate::builder::{ManifestBuilder, Parameter, SequenceCommand};
+        use crate::digest::SuitDigestAlgorithm;
+
+        // Only the first component matches the empty components of the null hooks
+        const EMPTY: [u8; 32] = [
+            0xe3, 0xb0, 0xc4, 0x42, 0x98, 0xfc, 0x1c, 0x14, 0x9a, 0xfb, 0xf4, 0xc8, 0x99, 0x6f,
+            0xb9, 0x24, 0x27, 0xae, 0x41, 0xe4, 0x64, 0x9b, 0x93, 0x4c, 0xa4, 0x95, 0x99, 0x1b,
+            0x78, 0x52, 0xb8, 0x55,
+        ];
+        let first = [0x81, 0x41, 0x00];
+        let second = [0x81, 0x41, 0x01];
+        let components = [
+            Component::from_bytes(&first),
+            Component::from_bytes(&second),
+        ];
+        let matching = [Parameter::ImageDigest {
+            algorithm: SuitDigestAlgorithm::Sha256,
+            digest: &EMPTY,
+        }];
+        let mismatching = [Parameter::ImageDigest {
+            algorithm: SuitDigestAlgorithm::Sha256,
+            digest: &[0x00; 32],
+        }];
+        let commands = [
+            SequenceCommand::SetComponentIndex(0),
+            SequenceCommand::OverrideParameters(&matching),
+            SequenceCommand::SetComponentIndex(1),
+            SequenceCommand::OverrideParameters(&mismatching),
+        ];
+        // The validation sequence checks the image, h'82 03 0F'
+        let sections = [(
+            crate::consts::Manifest::ImageValidation,
+            [0x82, 0x03, 0x0F].as_slice(),
+        )];
+        let builder = ManifestBuilder::new()
+            .components(&components)
+            .common_sequence(&commands)
+            .sections(&sections);
+        let mut buf = [0u8; 256];
+        let len = builder.encode_into(&mut buf).unwrap();
+
+        let manifest = Manifest::<Authenticated>::from_bytes::<Authenticated>(buf[..len].into());
+        let mismatch = Err(Error::ComponentConditionFailed {
+            index: 1,
+            command: SuitCommand::ImageMatch.into(),
+            position: 110,
+        });
+        assert_eq!(
+            manifest.execute_image_validation(&NullHooks::default()),
+            mismatch
+        );
+        assert_eq!(
+            manifest.execute_image_validation_component(0, &NullHooks::default()),
+            Ok(())
+        );
+        assert_eq!(
+            manifest.execute_image_validation_component(1, &NullHooks::default()),
+            mismatch
+        );
+        assert_eq!(
+            manifest.execute_image_validation_component(2, &NullHooks::default()),
+            Err(Error::NoComponent { index: 2 })
+        );
+
+        // The asynchronous hooks run through the same executor
+        #[cfg(feature = "async")]
+        {
+            let hooks = NullHooks::default();
+            assert_eq!(
+                block_on(manifest.execute_image_validation_async(&Blocking(&hooks))),
+                mismatch
+            );
+            let mut report = SuitReport::<4>::new();
+            let mut async_report = SuitReport::<4>::new();
+            assert_eq!(
+                manifest.execute_image_validation_with_report(&hooks, &mut report),
+                mismatch
+            );
+            assert_eq!(
+                block_on(manifest.execute_image_validation_with_report_async(
+                    &Blocking(&hooks),
+                    &mut async_report
+                )),
+                mismatch
+            );
+            assert!(!report.records().is_empty());
+            assert_eq!(report.records(), async_report.records());
+        }
+    }
+
+    #[test]
+    fn verify_images() {
+        use crate::builder::{ManifestBuilder, Parameter, SequenceCommand};
+        use crate::digest::SuitDigestAlgorithm;
+
+        // SHA-256 of the empty components of the null hooks
+        const EMPTY: [u8; 32] = [
+            0xe3, 0xb0, 0xc4, 0x42, 0x98, 0xfc, 0x1c, 0x14, 0x9a, 0xfb, 0xf4, 0xc8, 0x99, 0x6f,
+            0xb9, 0x24, 0x27, 0xae, 0x41, 0xe4, 0x64, 0x9b, 0x93, 0x4c, 0xa4, 0x95, 0x99, 0x1b,
+            0x78, 0x52, 0xb8, 0x55,
+        ];
+        let first = [0x81, 0x41, 0x00];
+        let second = [0x81, 0x41, 0x01];
+        let components = [
+            Component::from_bytes(&first),
+            Component::from_bytes(&second),
+        ];
+        let matching = [Parameter::ImageDigest {
+            algorithm: SuitDigestAlgorithm::Sha256,
+            digest: &EMPTY,
+        }];
+        let mismatching = [Parameter::ImageDigest {
+            algorithm: SuitDigestAlgorithm::Sha256,
+            digest: &[0x00; 32],
+        }];
+        // Validation checks the vendor, the image and invokes, h'86 01 0F 03 0F 17 0F'
+        let validation = [0x86, 0x01, 0x0F, 0x03, 0x0F, 0x17, 0x0F];
+        let sections = [(
+            crate::consts::Manifest::ImageValidation,
+            validation.as_slice(),
+        )];
+
+        for (second_digest, result) in [
+            (&matching, Ok(())),
+            (
+                &mismatching,
+                Err(Error::ComponentConditionFailed {
+                    index: 1,
+                    command: SuitCommand::ImageMatch.into(),
+                    position: 112,
+                }),
+            ),
+        ] {
+            let commands = [
+                SequenceCommand::SetComponentIndex(0),
+                SequenceCommand::OverrideParameters(&matching),
+                SequenceCommand::SetComponentIndex(1),
+                SequenceCommand::OverrideParameters(second_digest),
+            ];
+            let builder = ManifestBuilder::new()
+                .components(&components)
+                .common_sequence(&commands)
+                .sections(&sections);
+            let mut buf = [0u8; 256];
+            let len = builder.encode_into(&mut buf).unwrap();
+
+            let manifest =
+                Manifest::<Authenticated>::from_bytes::<Authenticated>(buf[..len].into());
+            assert_eq!(manifest.verify_images(&NullHooks::default()), result);
+            #[cfg(feature = "async")]
+            assert_eq!(
+                block_on(manifest.verify_images_async(&Blocking(&NullHooks::default()))),
+                result
+            );
+        }
+    }
+
+    #[test]
+    fn check_sequence_number() {
+        use crate::builder::{ManifestBuilder, SequenceCommand};
+
+        let component = [0x81, 0x41, 0x00];
+        let components = [Component::from_bytes(&component)];
+        let commands = [SequenceCommand::SetComponentIndex(0)];
+        let builder = ManifestBuilder::new()
+            .sequence_number(5)
+            .components(&components)
+            .common_sequence(&commands);
+        let mut buf = [0u8; 64];
+        let len = builder.encode_into(&mut buf).unwrap();
+        let manifest = Manifest::<Authenticated>::from_bytes::<Authenticated>(buf[..len].into());
+
+        assert_eq!(
+            manifest.check_sequence_number(&NullHooks::default()),
+            Ok(())
+        );
+        assert_eq!(
+            manifest.check_sequence_number(&NullHooks { sequence_number: 4 }),
+            Ok(())
+        );
+        assert_eq!(
+            manifest.check_sequence_number(&NullHooks { sequence_number: 5 }),
+            Err(Error::SequenceNumberTooLow {
+                sequence_number: 5,
+                stored: 5
+            })
+        );
+    }
+
+    #[test]
+    fn execute_full() {
+        use crate::builder::{ManifestBuilder, SequenceCommand};
+
+        let component = [0x81, 0x41, 0x00];
+        let components = [Component::from_bytes(&component)];
+        let commands = [SequenceCommand::SetComponentIndex(0)];
+        // Image validation section h'820C00'
+        let sections = [(
+            crate::consts::Manifest::ImageValidation,
+            [0x82, 0x0C, 0x00].as_slice(),
+        )];
+        let builder = ManifestBuilder::new()
+            .sequence_number(5)
+            .components(&components)
+            .common_sequence(&commands)
+            .sections(&sections);
+        let mut buf = [0u8; 64];
+        let len = builder.encode_into(&mut buf).unwrap();
+        let manifest = Manifest::<Authenticated>::from_bytes::<Authenticated>(buf[..len].into());
+
+        let outcome = manifest.execute_full(&NullHooks::default()).unwrap();
+        assert_eq!(outcome.sequence_number(), 5);
+        assert_eq!(outcome.components(), 1);
+        assert!(outcome.executed(crate::consts::Manifest::ImageValidation));
+        assert!(!outcome.executed(crate::consts::Manifest::PayloadFetch));
+        assert_eq!(outcome.sections().count(), 1);
+        assert_eq!(manifest.execute_all(&NullHooks::default()), Ok(()));
+    }
+
+    #[test]
+    fn scan_unsupported() {
+        use crate::builder::{ManifestBuilder, SequenceCommand};
+
+        let component = [0x81, 0x41, 0x00];
+        let components = [Component::from_bytes(&component)];
+        let commands = [SequenceCommand::SetComponentIndex(0)];
+        let builder = ManifestBuilder::new()
+            .sequence_number(1)
+            .components(&components)
+            .common_sequence(&commands);
+        let mut buf = [0u8; 64];
+        let len = builder.encode_into(&mut buf).unwrap();
+        let manifest = Manifest::<New>::from_bytes::<New>(buf[..len].into());
+        assert_eq!(manifest.scan_unsupported::<4>(), Ok(heapless::Vec::new()));
+
+        // Installation with two copy directives and a swap directive nested in a try each
+        let install = [
+            0x86, 0x16, 0x0F, 0x16, 0x0F, 0x0F, 0x81, 0x44, 0x82, 0x18, 0x1F, 0x0F,
+        ];
+        let sections = [(
+            crate::consts::Manifest::PayloadInstallation,
+            install.as_slice(),
+        )];
+        let builder = builder.sections(&sections);
+        let len = builder.encode_into(&mut buf).unwrap();
+        let manifest = Manifest::<New>::from_bytes::<New>(buf[..len].into());
+        let found = manifest.scan_unsupported::<4>().unwrap();
+        assert_eq!(found.as_slice(), &[SuitCommand::Copy, SuitCommand::Swap]);
+        assert_eq!(manifest.scan_unsupported::<1>(), Err(Error::CapacityError));
+    }
+
+    #[test]
+    fn summary() {
+        use crate::builder::{ManifestBuilder, SequenceCommand};
+
+        let component = [0x81, 0x41, 0x00];
+        let components = [Component::from_bytes(&component)];
+        let commands = [SequenceCommand::SetComponentIndex(0)];
+        // Payload installation section h'820C00'
+        let sections = [(
+            crate::consts::Manifest::PayloadInstallation,
+            [0x82, 0x0C, 0x00].as_slice(),
+        )];
+        let builder = ManifestBuilder::new()
+            .sequence_number(3)
+            .components(&components)
+            .common_sequence(&commands)
+            .sections(&sections);
+        let mut buf = [0u8; 64];
+        let len = builder.encode_into(&mut buf).unwrap();
+        let manifest = Manifest::<New>::from_bytes::<New>(buf[..len].into());
+
+        assert_eq!(
+            manifest.summary(),
+            Ok(ManifestSummary {
+                version: 1,
+                sequence_number: 3,
+                components: 1,
+                common: true,
+                install: true,
+                fetch: false,
+                text: false,
+            })
+        );
+    }
+
+    #[test]
+    fn summary_without_common_sequence() {
+        use crate::builder::ManifestBuilder;
+
+        let component = [0x81, 0x41, 0x00];
+        let components = [Component::from_bytes(&component)];
+        let builder = ManifestBuilder::new().components(&components);
+        let mut buf = [0u8; 64];
+        let len = builder.encode_into(&mut buf).unwrap();
+        let manifest = Manifest::<New>::from_bytes::<New>(buf[..len].into());
+
+        assert_eq!(
+            manifest.summary(),
+            Ok(ManifestSummary {
+                version: 1,
+                sequence_number: 0,
+                components: 1,
+                common: true,
+                install: false,
+                fetch: false,
+                text: false,
+            })
+        );
+        let manifest = Manifest::<Authenticated>::from_bytes::<Authenticated>(buf[..len].into());
+        let common = manifest.get_common().unwrap();
+        let hooks = NullHooks::default();
+        let info = ComponentInfo::new(components[0], 0);
+        let state =
+            block_on(common.component_state::<MaxNesting>(&info, &Blocking(&hooks), None)).unwrap();
+        assert_eq!(state.vendor_id, None);
+    }
+
+    #[test]
+    fn enforce_digest_policy_empty_alternative() {
+        use crate::builder::{ManifestBuilder, SequenceCommand};
+
+        let component = [0x81, 0x41, 0x00];
+        let components = [Component::from_bytes(&component)];
+        let commands = [SequenceCommand::SetComponentIndex(0)];
+        // Installation with a try each of an empty sequence and a sequence setting a SHA-256
+        // digest: [15, [h'', h'8214A10344822F4100']]
+        let install = [
+            0x82, 0x0F, 0x82, 0x40, 0x49, 0x82, 0x14, 0xA1, 0x03, 0x44, 0x82, 0x2F, 0x41, 0x00,
+        ];
+        let sections = [(
+            crate::consts::Manifest::PayloadInstallation,
+            install.as_slice(),
+        )];
+        let builder = ManifestBuilder::new()
+            .sequence_number(1)
+            .components(&components)
+            .common_sequence(&commands)
+            .sections(&sections);
+        let mut buf = [0u8; 64];
+        let len = builder.encode_into(&mut buf).unwrap();
+        let manifest = Manifest::<Authenticated>::from_bytes::<Authenticated>(buf[..len].into());
+
+        assert_eq!(
+            manifest.enforce_digest_policy(&[SuitDigestAlgorithm::Sha256]),
+            Ok(())
+        );
+        assert_eq!(
+            manifest.enforce_digest_policy(&[]),
+            Err(Error::DigestAlgorithmNotPermitted { algorithm: -16 })
+        );
+    }
+
+    #[test]
+    #[cfg(feature = "shake128")]
+    fn enforce_digest_policy() {
+        use crate::builder::{ManifestBuilder, Parameter, SequenceCommand};
+
+        let component = [0x81, 0x41, 0x00];
+        let components = [Component::from_bytes(&component)];
+        let parameters = [Parameter::ImageDigest {
+            algorithm: SuitDigestAlgorithm::Shake128,
+            digest: &[0x01; 32],
+        }];
+        let commands = [
+            SequenceCommand::SetComponentIndex(0),
+            SequenceCommand::OverrideParameters(&parameters),
+        ];
+        let builder = ManifestBuilder::new()
+            .sequence_number(1)
+            .components(&components)
+            .common_sequence(&commands);
+        let mut buf = [0u8; 128];
+        let len = builder.encode_into(&mut buf).unwrap();
+        let manifest = Manifest::<Authenticated>::from_bytes::<Authenticated>(buf[..len].into());
+
+        assert_eq!(
+            manifest.enforce_digest_policy(&[SuitDigestAlgorithm::Sha256]),
+            Err(Error::DigestAlgorithmNotPermitted { algorithm: -18 })
+        );
+        assert_eq!(
+            manifest.enforce_digest_policy(&[
+                SuitDigestAlgorithm::Sha256,
+                SuitDigestAlgorithm::Shake128
+            ]),
+            Ok(())
+        );
+    }
+
+    #[test]
+    fn error_position() {
+        // {1: 1, 3: h'A2 02 818141 00 04 43 820C00', 7: h'820E05'}, validation aborts
+        let manifest: &[u8] = &std::vec![
+            0xA3, 0x01, 0x01, 0x03, 0x4B, 0xA2, 0x02, 0x81, 0x81, 0x41, 0x00, 0x04, 0x43, 0x82,
+            0x0C, 0x00, 0x07, 0x43, 0x82, 0x0E, 0x05
+        ];
+        let manifest = Manifest::<Authenticated>::from_bytes::<Authenticated>(manifest.into());
+        assert_eq!(
+            manifest.execute_image_validation(&NullHooks::default()),
+            Err(Error::ManifestAbort { position: 19 })
+        );
+
+        // The common sequence aborts
+        let manifest: &[u8] = &std::vec![
+            0xA3, 0x01, 0x01, 0x03, 0x4B, 0xA2, 0x02, 0x81, 0x81, 0x41, 0x00, 0x04, 0x43, 0x82,
+            0x0E, 0x05, 0x07, 0x43, 0x82, 0x0C, 0x00
+        ];
+        let manifest = Manifest::<Authenticated>::from_bytes::<Authenticated>(manifest.into());
+        assert_eq!(
+            manifest.execute_image_validation(&NullHooks::default()),
+            Err(Error::ManifestAbort { position: 14 })
+        );
+    }
+}
@@ -1,6 +1,9 @@
 //! Manifest runtime parameter state.
+use crate::cbor::SubCbor;
 use crate::consts::SuitParameter;
 use crate::digest::SuitDigest;
+#[cfg(feature = "encryption")]
+use crate::encryption::EncryptionInfo;
 use crate::error::Error;
 use minicbor::bytes::{ByteArray, ByteSlice};
 use minicbor::decode::{Decode, Decoder};
@@ -18,9 +21,16 @@ pub(crate) struct ManifestState<'a> {
     pub(crate) class_id: Option<Uuid>,
     pub(crate) device_id: Option<Uuid>,
     pub(crate) image_digest: Option<SuitDigest<'a>>,
+    pub(crate) use_before: Option<u64>,
     pub(crate) component_slot: Option<u64>,
     pub(crate) image_size: Option<usize>,
     pub(crate) uri: Option<&'a str>,
+    pub(crate) source_component: Option<u32>,
+    pub(crate) invoke_args: Option<&'a ByteSlice>,
+    pub(crate) strict_order: Option<bool>,
+    pub(crate) soft_failure: Option<bool>,
+    #[cfg(feature = "encryption")]
+    pub(crate) encryption_info: Option<EncryptionInfo<'a>>,
 }
 
 impl<'a> ManifestState<'a> {
@@ -76,11 +86,21 @@ impl<'a> ManifestState<'a> {
     ) -> Result<(), Error> {
         let bytes = decoder.bytes()?;
         let mut inner = Decoder::new(bytes);
-        let digest = SuitDigest::decode(&mut inner, &mut ())?;
+        let digest = SuitDigest::from_cbor(&mut inner)?;
         self.set_image_digest(digest);
         Ok(())
     }
 
+    pub(crate) fn set_use_before(&mut self, use_before: u64) {
+        self.use_before = Some(use_before);
+    }
+
+    pub(crate) fn use_before_from_cbor(&mut self, decoder: &mut Decoder) -> Result<(), Error> {
+        let use_before = decoder.u64()?;
+        self.set_use_before(use_before);
+        Ok(())
+    }
+
     pub(crate) fn component_slot(&mut self, component_slot: u64) {
         self.component_slot = Some(component_slot);
     }
@@ -104,6 +124,49 @@ impl<'a> ManifestState<'a> {
         Ok(())
     }
 
+    pub(crate) fn set_strict_order(&mut self, strict_order: bool) {
+        self.strict_order = Some(strict_order);
+    }
+
+    pub(crate) fn strict_order_from_cbor(&mut self, decoder: &mut Decoder) -> Result<(), Error> {
+        let strict_order = decoder.bool()?;
+        self.set_strict_order(strict_order);
+        Ok(())
+    }
+
+    /// Whether the command sequence must be executed in order, defaults to `true`.
+    ///
+    /// Without strict order failed directives end the command sequence like failed conditions
+    /// when soft failure is enabled.
+    pub(crate) fn strict_order(&self) -> bool {
+        self.strict_order.unwrap_or(true)
+    }
+
+    pub(crate) fn set_soft_failure(&mut self, soft_failure: bool) {
+        self.soft_failure = Some(soft_failure);
+    }
+
+    pub(crate) fn soft_failure_from_cbor(&mut self, decoder: &mut Decoder) -> Result<(), Error> {
+        let soft_failure = decoder.bool()?;
+        self.set_soft_failure(soft_failure);
+        Ok(())
+    }
+
+    /// Whether condition failures end the command sequence without an error, defaults to `false`.
+    pub(crate) fn soft_failure(&self) -> bool {
+        self.soft_failure.unwrap_or(false)
+    }
+
+    /// State for a nested command sequence.
+    ///
+    /// The soft failure parameter is scoped to the command sequence that sets it and starts unset
+    /// in every nested sequence.
+    pub(crate) fn nested(&self) -> Self {
+        let mut state = self.clone();
+        state.soft_failure = None;
+        state
+    }
+
     pub(crate) fn set_uri(&mut self, uri: &'a str) {
         self.uri = Some(uri);
     }
@@ -114,6 +177,42 @@ impl<'a> ManifestState<'a> {
         Ok(())
     }
 
+    pub(crate) fn set_source_component(&mut self, index: u32) {
+        self.source_component = Some(index);
+    }
+
+    pub(crate) fn source_component_from_cbor(
+        &mut self,
+        decoder: &mut Decoder,
+    ) -> Result<(), Error> {
+        let index = decoder.u32()?;
+        self.set_source_component(index);
+        Ok(())
+    }
+
+    pub(crate) fn set_invoke_args(&mut self, args: &'a ByteSlice) {
+        self.invoke_args = Some(args);
+    }
+
+    pub(crate) fn invoke_args_from_cbor(&mut self, decoder: &mut Decoder<'a>) -> Result<(), Error> {
+        self.set_invoke_args(decoder.decode()?);
+        Ok(())
+    }
+
+    #[cfg(feature = "encryption")]
+    pub(crate) fn set_encryption_info(&mut self, info: EncryptionInfo<'a>) {
+        self.encryption_info = Some(info);
+    }
+
+    #[cfg(feature = "encryption")]
+    pub(crate) fn encryption_info_from_cbor(
+        &mut self,
+        decoder: &mut Decoder<'a>,
+    ) -> Result<(), Error> {
+        self.set_encryption_info(EncryptionInfo::from_cbor(decoder)?);
+        Ok(())
+    }
+
     pub(crate) fn update_parameter(&mut self, decoder: &mut Decoder<'a>) -> Result<(), Error> {
         let position = decoder.position();
         let length = decoder.map()?;
@@ -124,12 +223,18 @@ impl<'a> ManifestState<'a> {
                 SuitParameter::VendorId => self.vendor_id_from_cbor(decoder)?,
                 SuitParameter::ClassId => self.class_id_from_cbor(decoder)?,
                 SuitParameter::ImageDigest => self.image_digest_from_cbor(decoder)?,
+                SuitParameter::UseBefore => self.use_before_from_cbor(decoder)?,
                 SuitParameter::ComponentSlot => self.component_slot_from_cbor(decoder)?,
+                SuitParameter::StrictOrder => self.strict_order_from_cbor(decoder)?,
+                SuitParameter::SoftFailure => self.soft_failure_from_cbor(decoder)?,
                 SuitParameter::ImageSize => self.image_size_from_cbor(decoder)?,
                 SuitParameter::Uri => self.uri_from_cbor(decoder)?,
-                SuitParameter::SourceComponent => todo!(),
+                SuitParameter::SourceComponent => self.source_component_from_cbor(decoder)?,
                 SuitParameter::DeviceId => self.device_id_from_cbor(decoder)?,
                 SuitParameter::Content => self.content_from_cbor(decoder)?,
+                SuitParameter::InvokeArgs => self.invoke_args_from_cbor(decoder)?,
+                #[cfg(feature = "encryption")]
+                SuitParameter::EncryptionInfo => self.encryption_info_from_cbor(decoder)?,
                 param => {
                     return Err(Error::UnsupportedParameter {
                         parameter: param.into(),
@@ -141,6 +246,34 @@ impl<'a> ManifestState<'a> {
     }
 }
 
+/// Iterate over the parameters in a set or override parameters map without applying them.
+///
+/// Yields every parameter together with its encoded value, for example to list the parameters a
+/// manifest sets. Unsupported parameters are returned as [`Error::UnsupportedParameter`] and end
+/// the iteration.
+pub fn iter_parameters<'a, 'b>(
+    decoder: &'b mut Decoder<'a>,
+) -> impl Iterator<Item = Result<(SuitParameter, &'a ByteSlice), Error>> + 'b {
+    let position = decoder.position();
+    let (mut remaining, err) = match decoder.map() {
+        Ok(Some(len)) => (len, None),
+        Ok(None) => (0, Some(Err(Error::UnexpectedIndefiniteLength { position }))),
+        Err(e) => (0, Some(Err(e.into()))),
+    };
+    err.into_iter().chain(core::iter::from_fn(move || {
+        if remaining == 0 {
+            return None;
+        }
+        let parameter = decoder
+            .i32()
+            .map_err(Error::from)
+            .and_then(SuitParameter::try_from)
+            .and_then(|param| Ok((param, decoder.sub_cbor()?.into())));
+        remaining = if parameter.is_ok() { remaining - 1 } else { 0 };
+        Some(parameter)
+    }))
+}
+
 #[cfg(test)]
 #[allow(clippy::unwrap_used)]
 mod tests {
@@ -168,6 +301,30 @@ mod tests {
         );
     }
 
+    #[test]
+    fn unassigned_parameter() {
+        // Key 6 is not assigned and must not decode as component slot
+        let input = std::vec![0xA1, 0x06, 0x01];
+        let mut params = ManifestState::default();
+        let mut decoder = Decoder::new(&input);
+        let err = params.update_parameter(&mut decoder);
+        assert_eq!(
+            err.unwrap_err(),
+            Error::UnsupportedParameter { parameter: 6 }
+        );
+        assert_eq!(params.component_slot, None);
+    }
+
+    #[test]
+    fn use_before() {
+        let input = std::vec![0xA1, 0x04, 0x1A, 0x65, 0x53, 0xF1, 0x00];
+        let mut params = ManifestState::default();
+        let mut decoder = Decoder::new(&input);
+        params.update_parameter(&mut decoder).unwrap();
+        assert_eq!(params.use_before, Some(1_700_000_000));
+        assert_eq!(params.component_slot, None);
+    }
+
     #[test]
     fn vendor_id() {
         let input = std::vec![
@@ -205,6 +362,7 @@ mod tests {
         let mut decoder = Decoder::new(&input);
         params.update_parameter(&mut decoder).unwrap();
         assert_eq!(params.device_id.unwrap(), uuid);
+        assert_eq!(params.vendor_id, None);
     }
 
     #[test]
@@ -243,6 +401,49 @@ mod tests {
         assert_eq!(params.uri.unwrap(), uri);
     }
 
+    #[test]
+    fn strict_order() {
+        let params = ManifestState::default();
+        assert!(params.strict_order());
+
+        let input = std::vec![0xA1, 0x0C, 0xF4];
+        let mut params = ManifestState::default();
+        let mut decoder = Decoder::new(&input);
+        params.update_parameter(&mut decoder).unwrap();
+        assert_eq!(params.strict_order, Some(false));
+        assert!(!params.strict_order());
+    }
+
+    #[test]
+    fn soft_failure() {
+        let input = std::vec![0xA1, 0x0D, 0xF5];
+        let mut params = ManifestState::default();
+        let mut decoder = Decoder::new(&input);
+        params.update_parameter(&mut decoder).unwrap();
+        assert!(params.soft_failure());
+        assert!(!params.nested().soft_failure());
+    }
+
+    #[test]
+    fn source_component() {
+        let input = std::vec![0xA1, 0x16, 0x02];
+        let mut params = ManifestState::default();
+        let mut decoder = Decoder::new(&input);
+        params.update_parameter(&mut decoder).unwrap();
+        assert_eq!(params.source_component, Some(2));
+    }
+
+    #[test]
+    fn invoke_args() {
+        let input = std::vec![0xA1, 0x17, 0x43, 0x61, 0x72, 0x67];
+        let mut params = ManifestState::default();
+        let mut decoder = Decoder::new(&input);
+        params.update_parameter(&mut decoder).unwrap();
+
+        let args: &[u8] = b"arg";
+        assert_eq!(**params.invoke_args.unwrap(), *args);
+    }
+
     #[test]
     fn multiple() {
         use crate::digest::SuitDigestAlgorithm;
@@ -271,4 +472,31 @@ mod tests {
         assert_eq!(params.class_id.unwrap(), class_id);
         assert_eq!(params.image_digest.unwrap(), digest);
     }
+
+    #[test]
+    fn parameters() {
+        // {1: h'00', 14: 34768, 99: 0}
+        let input = std::vec![0xA3, 0x01, 0x41, 0x00, 0x0E, 0x19, 0x87, 0xD0, 0x18, 0x63, 0x00];
+        let mut decoder = Decoder::new(&input);
+        let params: std::vec::Vec<_> = iter_parameters(&mut decoder)
+            .map(|param| param.map(|(param, value)| (i32::from(param), value.to_vec())))
+            .collect();
+        assert_eq!(
+            params,
+            [
+                Ok((1, std::vec![0x41, 0x00])),
+                Ok((14, std::vec![0x19, 0x87, 0xD0])),
+                Err(Error::UnsupportedParameter { parameter: 99 }),
+            ]
+        );
+
+        let input = std::vec![0xBF, 0x01, 0x41, 0x00, 0xFF];
+        let mut decoder = Decoder::new(&input);
+        let mut params = iter_parameters(&mut decoder);
+        assert!(matches!(
+            params.next(),
+            Some(Err(Error::UnexpectedIndefiniteLength { position: 0 }))
+        ));
+        assert!(params.next().is_none());
+    }
 }
@@ -1,9 +1,17 @@
 //! Dress Up operating hooks.
 //!
 use generic_array::ArrayLength;
+use minicbor::bytes::ByteSlice;
 use uuid::Uuid;
 
-use crate::{component::Component, consts::SuitCommand, error::Error};
+use crate::{
+    component::Component,
+    consts::SuitCommand,
+    error::Error,
+    report::{ReportingPolicy, SuitRecord},
+};
+#[cfg(feature = "encryption")]
+use crate::{consts::SuitParameter, encryption::EncryptionInfo};
 
 /// A trait to expose operating system functionality to the SUIT manifest parsing
 ///
@@ -51,6 +59,15 @@ pub trait OperatingHooks {
         })
     }
 
+    /// Current time in seconds since the UNIX epoch.
+    ///
+    /// Required for the use before condition, which rejects manifests past their expiry.
+    fn current_time(&self) -> Result<u64, Error> {
+        Err(Error::UnsupportedCommand {
+            command: SuitCommand::UseBefore.into(),
+        })
+    }
+
     /// Read the data from a component (with slot) into the supplied buffer.
     fn component_read(
         &self,
@@ -80,10 +97,170 @@ pub trait OperatingHooks {
         self.component_capacity(component).map(|_| ())
     }
 
-    /// Retrieve the payload from the url and store it in the component.
-    fn fetch(&self, _component: &Component, _slot: Option<u64>, _uri: &str) -> Result<(), Error> {
+    /// Retrieve the payload from the uri and stream it into the component.
+    ///
+    /// The implementation passes each retrieved chunk together with its offset in the payload to
+    /// the `write` callback, which stores the chunk in the component (with slot) via
+    /// [`OperatingHooks::component_write`]. This avoids buffering the full payload in memory.
+    fn component_fetch(
+        &self,
+        _component: &Component,
+        _slot: Option<u64>,
+        _uri: &str,
+        _write: &mut impl FnMut(usize, &[u8]) -> Result<(), Error>,
+    ) -> Result<(), Error> {
         Err(Error::UnsupportedCommand {
             command: SuitCommand::Fetch.into(),
         })
     }
+
+    /// Verify the image digest while fetching the payload.
+    ///
+    /// When enabled, the fetch directive hashes each chunk as it is written into the component
+    /// and fails with [`Error::DigestMismatch`] if the digest of the payload does not match the
+    /// image digest parameter. The component is erased again via
+    /// [`OperatingHooks::component_erase`] on a mismatch or a failed fetch. Only an
+    /// implementation overriding the erase removes the bad image, with the default no-op erase
+    /// the image remains in the component and must not be used after the error. The default
+    /// implementation disables the verification.
+    fn verify_fetch(&self) -> bool {
+        false
+    }
+
+    /// Erase the content of the component (with slot).
+    ///
+    /// The write and fetch directives install a fresh image and call this once before the first
+    /// write into the component, after the capacity check. Flash components can erase here to
+    /// avoid programming over non-erased flash. It is called again to remove a payload that
+    /// failed the digest verification with [`OperatingHooks::verify_fetch`] enabled or broke off
+    /// before it could be verified, or an encrypted payload that failed to decrypt, as the
+    /// plaintext is not authenticated before the decryption finished. The default implementation
+    /// does nothing, a failed payload then remains in the component.
+    fn component_erase(&self, _component: &Component, _slot: Option<u64>) -> Result<(), Error> {
+        Ok(())
+    }
+
+    /// Invoke the component (with slot).
+    ///
+    /// The `args` contain the content of the invoke args parameter when set by the manifest.
+    /// Invoking a component might not return on success, failures are propagated as manifest
+    /// processing error.
+    fn invoke(
+        &self,
+        _component: &Component,
+        _slot: Option<u64>,
+        _args: Option<&ByteSlice>,
+    ) -> Result<(), Error> {
+        Err(Error::UnsupportedCommand {
+            command: SuitCommand::Invoke.into(),
+        })
+    }
+
+    /// Start the decryption of a payload before the first chunk.
+    ///
+    /// Called by every write or fetch directive with the encryption info parameter, including
+    /// after an earlier decryption failed before reaching [`OperatingHooks::decrypt_finish`].
+    /// Implementations keeping state between the chunks reset it here. The default implementation
+    /// does nothing.
+    #[cfg(feature = "encryption")]
+    fn decrypt_start(&self, _info: &EncryptionInfo) -> Result<(), Error> {
+        Ok(())
+    }
+
+    /// Decrypt a chunk of an encrypted payload.
+    ///
+    /// Called with consecutive chunks of the ciphertext when the encryption info parameter is set
+    /// for a write or fetch directive. The implementation writes the decrypted bytes into
+    /// `plaintext`, which is as large as the chunk, and returns the number of bytes written. Fewer
+    /// bytes may be returned, for example while holding back a possible authentication tag.
+    #[cfg(feature = "encryption")]
+    fn decrypt(
+        &self,
+        _info: &EncryptionInfo,
+        _ciphertext: &[u8],
+        _plaintext: &mut [u8],
+    ) -> Result<usize, Error> {
+        Err(Error::UnsupportedParameter {
+            parameter: SuitParameter::EncryptionInfo.into(),
+        })
+    }
+
+    /// Finish the decryption of a payload after the last chunk, verifying the authentication tag.
+    #[cfg(feature = "encryption")]
+    fn decrypt_finish(&self, _info: &EncryptionInfo) -> Result<(), Error> {
+        Err(Error::UnsupportedParameter {
+            parameter: SuitParameter::EncryptionInfo.into(),
+        })
+    }
+
+    /// Public key matching the key identifier from the COSE header of an authentication block.
+    ///
+    /// Used by [`crate::SuitManifest::authenticate_with_key`] to select one of multiple
+    /// provisioned keys. Returns [`Error::AuthenticationFailure`] if no key matches, which is also
+    /// the default implementation.
+    fn public_key_for_kid(&self, _kid: &[u8]) -> Result<&[u8], Error> {
+        Err(Error::AuthenticationFailure)
+    }
+
+    /// Sequence number of the manifest that installed the component.
+    ///
+    /// Used for rollback protection by [`crate::manifest::Manifest::check_sequence_number`]. The
+    /// default implementation returns 0 as if no manifest was installed before.
+    fn stored_sequence_number(&self, _component: &Component) -> Result<u64, Error> {
+        Ok(0)
+    }
+
+    /// Resolve the dependency with the supplied index into the SUIT envelope of the dependency.
+    ///
+    /// Called when a command sequence selects a dependency. The `prefix` is the component
+    /// identifier prefix from the dependency metadata in the common section, if any. The returned
+    /// envelope is authenticated with [`Self::authenticate_dependency`] and the image validation
+    /// of the dependent manifest is executed with the same hooks before the command sequence
+    /// continues.
+    fn resolve_dependency(&self, _index: u32, _prefix: Option<&Component>) -> Result<&[u8], Error> {
+        Err(Error::UnsupportedCommand {
+            command: SuitCommand::SetDependencyIndex.into(),
+        })
+    }
+
+    /// Authenticate a dependent manifest.
+    ///
+    /// Called like the closure of [`crate::SuitManifest::authenticate`] with the COSE structures of
+    /// the authentication object of a resolved dependency. The default implementation rejects
+    /// every dependency.
+    fn authenticate_dependency(&self, _cose: &[u8], _payload: &[u8]) -> Result<bool, Error> {
+        Ok(false)
+    }
+
+    /// Match a component against a component identifier from a set component index list.
+    ///
+    /// Called when the set component index command lists components by identifier instead of by
+    /// index. The default implementation compares the decoded identifiers, see
+    /// [`Component::identifier_eq`].
+    fn match_component_identifier(
+        &self,
+        component: &Component,
+        candidate: &Component,
+    ) -> Result<bool, Error> {
+        component.identifier_eq(candidate)
+    }
+
+    /// Receive the reporting policy of a command before it is executed.
+    ///
+    /// Called for every condition and directive carrying a reporting policy. The default
+    /// implementation ignores the policy.
+    fn reporting_policy(
+        &self,
+        _command: SuitCommand,
+        _component: &Component,
+        _policy: ReportingPolicy,
+    ) {
+    }
+
+    /// Emit the record of a completed command.
+    ///
+    /// Called as each command completes when its reporting policy requests a record, allowing
+    /// records to be streamed out instead of buffered in a report. The default implementation
+    /// drops the record.
+    fn emit_record(&self, _record: &SuitRecord) {}
 }
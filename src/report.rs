@@ -1,31 +1,139 @@
 //! SUIT report handling and generation
-use minicbor::Decode;
+use minicbor::{CborLen, Decode, Encode};
 
+use crate::consts::SuitCommand;
+use crate::error::Error;
+
+/// Reporting policy of a SUIT command.
+///
+/// Conditions and directives carry a reporting policy as argument, describing whether a record
+/// should be generated for the command.
+/// See <https://datatracker.ietf.org/doc/html/draft-ietf-suit-manifest-34#name-reporting-policy>
 #[derive(Copy, Clone, Debug, PartialEq, Eq)]
-pub(crate) struct ReportingPolicy {
+pub struct ReportingPolicy {
     policy: u8,
 }
 
 impl ReportingPolicy {
-    pub(crate) fn new(policy: u8) -> ReportingPolicy {
-        ReportingPolicy { policy }
+    /// Create a reporting policy from the policy bits.
+    ///
+    /// Bits outside of the four defined policy flags are ignored.
+    pub fn new(policy: u8) -> ReportingPolicy {
+        ReportingPolicy {
+            policy: policy & 0x0F,
+        }
     }
 
-    pub(crate) fn send_record_on_success(&self) -> bool {
+    /// A record should be sent when the command succeeds.
+    pub fn send_record_on_success(&self) -> bool {
         self.policy & 0x01 > 0
     }
 
-    pub(crate) fn send_record_on_failure(&self) -> bool {
+    /// A record should be sent when the command fails.
+    pub fn send_record_on_failure(&self) -> bool {
         self.policy & 0x02 > 0
     }
 
-    pub(crate) fn add_sysinfo_on_success(&self) -> bool {
+    /// System information should be added to the record when the command succeeds.
+    pub fn add_sysinfo_on_success(&self) -> bool {
         self.policy & 0x04 > 0
     }
 
-    pub(crate) fn add_sysinfo_on_failure(&self) -> bool {
+    /// System information should be added to the record when the command fails.
+    pub fn add_sysinfo_on_failure(&self) -> bool {
         self.policy & 0x08 > 0
     }
+
+    /// Create a record of the command result if the policy requests one.
+    pub(crate) fn record(
+        &self,
+        command: SuitCommand,
+        component_index: u32,
+        result: Result<(), Error>,
+    ) -> Option<SuitRecord> {
+        let (send, sysinfo) = match result {
+            Ok(()) => (self.send_record_on_success(), self.add_sysinfo_on_success()),
+            Err(_) => (self.send_record_on_failure(), self.add_sysinfo_on_failure()),
+        };
+        send.then_some(SuitRecord {
+            command,
+            component_index,
+            result,
+            sysinfo,
+        })
+    }
+}
+
+/// Record of a single executed SUIT command.
+#[derive(Copy, Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct SuitRecord {
+    command: SuitCommand,
+    component_index: u32,
+    result: Result<(), Error>,
+    sysinfo: bool,
+}
+
+impl SuitRecord {
+    /// The executed command.
+    pub fn command(&self) -> SuitCommand {
+        self.command
+    }
+
+    /// Index of the component the command was executed for.
+    pub fn component_index(&self) -> u32 {
+        self.component_index
+    }
+
+    /// Result of the command.
+    pub fn result(&self) -> Result<(), Error> {
+        self.result
+    }
+
+    /// The reporting policy requests system information to be added to the record.
+    pub fn sysinfo(&self) -> bool {
+        self.sysinfo
+    }
+}
+
+/// Destination for records generated while processing a command sequence.
+pub(crate) trait RecordSink: core::fmt::Debug {
+    fn push(&mut self, record: SuitRecord);
+}
+
+/// SUIT report accumulating up to `N` command records.
+///
+/// Records are added for commands whose reporting policy requests them. Records beyond the
+/// capacity are dropped and the report is marked as truncated.
+#[derive(Clone, Debug, Default)]
+pub struct SuitReport<const N: usize> {
+    records: heapless::Vec<SuitRecord, N>,
+    truncated: bool,
+}
+
+impl<const N: usize> SuitReport<N> {
+    /// Create an empty report.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The records in order of command execution.
+    pub fn records(&self) -> &[SuitRecord] {
+        &self.records
+    }
+
+    /// Whether records were dropped because the report ran out of capacity.
+    pub fn is_truncated(&self) -> bool {
+        self.truncated
+    }
+}
+
+impl<const N: usize> RecordSink for SuitReport<N> {
+    fn push(&mut self, record: SuitRecord) {
+        if self.records.push(record).is_err() {
+            self.truncated = true;
+        }
+    }
 }
 
 impl<'b, C> Decode<'b, C> for ReportingPolicy {
@@ -43,6 +151,23 @@ impl<'b, C> Decode<'b, C> for ReportingPolicy {
     }
 }
 
+impl<C> Encode<C> for ReportingPolicy {
+    fn encode<W: minicbor::encode::Write>(
+        &self,
+        e: &mut minicbor::Encoder<W>,
+        _ctx: &mut C,
+    ) -> Result<(), minicbor::encode::Error<W::Error>> {
+        e.u8(self.policy)?;
+        Ok(())
+    }
+}
+
+impl<C> CborLen<C> for ReportingPolicy {
+    fn cbor_len(&self, ctx: &mut C) -> usize {
+        self.policy.cbor_len(ctx)
+    }
+}
+
 #[cfg(test)]
 #[allow(clippy::unwrap_used)]
 mod tests {
@@ -67,6 +192,30 @@ mod tests {
         assert!(policy.add_sysinfo_on_failure());
     }
 
+    #[test]
+    fn record() {
+        let policy = ReportingPolicy::new(0x06);
+        assert_eq!(policy.record(SuitCommand::Fetch, 0, Ok(())), None);
+        let record = policy
+            .record(SuitCommand::Fetch, 1, Err(Error::CapacityError))
+            .unwrap();
+        assert_eq!(record.command(), SuitCommand::Fetch);
+        assert_eq!(record.component_index(), 1);
+        assert_eq!(record.result(), Err(Error::CapacityError));
+        assert!(!record.sysinfo());
+    }
+
+    #[test]
+    fn report_truncated() {
+        let policy = ReportingPolicy::new(0x01);
+        let mut report = SuitReport::<1>::new();
+        for _ in 0..2 {
+            report.push(policy.record(SuitCommand::Abort, 0, Ok(())).unwrap());
+        }
+        assert_eq!(report.records().len(), 1);
+        assert!(report.is_truncated());
+    }
+
     #[test]
     fn not_a_report_policy() {
         let input = std::vec![0x20];
@@ -0,0 +1,509 @@
+//! Operating hooks and fixtures shared by the unit tests.
+extern crate std;
+
+use std::cell::{Cell, RefCell};
+
+use minicbor::bytes::ByteSlice;
+use sha2::Digest;
+use uuid::{uuid, Uuid};
+
+use crate::component::Component;
+use crate::consts::SuitCommand;
+#[cfg(feature = "encryption")]
+use crate::encryption::EncryptionInfo;
+use crate::error::Error;
+use crate::report::{ReportingPolicy, SuitRecord};
+use crate::OperatingHooks;
+
+/// Hooks accepting every component, with empty storage and two provisioned keys.
+///
+/// Key identifier `h'01'` resolves to `b"key one"` and `h'02'` to `b"key two"`. Every component
+/// reports `sequence_number` as the sequence number of the manifest that installed it.
+#[derive(Default)]
+pub(crate) struct NullHooks {
+    pub(crate) sequence_number: u64,
+}
+
+impl OperatingHooks for NullHooks {
+    type ReadWriteBufferSize = generic_array::typenum::U16;
+
+    fn match_vendor_id(&self, _uuid: uuid::Uuid, _component: &Component) -> Result<bool, Error> {
+        Ok(true)
+    }
+
+    fn match_class_id(&self, _uuid: uuid::Uuid, _component: &Component) -> Result<bool, Error> {
+        Ok(true)
+    }
+
+    fn component_read(
+        &self,
+        _component: &Component,
+        _slot: Option<u64>,
+        _offset: usize,
+        _bytes: &mut [u8],
+    ) -> Result<(), Error> {
+        Ok(())
+    }
+
+    fn component_write(
+        &self,
+        _component: &Component,
+        _slot: Option<u64>,
+        _offset: usize,
+        _bytes: &[u8],
+    ) -> Result<(), Error> {
+        Ok(())
+    }
+
+    fn component_size(&self, _component: &Component) -> Result<usize, Error> {
+        Ok(0)
+    }
+
+    fn component_capacity(&self, _component: &Component) -> Result<usize, Error> {
+        Ok(0)
+    }
+
+    fn public_key_for_kid(&self, kid: &[u8]) -> Result<&[u8], Error> {
+        match kid {
+            [0x01] => Ok(b"key one"),
+            [0x02] => Ok(b"key two"),
+            _ => Err(Error::AuthenticationFailure),
+        }
+    }
+
+    fn stored_sequence_number(&self, _component: &Component) -> Result<u64, Error> {
+        Ok(self.sequence_number)
+    }
+}
+
+/// Envelope of a dependent manifest, authenticated with an empty COSE_Sign1 structure.
+///
+/// The manifest has the single component h'00' and the image validation sequence
+/// `[command, 0]`.
+pub(crate) fn dependency_envelope(command: u8) -> std::vec::Vec<u8> {
+    // {1: 1, 2: 0, 3: << {2: [[h'00']], 4: << [12, 0] >>} >>, 7: << [command, 0] >>}
+    let manifest = [
+        0xA4, 0x01, 0x01, 0x02, 0x00, 0x03, 0x4B, 0xA2, 0x02, 0x81, 0x81, 0x41, 0x00, 0x04, 0x43,
+        0x82, 0x0C, 0x00, 0x07, 0x43, 0x82, command, 0x00,
+    ];
+    // 107({2: << [<< [-16, h'digest'] >>, << 18([h'', {}, null, h'']) >>] >>, 3: << manifest >>})
+    let mut envelope =
+        std::vec![0xD8, 0x6B, 0xA2, 0x02, 0x58, 0x2E, 0x82, 0x58, 0x24, 0x82, 0x2F, 0x58, 0x20,];
+    // The digest covers the bstr wrapped manifest
+    let digest = sha2::Sha256::new()
+        .chain_update([0x57])
+        .chain_update(manifest)
+        .finalize();
+    envelope.extend_from_slice(&digest);
+    envelope.extend_from_slice(&[0x46, 0xD2, 0x84, 0x40, 0xA0, 0xF6, 0x40, 0x03, 0x57]);
+    envelope.extend_from_slice(&manifest);
+    envelope
+}
+
+/// Vendor identifier matched by the [`StorageHooks`].
+pub(crate) const VENDOR_ID: Uuid = uuid!("fa6b4a53-d5ad-5fdf-be9d-e663e4d41ffe");
+
+/// Class identifier matched by the [`StorageHooks`].
+pub(crate) const CLASS_ID: Uuid = uuid!("1492af14-2569-5e48-bf42-9b2d51f2ab45");
+
+/// Component slot matched by the [`StorageHooks`], exceeding the `u32` range.
+pub(crate) const LARGE_SLOT: u64 = 0x1_0000_0001;
+
+/// Hooks with a single four byte component of the [`VENDOR_ID`] and [`CLASS_ID`].
+///
+/// Fetching the URI `"x"` delivers h'01020304' in two chunks, fetching `"y"` fails after the first
+/// chunk and `"z"` delivers the chunks in reverse order. Erasing the component zeroes it.
+#[derive(Default)]
+pub(crate) struct StorageHooks {
+    pub(crate) buf: Cell<[u8; 4]>,
+    /// Number of times the component was erased.
+    pub(crate) erased: Cell<usize>,
+    pub(crate) verify_fetch: bool,
+}
+
+impl OperatingHooks for StorageHooks {
+    type ReadWriteBufferSize = generic_array::typenum::U64;
+
+    fn match_vendor_id(&self, uuid: Uuid, _component: &Component) -> Result<bool, Error> {
+        Ok(uuid == VENDOR_ID)
+    }
+
+    fn match_class_id(&self, uuid: Uuid, _component: &Component) -> Result<bool, Error> {
+        Ok(uuid == CLASS_ID)
+    }
+
+    fn current_time(&self) -> Result<u64, Error> {
+        Ok(1000)
+    }
+
+    fn match_component_slot(
+        &self,
+        _component: &Component,
+        component_slot: u64,
+    ) -> Result<bool, Error> {
+        Ok(component_slot == LARGE_SLOT)
+    }
+
+    fn component_read(
+        &self,
+        _component: &Component,
+        _slot: Option<u64>,
+        offset: usize,
+        bytes: &mut [u8],
+    ) -> Result<(), Error> {
+        let buf = self.buf.get();
+        bytes.copy_from_slice(
+            buf.get(offset..offset + bytes.len())
+                .ok_or(Error::InvalidCommandSequence { position: 0 })?,
+        );
+        Ok(())
+    }
+
+    fn component_write(
+        &self,
+        _component: &Component,
+        _slot: Option<u64>,
+        offset: usize,
+        bytes: &[u8],
+    ) -> Result<(), Error> {
+        let mut buf = self.buf.get();
+        buf.get_mut(offset..offset + bytes.len())
+            .ok_or(Error::InvalidCommandSequence { position: 0 })?
+            .copy_from_slice(bytes);
+        self.buf.set(buf);
+        Ok(())
+    }
+
+    fn component_size(&self, _component: &Component) -> Result<usize, Error> {
+        Ok(self.buf.get().len())
+    }
+
+    fn component_capacity(&self, _component: &Component) -> Result<usize, Error> {
+        Ok(self.buf.get().len())
+    }
+
+    fn component_fetch(
+        &self,
+        _component: &Component,
+        _slot: Option<u64>,
+        uri: &str,
+        write: &mut impl FnMut(usize, &[u8]) -> Result<(), Error>,
+    ) -> Result<(), Error> {
+        match uri {
+            "x" => {
+                write(0, &[0x01, 0x02])?;
+                write(2, &[0x03, 0x04])
+            }
+            // The transfer breaks off after the first chunk
+            "y" => {
+                write(0, &[0x01, 0x02])?;
+                Err(Error::ConditionMatchFail { position: 0 })
+            }
+            // The chunks arrive in reverse order
+            "z" => {
+                write(2, &[0x03, 0x04])?;
+                write(0, &[0x01, 0x02])
+            }
+            _ => Err(Error::ConditionMatchFail { position: 0 }),
+        }
+    }
+
+    fn verify_fetch(&self) -> bool {
+        self.verify_fetch
+    }
+
+    fn component_erase(&self, _component: &Component, _slot: Option<u64>) -> Result<(), Error> {
+        self.buf.set([0u8; 4]);
+        self.erased.set(self.erased.get() + 1);
+        Ok(())
+    }
+}
+
+/// Hooks recording the reporting policies and records on top of the [`StorageHooks`].
+#[derive(Default)]
+pub(crate) struct ReportHooks {
+    pub(crate) storage: StorageHooks,
+    /// Last reporting policy set.
+    pub(crate) policy: Cell<Option<(SuitCommand, ReportingPolicy)>>,
+    pub(crate) records: RefCell<std::vec::Vec<SuitRecord>>,
+}
+
+impl OperatingHooks for ReportHooks {
+    type ReadWriteBufferSize = generic_array::typenum::U64;
+
+    fn match_vendor_id(&self, uuid: Uuid, component: &Component) -> Result<bool, Error> {
+        self.storage.match_vendor_id(uuid, component)
+    }
+
+    fn match_class_id(&self, uuid: Uuid, component: &Component) -> Result<bool, Error> {
+        self.storage.match_class_id(uuid, component)
+    }
+
+    fn component_read(
+        &self,
+        component: &Component,
+        slot: Option<u64>,
+        offset: usize,
+        bytes: &mut [u8],
+    ) -> Result<(), Error> {
+        self.storage.component_read(component, slot, offset, bytes)
+    }
+
+    fn component_write(
+        &self,
+        component: &Component,
+        slot: Option<u64>,
+        offset: usize,
+        bytes: &[u8],
+    ) -> Result<(), Error> {
+        self.storage.component_write(component, slot, offset, bytes)
+    }
+
+    fn component_size(&self, component: &Component) -> Result<usize, Error> {
+        self.storage.component_size(component)
+    }
+
+    fn component_capacity(&self, component: &Component) -> Result<usize, Error> {
+        self.storage.component_capacity(component)
+    }
+
+    fn component_fetch(
+        &self,
+        component: &Component,
+        slot: Option<u64>,
+        uri: &str,
+        write: &mut impl FnMut(usize, &[u8]) -> Result<(), Error>,
+    ) -> Result<(), Error> {
+        self.storage.component_fetch(component, slot, uri, write)
+    }
+
+    fn reporting_policy(
+        &self,
+        command: SuitCommand,
+        _component: &Component,
+        policy: ReportingPolicy,
+    ) {
+        self.policy.set(Some((command, policy)));
+    }
+
+    fn emit_record(&self, record: &SuitRecord) {
+        self.records.borrow_mut().push(*record);
+    }
+}
+
+/// Hooks decrypting by inverting every byte, on top of the [`StorageHooks`].
+#[cfg(feature = "encryption")]
+#[derive(Default)]
+pub(crate) struct DecryptHooks {
+    pub(crate) storage: StorageHooks,
+    /// Fail the authentication tag check when finishing the decryption.
+    pub(crate) tag_mismatch: bool,
+    /// Number of decryptions started.
+    pub(crate) started: Cell<usize>,
+    /// Whether a decryption finished with a valid tag.
+    pub(crate) decrypted: Cell<bool>,
+}
+
+#[cfg(feature = "encryption")]
+impl OperatingHooks for DecryptHooks {
+    type ReadWriteBufferSize = generic_array::typenum::U64;
+
+    fn match_vendor_id(&self, uuid: Uuid, component: &Component) -> Result<bool, Error> {
+        self.storage.match_vendor_id(uuid, component)
+    }
+
+    fn match_class_id(&self, uuid: Uuid, component: &Component) -> Result<bool, Error> {
+        self.storage.match_class_id(uuid, component)
+    }
+
+    fn component_read(
+        &self,
+        component: &Component,
+        slot: Option<u64>,
+        offset: usize,
+        bytes: &mut [u8],
+    ) -> Result<(), Error> {
+        self.storage.component_read(component, slot, offset, bytes)
+    }
+
+    fn component_write(
+        &self,
+        component: &Component,
+        slot: Option<u64>,
+        offset: usize,
+        bytes: &[u8],
+    ) -> Result<(), Error> {
+        self.storage.component_write(component, slot, offset, bytes)
+    }
+
+    fn component_size(&self, component: &Component) -> Result<usize, Error> {
+        self.storage.component_size(component)
+    }
+
+    fn component_capacity(&self, component: &Component) -> Result<usize, Error> {
+        self.storage.component_capacity(component)
+    }
+
+    fn component_fetch(
+        &self,
+        component: &Component,
+        slot: Option<u64>,
+        uri: &str,
+        write: &mut impl FnMut(usize, &[u8]) -> Result<(), Error>,
+    ) -> Result<(), Error> {
+        self.storage.component_fetch(component, slot, uri, write)
+    }
+
+    fn component_erase(&self, component: &Component, slot: Option<u64>) -> Result<(), Error> {
+        self.storage.component_erase(component, slot)
+    }
+
+    fn decrypt_start(&self, _info: &EncryptionInfo) -> Result<(), Error> {
+        self.started.set(self.started.get() + 1);
+        self.decrypted.set(false);
+        Ok(())
+    }
+
+    fn decrypt(
+        &self,
+        _info: &EncryptionInfo,
+        ciphertext: &[u8],
+        plaintext: &mut [u8],
+    ) -> Result<usize, Error> {
+        for (p, c) in plaintext.iter_mut().zip(ciphertext) {
+            *p = c ^ 0xFF;
+        }
+        Ok(ciphertext.len())
+    }
+
+    fn decrypt_finish(&self, _info: &EncryptionInfo) -> Result<(), Error> {
+        if self.tag_mismatch {
+            return Err(Error::AuthenticationFailure);
+        }
+        self.decrypted.set(true);
+        Ok(())
+    }
+}
+
+/// Hooks resolving every dependency to the same [`dependency_envelope`].
+pub(crate) struct DependencyHooks {
+    envelope: std::vec::Vec<u8>,
+    /// Index of the last resolved dependency and whether it had a prefix.
+    pub(crate) resolved: Cell<Option<(u32, bool)>>,
+}
+
+impl DependencyHooks {
+    /// Resolve to the dependency envelope validating with `command`.
+    pub(crate) fn new(command: u8) -> Self {
+        DependencyHooks {
+            envelope: dependency_envelope(command),
+            resolved: Cell::new(None),
+        }
+    }
+}
+
+impl OperatingHooks for DependencyHooks {
+    type ReadWriteBufferSize = generic_array::typenum::U16;
+
+    fn match_vendor_id(&self, _uuid: Uuid, _component: &Component) -> Result<bool, Error> {
+        Ok(true)
+    }
+
+    fn match_class_id(&self, _uuid: Uuid, _component: &Component) -> Result<bool, Error> {
+        Ok(true)
+    }
+
+    fn component_read(
+        &self,
+        _component: &Component,
+        _slot: Option<u64>,
+        _offset: usize,
+        _bytes: &mut [u8],
+    ) -> Result<(), Error> {
+        Ok(())
+    }
+
+    fn component_write(
+        &self,
+        _component: &Component,
+        _slot: Option<u64>,
+        _offset: usize,
+        _bytes: &[u8],
+    ) -> Result<(), Error> {
+        Ok(())
+    }
+
+    fn component_size(&self, _component: &Component) -> Result<usize, Error> {
+        Ok(0)
+    }
+
+    fn component_capacity(&self, _component: &Component) -> Result<usize, Error> {
+        Ok(0)
+    }
+
+    fn resolve_dependency(&self, index: u32, prefix: Option<&Component>) -> Result<&[u8], Error> {
+        self.resolved.set(Some((index, prefix.is_some())));
+        Ok(&self.envelope)
+    }
+
+    fn authenticate_dependency(&self, _cose: &[u8], _payload: &[u8]) -> Result<bool, Error> {
+        Ok(true)
+    }
+}
+
+/// Hooks recording the arguments of the last invocation.
+#[derive(Default)]
+pub(crate) struct InvokeHooks {
+    pub(crate) invoked: RefCell<Option<std::vec::Vec<u8>>>,
+}
+
+impl OperatingHooks for InvokeHooks {
+    type ReadWriteBufferSize = generic_array::typenum::U16;
+
+    fn match_vendor_id(&self, _uuid: Uuid, _component: &Component) -> Result<bool, Error> {
+        Ok(true)
+    }
+
+    fn match_class_id(&self, _uuid: Uuid, _component: &Component) -> Result<bool, Error> {
+        Ok(true)
+    }
+
+    fn component_read(
+        &self,
+        _component: &Component,
+        _slot: Option<u64>,
+        _offset: usize,
+        _bytes: &mut [u8],
+    ) -> Result<(), Error> {
+        Ok(())
+    }
+
+    fn component_write(
+        &self,
+        _component: &Component,
+        _slot: Option<u64>,
+        _offset: usize,
+        _bytes: &[u8],
+    ) -> Result<(), Error> {
+        Ok(())
+    }
+
+    fn component_size(&self, _component: &Component) -> Result<usize, Error> {
+        Ok(0)
+    }
+
+    fn component_capacity(&self, _component: &Component) -> Result<usize, Error> {
+        Ok(0)
+    }
+
+    fn invoke(
+        &self,
+        _component: &Component,
+        _slot: Option<u64>,
+        args: Option<&ByteSlice>,
+    ) -> Result<(), Error> {
+        self.invoked
+            .replace(Some(args.map(|a| a.to_vec()).unwrap_or_default()));
+        Ok(())
+    }
+}
@@ -0,0 +1,234 @@
+//! SUIT text section.
+//!
+//! Contains human readable descriptions of the manifest and of the components in the manifest.
+use core::str::Utf8Error;
+
+use minicbor::data::Type;
+use minicbor::decode::Decoder;
+
+use crate::cbor::SubCbor;
+use crate::component::Component;
+use crate::consts::{SuitText, SuitTextComponent};
+use crate::error::Error;
+
+/// Decode a text string, reporting invalid UTF-8 as [`Error::Utf8Error`].
+fn decode_str<'a>(decoder: &mut Decoder<'a>) -> Result<&'a str, Error> {
+    let position = decoder.position();
+    decoder.str().map_err(|e| {
+        match core::error::Error::source(&e).and_then(|s| s.downcast_ref::<Utf8Error>()) {
+            Some(_) => Error::Utf8Error { position },
+            None => e.into(),
+        }
+    })
+}
+
+/// Find the text string with the integer key in the text map, skipping unknown keys.
+fn find_text(cbor: &[u8], search_key: i32) -> Result<Option<&str>, Error> {
+    let mut decoder = Decoder::new(cbor);
+    let position = decoder.position();
+    let len = decoder
+        .map()?
+        .ok_or(Error::UnexpectedIndefiniteLength { position })?;
+    for _ in 0..len {
+        if matches!(
+            decoder.datatype()?,
+            Type::U8 | Type::U16 | Type::U32 | Type::I8 | Type::I16 | Type::I32
+        ) {
+            if decoder.i32()? == search_key {
+                return decode_str(&mut decoder).map(Some);
+            }
+        } else {
+            // Component identifiers and other keys
+            decoder.skip()?;
+        }
+        decoder.skip()?;
+    }
+    Ok(None)
+}
+
+/// SUIT text section.
+///
+/// Decodes the text map of the manifest.
+#[derive(Clone, Debug)]
+pub struct TextSection<'a> {
+    cbor: &'a [u8],
+}
+
+impl<'a> TextSection<'a> {
+    pub(crate) fn new(cbor: &'a [u8]) -> Self {
+        Self { cbor }
+    }
+
+    /// Create a text section from the CBOR-encoded text map.
+    pub fn from_bytes(bytes: &'a impl AsRef<[u8]>) -> Self {
+        Self::new(bytes.as_ref())
+    }
+
+    /// Free text description of the manifest.
+    pub fn manifest_description(&self) -> Result<Option<&'a str>, Error> {
+        find_text(self.cbor, SuitText::ManifestDescription.into())
+    }
+
+    /// Free text description of the update.
+    pub fn update_description(&self) -> Result<Option<&'a str>, Error> {
+        find_text(self.cbor, SuitText::UpdateDescription.into())
+    }
+
+    /// JSON source of the manifest.
+    pub fn json_source(&self) -> Result<Option<&'a str>, Error> {
+        find_text(self.cbor, SuitText::ManifestJsonSource.into())
+    }
+
+    /// YAML source of the manifest.
+    pub fn yaml_source(&self) -> Result<Option<&'a str>, Error> {
+        find_text(self.cbor, SuitText::ManifestYamlSource.into())
+    }
+
+    /// Iterate over the per-component text descriptions.
+    pub fn components(&self) -> Result<ComponentTextIter<'a>, Error> {
+        let mut decoder = Decoder::new(self.cbor);
+        let position = decoder.position();
+        let remaining = decoder
+            .map()?
+            .ok_or(Error::UnexpectedIndefiniteLength { position })?;
+        Ok(ComponentTextIter { decoder, remaining })
+    }
+}
+
+/// Text description of a single component.
+#[derive(Clone, Debug)]
+pub struct ComponentText<'a> {
+    component: Component<'a>,
+    cbor: &'a [u8],
+}
+
+impl<'a> ComponentText<'a> {
+    /// The component this text describes.
+    pub fn component(&self) -> Component<'a> {
+        self.component
+    }
+
+    /// Name of the vendor.
+    pub fn vendor_name(&self) -> Result<Option<&'a str>, Error> {
+        find_text(self.cbor, SuitTextComponent::VendorName.into())
+    }
+
+    /// Name of the model.
+    pub fn model_name(&self) -> Result<Option<&'a str>, Error> {
+        find_text(self.cbor, SuitTextComponent::ModelName.into())
+    }
+
+    /// Domain of the vendor.
+    pub fn vendor_domain(&self) -> Result<Option<&'a str>, Error> {
+        find_text(self.cbor, SuitTextComponent::VendorDomain.into())
+    }
+
+    /// Information about the model.
+    pub fn model_info(&self) -> Result<Option<&'a str>, Error> {
+        find_text(self.cbor, SuitTextComponent::ModelInfo.into())
+    }
+
+    /// Free text description of the component.
+    pub fn component_description(&self) -> Result<Option<&'a str>, Error> {
+        find_text(self.cbor, SuitTextComponent::ComponentDescription.into())
+    }
+
+    /// Version of the component.
+    pub fn component_version(&self) -> Result<Option<&'a str>, Error> {
+        find_text(self.cbor, SuitTextComponent::ComponentVersion.into())
+    }
+}
+
+/// Iterator over the component text descriptions in a text section.
+#[derive(Clone, Debug)]
+pub struct ComponentTextIter<'a> {
+    decoder: Decoder<'a>,
+    remaining: u64,
+}
+
+impl<'a> ComponentTextIter<'a> {
+    fn decode_component_text(&mut self) -> Result<Option<ComponentText<'a>>, Error> {
+        if self.decoder.datatype()? == Type::Array {
+            let component = self.decoder.decode::<Component>()?;
+            let position = self.decoder.position();
+            if self.decoder.datatype()? != Type::Map {
+                return Err(Error::UnexpectedCbor { position });
+            }
+            let cbor = self.decoder.sub_cbor()?;
+            Ok(Some(ComponentText { component, cbor }))
+        } else {
+            // Manifest level text
+            self.decoder.skip()?;
+            self.decoder.skip()?;
+            Ok(None)
+        }
+    }
+}
+
+impl<'a> Iterator for ComponentTextIter<'a> {
+    type Item = Result<ComponentText<'a>, Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while self.remaining > 0 {
+            self.remaining -= 1;
+            match self.decode_component_text() {
+                Ok(Some(text)) => return Some(Ok(text)),
+                Ok(None) => continue,
+                Err(e) => {
+                    self.remaining = 0;
+                    return Some(Err(e));
+                }
+            }
+        }
+        None
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod tests {
+    extern crate std;
+    use super::*;
+
+    #[test]
+    fn manifest_text() {
+        // {1: "a", 2: "b", 9: "c"}
+        let input = std::vec![0xA3, 0x01, 0x61, 0x61, 0x02, 0x61, 0x62, 0x09, 0x61, 0x63];
+        let text = TextSection::from_bytes(&input);
+        assert_eq!(text.manifest_description().unwrap(), Some("a"));
+        assert_eq!(text.update_description().unwrap(), Some("b"));
+        assert_eq!(text.json_source().unwrap(), None);
+        assert_eq!(text.components().unwrap().count(), 0);
+    }
+
+    #[test]
+    fn component_text() {
+        // {1: "a", [h'00']: {1: "v", 6: "1.0"}}
+        let input = std::vec![
+            0xA2, 0x01, 0x61, 0x61, 0x81, 0x41, 0x00, 0xA2, 0x01, 0x61, 0x76, 0x06, 0x63, 0x31,
+            0x2E, 0x30
+        ];
+        let text = TextSection::from_bytes(&input);
+        let mut components = text.components().unwrap();
+        let component = components.next().unwrap().unwrap();
+        assert_eq!(
+            component.component(),
+            Component::from_bytes(&[0x81, 0x41, 0x00])
+        );
+        assert_eq!(component.vendor_name().unwrap(), Some("v"));
+        assert_eq!(component.component_version().unwrap(), Some("1.0"));
+        assert_eq!(component.model_name().unwrap(), None);
+        assert!(components.next().is_none());
+    }
+
+    #[test]
+    fn invalid_utf8() {
+        // {1: "\xff"}
+        let input = std::vec![0xA1, 0x01, 0x61, 0xFF];
+        let text = TextSection::from_bytes(&input);
+        assert_eq!(
+            text.manifest_description(),
+            Err(Error::Utf8Error { position: 2 })
+        );
+    }
+}
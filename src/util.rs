@@ -0,0 +1,37 @@
+//! Allocation-free formatting helpers.
+use heapless::string::String;
+
+use crate::error::Error;
+
+/// Append the bytes as lowercase hex to the string.
+///
+/// Returns [`Error::CapacityError`] when the hex representation does not fit, the string is left
+/// unchanged in that case.
+pub fn hex<const N: usize>(bytes: &[u8], s: &mut String<N>) -> Result<(), Error> {
+    const DIGITS: &[u8; 16] = b"0123456789abcdef";
+    if s.capacity() - s.len() < bytes.len() * 2 {
+        return Err(Error::CapacityError);
+    }
+    for byte in bytes {
+        for digit in [byte >> 4, byte & 0x0F] {
+            s.push(char::from(DIGITS[usize::from(digit)]))
+                .map_err(|_| Error::CapacityError)?;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn hex_string() {
+        let mut s = String::<8>::new();
+        hex(&[0x00, 0xAB, 0x1F], &mut s).unwrap();
+        assert_eq!(s.as_str(), "00ab1f");
+        assert_eq!(hex(&[0x01, 0x02], &mut s), Err(Error::CapacityError));
+        assert_eq!(s.as_str(), "00ab1f");
+    }
+}